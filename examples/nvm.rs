@@ -1,13 +1,13 @@
 //! NVM Reset
 //!
 //! This demo is intended to run on a linux host (e.g. a Raspberry Pi) and shows how to read and
-//! write the NVM over I²C. It dumps the existing NVM and then writes the factory default - as
-//! generated by the [ST GUI application][gui] - so be careful.
+//! write the NVM over I²C. It dumps the existing NVM and then writes the factory default of the
+//! detected variant - as generated by the [ST GUI application][gui] - so be careful.
 //!
 //! [gui]: https://www.st.com/en/embedded-software/stsw-stusb002.html
 
 use linux_embedded_hal::I2cdev;
-use stusb4500::{nvm::DEFAULT_NVM_DATA, Address, STUSB4500};
+use stusb4500::prelude::*;
 
 const I2C_BUS: &str = "i2c-1";
 
@@ -16,6 +16,11 @@ fn main() {
         I2cdev::new(format!("/dev/{I2C_BUS}")).unwrap(),
         Address::Default,
     );
+    let variant = mcu.variant().unwrap();
+    let Some(image) = NvmImage::factory_default(variant) else {
+        println!("No factory data known for {:?}", variant);
+        return;
+    };
     let mut nvm = mcu.unlock_nvm().unwrap();
 
     let sectors = nvm.read_sectors().unwrap();
@@ -26,7 +31,7 @@ fn main() {
     });
 
     println!("Writing default NVM data...");
-    nvm.write_sectors(DEFAULT_NVM_DATA).unwrap();
+    nvm.write_sectors(image.0).unwrap();
 
     nvm.lock().unwrap();
 }
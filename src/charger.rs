@@ -0,0 +1,117 @@
+//! Battery charger input current limit following the active contract
+//!
+//! Implement [`InputCurrentLimit`] for the charger IC driver and call [`CurrentLimiter::update`]
+//! on each alert or periodically:
+//!
+//! ```ignore
+//! let mut limiter = CurrentLimiter::new(100);
+//! // ALERT or timer
+//! limiter.update(&mut stusb, &mut charger)?;
+//! ```
+
+use hal::delay::DelayNs;
+use hal::i2c::I2c;
+
+use crate::{Error, STUSB4500};
+
+/// Charger IC whose input current can be limited
+pub trait InputCurrentLimit {
+    type Error;
+
+    /// Draw at most `limit_ma` from VBUS, 0 if no source is attached
+    fn set_input_current_limit(&mut self, limit_ma: u32) -> Result<(), Self::Error>;
+}
+
+/// Error of [`CurrentLimiter::update`]
+#[derive(Debug)]
+pub enum LimitError<E, C> {
+    /// Reading the contract failed, the limit is unchanged
+    Device(Error<E>),
+    /// Setting the limit failed
+    Charger(C),
+}
+
+/// Passes the current available from the source on to an [`InputCurrentLimit`]
+///
+/// A lower limit is applied right away so the sink never draws more than granted. A higher one
+/// only once it exceeds the applied limit by the hysteresis, so a source alternating between
+/// close contracts doesn't keep reprogramming the charger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrentLimiter {
+    hysteresis_ma: u32,
+    applied: Option<u32>,
+}
+
+impl CurrentLimiter {
+    pub const fn new(hysteresis_ma: u32) -> Self {
+        CurrentLimiter {
+            hysteresis_ma,
+            applied: None,
+        }
+    }
+
+    /// Last limit set on the charger in mA, `None` before the first update
+    pub fn applied_ma(&self) -> Option<u32> {
+        self.applied
+    }
+
+    /// Set the limit for `available_ma`, returns the new limit if the charger was updated
+    pub fn apply<C: InputCurrentLimit>(
+        &mut self,
+        available_ma: u32,
+        charger: &mut C,
+    ) -> Result<Option<u32>, C::Error> {
+        let update = match self.applied {
+            None => true,
+            Some(applied) => {
+                available_ma < applied
+                    || available_ma >= applied.saturating_add(self.hysteresis_ma.max(1))
+            }
+        };
+        if !update {
+            return Ok(None);
+        }
+        event!(limit_ma = available_ma, "input current limit");
+        charger.set_input_current_limit(available_ma)?;
+        self.applied = Some(available_ma);
+        Ok(Some(available_ma))
+    }
+
+    /// Read the available current from `stusb` and [`apply`](Self::apply) it
+    pub fn update<I2C, E, DELAY, ALERT, C>(
+        &mut self,
+        stusb: &mut STUSB4500<I2C, DELAY, ALERT>,
+        charger: &mut C,
+    ) -> Result<Option<u32>, LimitError<E, C::Error>>
+    where
+        I2C: I2c<Error = E>,
+        DELAY: DelayNs,
+        C: InputCurrentLimit,
+    {
+        let available = stusb.available_current_ma().map_err(LimitError::Device)?;
+        self.apply(available, charger).map_err(LimitError::Charger)
+    }
+}
+
+impl<I2C, E, DELAY, ALERT> STUSB4500<I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    /// Current the sink may draw in mA
+    ///
+    /// The operating current of an explicit PD contract, otherwise the Type-C current advertised
+    /// by the source's Rp (500mA for default USB power), 0 if no source is attached.
+    pub fn available_current_ma(&mut self) -> Result<u32, Error<E>> {
+        if !self.is_attached()? {
+            return Ok(0);
+        }
+        let rdo = self.get_current_rdo()?;
+        if rdo.position() != 0 {
+            return Ok(rdo.operating_current_ma());
+        }
+        Ok(self
+            .typec_current()?
+            .map_or(0, |current| u32::from(current.current_ma())))
+    }
+}
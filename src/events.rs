@@ -0,0 +1,313 @@
+//! Alert events, from an interrupt handler or a superloop
+//!
+//! With the `heapless` feature the ALERT interrupt handler owns the driver (e.g. as an RTIC
+//! resource or behind a critical section mutex) and calls `EventProducer::service`, which
+//! reads and clears all status registers in a single burst and queues the decoded [`Event`]s.
+//! The main loop drains them with the `EventConsumer` iterator without touching the bus:
+//!
+//! ```ignore
+//! static mut QUEUE: Queue<Event, 8> = Queue::new();
+//! let (mut producer, mut consumer) = stusb4500::events::split(unsafe { &mut QUEUE });
+//!
+//! // ALERT ISR
+//! producer.service(&mut stusb)?;
+//!
+//! // Main loop
+//! while let Some(event) = consumer.next() { ... }
+//! ```
+//!
+//! Firmware without interrupts calls [`AlertService::service`] from its main loop instead. It
+//! reads the alerts at most once per interval and returns the events directly:
+//!
+//! ```ignore
+//! let mut alerts = AlertService::new(50);
+//!
+//! loop {
+//!     for event in alerts.service(&mut stusb, millis())? { ... }
+//!     // other work
+//! }
+//! ```
+
+use hal::delay::DelayNs;
+use hal::i2c::I2c;
+#[cfg(feature = "heapless")]
+use heapless::spsc::{Consumer, Producer, Queue};
+
+use crate::negotiate::{ContractChange, ContractWatcher};
+use crate::{
+    Alert, AlertSnapshot, ContractState, Error, Faults, MonitoringStatus, ProtocolStatus,
+    TypeCState, STUSB4500,
+};
+
+/// Decoded alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// A source was attached
+    Attached,
+    /// The source was detached
+    Detached,
+    /// VBUS monitoring status changed
+    Monitoring(MonitoringStatus),
+    /// Hardware fault status changed, empty once the faults cleared
+    Fault(Faults),
+    /// Type-C state machine changed
+    TypeC(TypeCState),
+    /// PD message, hard reset or BIST received
+    Protocol(ProtocolStatus),
+    /// The negotiated voltage or current changed, e.g. the source rebalanced its power budget
+    ContractChanged(ContractChange),
+}
+
+/// Events from one read of the alerts, oldest first
+///
+/// Holds at most one event of each kind, so it never overflows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Events {
+    events: [Option<Event>; 6],
+    next: usize,
+}
+
+impl Events {
+    /// Read and clear the alerts and decode an event for each unmasked one
+    ///
+    /// [`Event::Fault`] is decoded for CC fault alerts and for monitoring alerts while VBUS is
+    /// out of range. After attach, Type-C or protocol alerts while attached the
+    /// [`ContractState`] is read as well (up to 6 more transactions) and
+    /// [`Event::ContractChanged`] added last if `contract` saw a change.
+    fn read<I2C, E, DELAY, ALERT>(
+        stusb: &mut STUSB4500<I2C, DELAY, ALERT>,
+        contract: &mut ContractWatcher,
+    ) -> Result<(AlertSnapshot, Self), Error<E>>
+    where
+        I2C: I2c<Error = E>,
+        DELAY: DelayNs,
+    {
+        let snapshot = stusb.alert_snapshot()?;
+        let alerts = snapshot.alerts;
+        // VBUS faults are reported through the monitoring alert
+        let fault = alerts.contains(Alert::CCHWFaultStatus)
+            || (alerts.contains(Alert::TypeCMonitoringStatus)
+                && snapshot
+                    .faults
+                    .intersects(Faults::VbusOverVoltage | Faults::VbusUnderVoltage));
+        let decoded = [
+            (
+                alerts.contains(Alert::PortStatus),
+                if snapshot.attached {
+                    Event::Attached
+                } else {
+                    Event::Detached
+                },
+            ),
+            (
+                alerts.contains(Alert::TypeCMonitoringStatus),
+                Event::Monitoring(snapshot.monitoring),
+            ),
+            (fault, Event::Fault(snapshot.faults)),
+            (
+                alerts.contains(Alert::PDTypeCStatus),
+                Event::TypeC(snapshot.typec),
+            ),
+            (
+                alerts.contains(Alert::PRTStatus),
+                Event::Protocol(snapshot.protocol),
+            ),
+        ];
+
+        let mut events = Events::default();
+        for (_, event) in decoded.iter().filter(|(active, _)| *active) {
+            events.push(*event);
+        }
+        let contract_alerts = Alert::PortStatus | Alert::PDTypeCStatus | Alert::PRTStatus;
+        if alerts.intersects(contract_alerts) {
+            let state = if snapshot.attached {
+                stusb.attached_contract_state()?
+            } else {
+                ContractState::Detached
+            };
+            if let Some(change) = contract.observe(state) {
+                events.push(Event::ContractChanged(change));
+            }
+        }
+        Ok((snapshot, events))
+    }
+
+    fn push(&mut self, event: Event) {
+        if let Some(slot) = self.events.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(event);
+        }
+    }
+
+    /// Number of events not yet taken
+    pub fn len(&self) -> usize {
+        self.events
+            .iter()
+            .skip(self.next)
+            .filter(|event| event.is_some())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Iterator for Events {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        let event = self.events.get(self.next).copied().flatten()?;
+        self.next += 1;
+        Some(event)
+    }
+}
+
+/// Rate-limited alert servicing for superloop firmware, see the
+/// [module documentation](self)
+///
+/// Keeps the last [`AlertSnapshot`] for status queries between reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlertService {
+    interval_ms: u32,
+    last_ms: Option<u32>,
+    snapshot: Option<AlertSnapshot>,
+    contract: ContractWatcher,
+}
+
+impl AlertService {
+    /// Read the alerts at most every `interval_ms`
+    pub const fn new(interval_ms: u32) -> Self {
+        AlertService {
+            interval_ms,
+            last_ms: None,
+            snapshot: None,
+            contract: ContractWatcher::new(),
+        }
+    }
+
+    /// Read and clear the alerts if `interval_ms` passed since the last read, returning the new
+    /// events
+    ///
+    /// `now_ms` is any free running millisecond counter, e.g. a SysTick count, and may wrap.
+    /// Returns no events without accessing the bus while the interval hasn't passed.
+    pub fn service<I2C, E, DELAY, ALERT>(
+        &mut self,
+        stusb: &mut STUSB4500<I2C, DELAY, ALERT>,
+        now_ms: u32,
+    ) -> Result<Events, Error<E>>
+    where
+        I2C: I2c<Error = E>,
+        DELAY: DelayNs,
+    {
+        if let Some(last) = self.last_ms {
+            if now_ms.wrapping_sub(last) < self.interval_ms {
+                return Ok(Events::default());
+            }
+        }
+        let (snapshot, events) = Events::read(stusb, &mut self.contract)?;
+        self.last_ms = Some(now_ms);
+        self.snapshot = Some(snapshot);
+        Ok(events)
+    }
+
+    /// Status from the last read, `None` before the first one
+    pub fn snapshot(&self) -> Option<&AlertSnapshot> {
+        self.snapshot.as_ref()
+    }
+
+    /// Contract seen by the last read, see [`ContractWatcher::contract`]
+    pub fn contract(&self) -> Option<crate::negotiate::ContractValues> {
+        self.contract.contract()
+    }
+}
+
+/// Interrupt handler side, see the [module documentation](self)
+#[cfg(feature = "heapless")]
+pub struct EventProducer<'q, const N: usize> {
+    queue: Producer<'q, Event, N>,
+    dropped: u32,
+    contract: ContractWatcher,
+}
+
+/// Thread mode side, see the [module documentation](self)
+#[cfg(feature = "heapless")]
+pub struct EventConsumer<'q, const N: usize> {
+    queue: Consumer<'q, Event, N>,
+}
+
+/// Split `queue` into its producer and consumer halves
+///
+/// A `Queue<Event, N>` holds up to `N - 1` events.
+#[cfg(feature = "heapless")]
+pub fn split<const N: usize>(
+    queue: &mut Queue<Event, N>,
+) -> (EventProducer<'_, N>, EventConsumer<'_, N>) {
+    let (producer, consumer) = queue.split();
+    (
+        EventProducer {
+            queue: producer,
+            dropped: 0,
+            contract: ContractWatcher::new(),
+        },
+        EventConsumer { queue: consumer },
+    )
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> EventProducer<'_, N> {
+    /// Read and clear the alerts and queue an event for each unmasked one
+    ///
+    /// Returns the number of events queued. Events that don't fit are dropped and counted in
+    /// [`dropped`](Self::dropped).
+    ///
+    /// [`Event::Fault`] is queued for CC fault alerts and for monitoring alerts while VBUS is out
+    /// of range. After attach, Type-C or protocol alerts while attached the
+    /// [`ContractState`] is read as well (up to 6 more transactions) and
+    /// [`Event::ContractChanged`] queued last if the contract changed. A renegotiation in flight
+    /// is not reported until it completes.
+    pub fn service<I2C, E, DELAY, ALERT>(
+        &mut self,
+        stusb: &mut STUSB4500<I2C, DELAY, ALERT>,
+    ) -> Result<usize, Error<E>>
+    where
+        I2C: I2c<Error = E>,
+        DELAY: DelayNs,
+    {
+        let (_, events) = Events::read(stusb, &mut self.contract)?;
+        let mut queued = 0;
+        for event in events {
+            match self.queue.enqueue(event) {
+                Ok(()) => queued += 1,
+                Err(_) => self.dropped = self.dropped.saturating_add(1),
+            }
+        }
+        Ok(queued)
+    }
+
+    /// Events dropped because the queue was full
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> EventConsumer<'_, N> {
+    /// Number of queued events
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Yields the queued events, oldest first
+#[cfg(feature = "heapless")]
+impl<const N: usize> Iterator for EventConsumer<'_, N> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.queue.dequeue()
+    }
+}
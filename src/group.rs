@@ -0,0 +1,183 @@
+//! Several STUSB4500 on one I2C bus, e.g. a multi-port charger
+//!
+//! The ports share the retries, bus recovery, read-only mode and delay set on the group:
+//!
+//! ```ignore
+//! let mut group = Stusb4500Group::new(i2c, [Address::Strap(false, false), Address::Strap(false, true)])
+//!     .retries(3)
+//!     .delay(delay);
+//! group.for_each_status(|port, status| log::info!("port {}: {:?}", port, status));
+//! ```
+
+use hal::delay::DelayNs;
+use hal::i2c::I2c;
+
+use crate::{Address, Alert, Error, NoDelay, Rdo, STUSB4500};
+
+/// Status of a single port in a [`Stusb4500Group`]
+#[derive(Debug)]
+pub struct PortStatus {
+    pub alerts: Alert,
+    /// VBUS voltage in mV
+    pub voltage: u16,
+    pub rdo: Rdo,
+}
+
+impl PortStatus {
+    fn read<I2C, E, DELAY>(port: &mut STUSB4500<I2C, DELAY>) -> Result<Self, Error<E>>
+    where
+        I2C: I2c<Error = E>,
+        DELAY: DelayNs,
+    {
+        Ok(PortStatus {
+            alerts: port.get_alerts()?,
+            voltage: port.get_voltage_mv()?,
+            rdo: port.get_current_rdo()?,
+        })
+    }
+}
+
+/// Bus recovery of a port driver, which borrows the group's bus
+type PortRecovery<I2C> = for<'a, 'b> fn(&'b mut &'a mut I2C);
+
+/// Several STUSB4500 sharing a single I2C bus
+///
+/// The group owns the bus and a list of addresses, each port is accessed through a short-lived
+/// driver borrowing the bus and the group's delay.
+pub struct Stusb4500Group<I2C, const N: usize, DELAY = NoDelay> {
+    i2c: I2C,
+    addresses: [Address; N],
+    retries: u8,
+    recovery: Option<(u8, PortRecovery<I2C>)>,
+    read_only: bool,
+    delay: DELAY,
+}
+
+impl<I2C, const N: usize> Stusb4500Group<I2C, N> {
+    pub fn new(i2c: I2C, addresses: [Address; N]) -> Self {
+        Stusb4500Group {
+            i2c,
+            addresses,
+            retries: 0,
+            recovery: None,
+            read_only: false,
+            delay: NoDelay,
+        }
+    }
+}
+
+impl<I2C, const N: usize, DELAY> Stusb4500Group<I2C, N, DELAY> {
+    /// Release the bus
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Number of ports in the group
+    pub fn len(&self) -> usize {
+        N
+    }
+
+    pub fn is_empty(&self) -> bool {
+        N == 0
+    }
+
+    /// Retry failed I2C transactions, see [`Stusb4500Builder::retries`](crate::Stusb4500Builder::retries)
+    pub fn retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Recover the bus, see
+    /// [`Stusb4500Builder::bus_recovery`](crate::Stusb4500Builder::bus_recovery)
+    ///
+    /// `recover` gets the bus as borrowed by the port driver, e.g. `|bus| clock_out_scl(bus)`.
+    /// Failures are counted per port access.
+    pub fn bus_recovery(mut self, after: u8, recover: PortRecovery<I2C>) -> Self {
+        self.recovery = Some((after, recover));
+        self
+    }
+
+    /// Only allow status reads on every port, see
+    /// [`Stusb4500Builder::read_only`](crate::Stusb4500Builder::read_only)
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Delay for timed sequences and NVM polling of every port
+    pub fn delay<D>(self, delay: D) -> Stusb4500Group<I2C, N, D> {
+        Stusb4500Group {
+            i2c: self.i2c,
+            addresses: self.addresses,
+            retries: self.retries,
+            recovery: self.recovery,
+            read_only: self.read_only,
+            delay,
+        }
+    }
+}
+
+impl<I2C, E, const N: usize, DELAY> Stusb4500Group<I2C, N, DELAY>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    /// Driver for the port at `address` with the group's settings
+    fn driver(&mut self, address: Address) -> STUSB4500<&mut I2C, &mut DELAY> {
+        let mut builder = STUSB4500::builder(&mut self.i2c)
+            .address(address)
+            .retries(self.retries)
+            .delay(&mut self.delay);
+        if let Some((after, recover)) = self.recovery {
+            builder = builder.bus_recovery(after, recover);
+        }
+        if self.read_only {
+            builder = builder.read_only();
+        }
+        builder.build()
+    }
+
+    /// Get a driver for a single port
+    pub fn port(&mut self, index: usize) -> Option<STUSB4500<&mut I2C, &mut DELAY>> {
+        let address = *self.addresses.get(index)?;
+        Some(self.driver(address))
+    }
+
+    /// Call `f` with a driver for each port in turn
+    pub fn for_each_port<F>(&mut self, mut f: F)
+    where
+        F: FnMut(usize, &mut STUSB4500<&mut I2C, &mut DELAY>),
+    {
+        for (index, address) in self.addresses.into_iter().enumerate() {
+            f(index, &mut self.driver(address));
+        }
+    }
+
+    /// Read the status of each port and pass it to `f`
+    pub fn for_each_status<F>(&mut self, mut f: F)
+    where
+        F: FnMut(usize, Result<PortStatus, Error<E>>),
+    {
+        self.for_each_port(|index, port| f(index, PortStatus::read(port)))
+    }
+
+    /// Read and clear the alerts of every port
+    pub fn poll_alerts(&mut self) -> [Result<Alert, Error<E>>; N] {
+        self.addresses.map(|address| {
+            let mut port = self.driver(address);
+            let alerts = port.get_alerts()?;
+            port.clear_interrupts()?;
+            Ok(alerts)
+        })
+    }
+
+    /// Program the same NVM data into every port
+    ///
+    /// All ports are attempted even if one fails, the result of each port is returned.
+    pub fn write_sectors_all(&mut self, sectors: [[u8; 8]; 5]) -> [Result<(), Error<E>>; N] {
+        self.addresses.map(|address| {
+            let mut port = self.driver(address);
+            port.with_nvm(|nvm| nvm.write_sectors(sectors))
+        })
+    }
+}
@@ -1,29 +1,106 @@
 #![no_std]
+// Driver paths must not panic. Arithmetic is limited to bounded register fields and
+// saturating timeout counters.
+#![deny(
+    clippy::indexing_slicing,
+    clippy::unwrap_used,
+    clippy::expect_used,
+    clippy::panic,
+    clippy::unreachable,
+    clippy::todo,
+    clippy::unimplemented
+)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 extern crate embedded_hal as hal;
 
 use byteorder::{ByteOrder, LittleEndian};
+use hal::delay::DelayNs;
 use hal::i2c::I2c;
 
+/// Trace-level record of I2C transactions, enabled by the `log`, `defmt` and `tracing`
+/// features
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::trace!($($arg)*);
+        #[cfg(feature = "defmt")]
+        defmt::trace!($($arg)*);
+        #[cfg(feature = "tracing")]
+        tracing::trace!($($arg)*);
+    };
+}
+
+/// Update the [`stats::Stats`] counters, enabled by the `stats` feature
+macro_rules! stat {
+    ($self:ident.$record:ident($($arg:expr)?)) => {
+        #[cfg(feature = "stats")]
+        $self.stats.$record($($arg)?);
+    };
+}
+
+/// Debug-level tracing event for higher level steps, enabled by the `tracing` feature
+macro_rules! event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+// The data layer lives in `stusb4500-types`, re-exported under the old paths
+#[cfg(feature = "std")]
+pub use stusb4500_types::gui_conf;
+pub use stusb4500_types::{
+    config, image, pdo, rdo, registers, variant, InvalidPdoChannel, PdoChannel,
+};
+
+pub mod charger;
+pub mod events;
+pub mod group;
+pub mod message;
+pub mod monitoring;
+pub mod negotiate;
 pub mod nvm;
-pub mod pdo;
-pub mod rdo;
-pub mod registers;
+pub mod prelude;
+pub mod profile;
+#[cfg(feature = "std")]
+pub mod provision;
+pub mod runtime;
+pub mod source_pdo;
+pub mod split;
+#[cfg(feature = "stats")]
+pub mod stats;
+pub mod status;
+#[cfg(feature = "uom")]
+pub mod units;
 
-use nvm::STUSB4500Nvm;
+use config::{GpioCfg, NvmConfig};
+pub use group::Stusb4500Group;
+use image::NvmImage;
+use message::{ControlMessage, MessageHeader};
+use nvm::{NvmImageSource, NvmReport, STUSB4500Nvm, SourceError};
 use pdo::*;
 use rdo::*;
 use registers::*;
+use status::*;
+use variant::{DeviceInfo, SpecVersions, Variant};
 
 pub const STUSB4500_ADDR: u8 = 0x28;
 
 /// Address enum for STUSB4500
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Address {
     /// Default address with all address pins tied low
+    #[default]
     Default,
     /// Address determined by A1 and A0 pins. True = tied high, low = tied low.
     Strap(bool, bool),
-    /// Custom address from config file etc
+    /// Custom address from config file etc, e.g. behind an address-translating bridge.
+    /// Not validated, use `Address::try_from` for a checked address.
     Custom(u8),
 }
 
@@ -31,58 +108,827 @@ impl Address {
     pub(crate) fn addr(&self) -> u8 {
         match self {
             Address::Default => STUSB4500_ADDR,
-            Address::Strap(a1, a0) => STUSB4500_ADDR | (*a1 as u8) << 1 | (*a0 as u8) << 0,
+            Address::Strap(a1, a0) => STUSB4500_ADDR | (*a1 as u8) << 1 | *a0 as u8,
             Address::Custom(addr) => *addr,
         }
     }
 }
 
-impl Default for Address {
-    fn default() -> Self {
-        Address::Default
+/// Address outside of the 0x28-0x2B range selectable by the address pins
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidAddress(pub u8);
+
+impl TryFrom<u8> for Address {
+    type Error = InvalidAddress;
+
+    fn try_from(addr: u8) -> Result<Self, Self::Error> {
+        match addr {
+            STUSB4500_ADDR => Ok(Address::Default),
+            0x29..=0x2B => Ok(Address::Strap(addr & 0x2 != 0, addr & 0x1 != 0)),
+            _ => Err(InvalidAddress(addr)),
+        }
     }
 }
 
+/// Direction of a failed register access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
 #[derive(Debug)]
 pub enum Error<I2C> {
-    I2CError(I2C),
+    /// I2C transaction accessing `reg` failed
+    Register {
+        reg: Register,
+        kind: AccessKind,
+        source: I2C,
+    },
     InvalidPdo,
     OutaRangePdo,
+    /// Register access other than NVM programming while the NVM is unlocked. Lock the NVM (or
+    /// unlock and lock it again if the session was dropped) to recover.
+    NvmBusy,
+    /// A `wait_for_*` helper timed out
+    Timeout,
+    /// An NVM request did not complete
+    NvmTimeout,
+    /// Verifying the NVM sector failed and the previous image was programmed back, see
+    /// [`nvm::STUSB4500Nvm::update_sectors_atomic`]
+    RolledBack(u8),
+    /// The NVM didn't accept the password, it is protected and can't be read or programmed
+    NvmLocked,
+    /// I2C transaction accessing `reg` still failing after all retries, see
+    /// [`STUSB4500::with_retries`]
+    RetriesExhausted {
+        reg: Register,
+        kind: AccessKind,
+        source: I2C,
+    },
+    /// Waiting for the ALERT pin failed
+    AlertPin,
+    /// DEVICE_ID isn't a known STUSB4500 variant
+    UnknownDevice(u8),
+    /// NVM sector outside of 0-4
+    InvalidSector(u8),
+    /// Register write on a driver built with [`Stusb4500Builder::read_only`]
+    ReadOnly,
+    /// The source answered a command with Reject, Wait or Not_Supported, see
+    /// [`STUSB4500::send_command`]
+    CommandRejected(ControlMessage),
+    /// Message can't be sent by [`STUSB4500::send_control_message`]
+    UnsupportedMessage,
+    /// NVM sector read back different from what was written
+    VerifyFailed(u8),
+    /// The device stopped answering or reset (e.g. unplugged) while accessing this NVM sector
+    ///
+    /// When writing, the NVM content is unknown: unlock again and rewrite it, e.g. from a
+    /// backup. The driver locks the stale session on its next non-NVM access by itself.
+    InterruptedProgramming(u8),
+    /// The device answers but didn't report a valid DEVICE_ID in time, see
+    /// [`STUSB4500::wait_until_ready`]
+    NotReady(u8),
+}
+
+impl<I2C> Error<I2C> {
+    /// The underlying bus error, if any
+    pub fn i2c_error(&self) -> Option<&I2C> {
+        match self {
+            Error::Register { source, .. } | Error::RetriesExhausted { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+
+    /// Register being accessed when the bus error occurred
+    pub fn register(&self) -> Option<Register> {
+        match self {
+            Error::Register { reg, .. } | Error::RetriesExhausted { reg, .. } => Some(*reg),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
-pub enum PdoChannel {
-    PDO1,
-    PDO2,
-    PDO3,
+/// Like `Debug` without the bus error, which usually doesn't implement `uDebug`
+#[cfg(feature = "ufmt")]
+impl<I2C> ufmt::uDebug for Error<I2C> {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            Error::Register { reg, kind, .. } => f
+                .debug_struct("Register")?
+                .field("reg", reg)?
+                .field("kind", kind)?
+                .finish(),
+            Error::RetriesExhausted { reg, kind, .. } => f
+                .debug_struct("RetriesExhausted")?
+                .field("reg", reg)?
+                .field("kind", kind)?
+                .finish(),
+            Error::InvalidPdo => f.write_str("InvalidPdo"),
+            Error::OutaRangePdo => f.write_str("OutaRangePdo"),
+            Error::NvmBusy => f.write_str("NvmBusy"),
+            Error::ReadOnly => f.write_str("ReadOnly"),
+            Error::Timeout => f.write_str("Timeout"),
+            Error::NvmTimeout => f.write_str("NvmTimeout"),
+            Error::RolledBack(sector) => f.debug_tuple("RolledBack")?.field(sector)?.finish(),
+            Error::NvmLocked => f.write_str("NvmLocked"),
+            Error::AlertPin => f.write_str("AlertPin"),
+            Error::UnknownDevice(id) => f.debug_tuple("UnknownDevice")?.field(id)?.finish(),
+            Error::InvalidSector(sector) => f.debug_tuple("InvalidSector")?.field(sector)?.finish(),
+            Error::CommandRejected(reply) => {
+                f.debug_tuple("CommandRejected")?.field(reply)?.finish()
+            }
+            Error::UnsupportedMessage => f.write_str("UnsupportedMessage"),
+            Error::VerifyFailed(sector) => f.debug_tuple("VerifyFailed")?.field(sector)?.finish(),
+            Error::InterruptedProgramming(sector) => f
+                .debug_tuple("InterruptedProgramming")?
+                .field(sector)?
+                .finish(),
+            Error::NotReady(id) => f.debug_tuple("NotReady")?.field(id)?.finish(),
+        }
+    }
 }
 
-pub struct STUSB4500<I2C> {
+/// Options for [`STUSB4500::init`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InitOptions {
+    /// Alert mask to configure
+    pub alerts: AlertMask,
+    /// Compare the NVM content against this image
+    pub expected_nvm: Option<NvmImage>,
+}
+
+/// Result of [`STUSB4500::init`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitReport {
+    pub variant: Variant,
+    /// Whether the NVM matched [`InitOptions::expected_nvm`], `None` if not checked
+    pub nvm_matches: Option<bool>,
+}
+
+/// How often and how long the `wait_*` helpers poll, trading I2C bus load against latency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollPolicy {
+    /// Time between polls in ms, at least 1
+    pub interval_ms: u32,
+    /// Give up with [`Error::Timeout`] after this long in ms, the last poll happens at the
+    /// deadline
+    pub timeout_ms: u32,
+}
+
+impl PollPolicy {
+    pub const fn new(interval_ms: u32, timeout_ms: u32) -> Self {
+        PollPolicy {
+            interval_ms,
+            timeout_ms,
+        }
+    }
+
+    /// Default interval with `timeout_ms`
+    pub const fn timeout(timeout_ms: u32) -> Self {
+        PollPolicy {
+            interval_ms: 10,
+            timeout_ms,
+        }
+    }
+
+    fn start(self) -> Poll {
+        Poll {
+            policy: self,
+            elapsed: 0,
+        }
+    }
+}
+
+/// Poll every 10ms for up to 1s
+impl Default for PollPolicy {
+    fn default() -> Self {
+        PollPolicy::timeout(1000)
+    }
+}
+
+/// Time spent polling under a [`PollPolicy`]
+struct Poll {
+    policy: PollPolicy,
+    elapsed: u32,
+}
+
+impl Poll {
+    /// Time to wait before the next poll, `None` once the deadline passed
+    fn next_delay_ms(&mut self) -> Option<u32> {
+        let remaining = self.policy.timeout_ms.checked_sub(self.elapsed)?;
+        if remaining == 0 {
+            return None;
+        }
+        let delay = self.policy.interval_ms.clamp(1, remaining);
+        self.elapsed = self.elapsed.saturating_add(delay);
+        Some(delay)
+    }
+
+    /// Wait before the next poll, false once the deadline passed
+    fn wait<D: DelayNs>(&mut self, delay: &mut D) -> bool {
+        match self.next_delay_ms() {
+            Some(ms) => {
+                delay.delay_ms(ms);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// STUSB4500 driver
+///
+/// Holds no references or interior mutability of its own, so it is `Send`/`Sync` whenever `I2C`
+/// is and can be moved into an RTIC resource or an Embassy task. To share it between an ISR and
+/// a task, wrap it in a mutex like any other peripheral.
+///
+/// The optional `DELAY` is used by timed sequences such as [`STUSB4500::software_reset`] and to
+/// pace NVM request polling, see [`STUSB4500::new_with_delay`]. The optional `ALERT` pin enables
+/// [`STUSB4500::alert_pending`] and, with the `async` feature, `wait_for_alert`, see
+/// [`STUSB4500::builder`].
+pub struct STUSB4500<I2C, DELAY = NoDelay, ALERT = NoAlertPin> {
     i2c: I2C,
     address: u8,
+    retries: u8,
+    recovery: Option<BusRecovery<I2C>>,
+    /// Failed transactions since the last success or recovery
+    failures: u8,
+    read_only: bool,
+    nvm_unlocked: bool,
+    /// An NVM write was cut off, relock on the next non-NVM access
+    nvm_interrupted: bool,
+    delay: DELAY,
+    alert: ALERT,
+    #[cfg(feature = "stats")]
+    stats: stats::Stats,
+}
+
+/// Delay that returns immediately, used by drivers created with [`STUSB4500::new`]
+///
+/// Timed sequences then rely on the I2C transactions alone for timing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoDelay;
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// Bus recovery hook, see [`Stusb4500Builder::bus_recovery`]
+struct BusRecovery<I2C> {
+    after: u8,
+    recover: fn(&mut I2C),
+}
+
+impl<I2C> Clone for BusRecovery<I2C> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
-impl<I2C, E> STUSB4500<I2C>
+impl<I2C> Copy for BusRecovery<I2C> {}
+
+/// Placeholder for drivers without an ALERT pin
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoAlertPin;
+
+impl<I2C> STUSB4500<I2C> {
+    pub fn new(i2c: I2C, address: Address) -> Self {
+        STUSB4500::new_with_delay(i2c, address, NoDelay)
+    }
+
+    /// Configure the optional parts of the driver, e.g.
+    /// `STUSB4500::builder(i2c).address(Address::Custom(0x29)).retries(3).delay(delay).build()`
+    pub fn builder(i2c: I2C) -> Stusb4500Builder<I2C> {
+        Stusb4500Builder {
+            i2c,
+            address: Address::Default,
+            retries: 0,
+            recovery: None,
+            read_only: false,
+            delay: NoDelay,
+            alert: NoAlertPin,
+        }
+    }
+}
+
+impl<I2C, DELAY> STUSB4500<I2C, DELAY> {
+    /// Driver using `delay` for timed sequences and NVM polling
+    ///
+    /// `delay` is the driver's only time source: NVM and `wait_for_*` timeouts count the time
+    /// requested from it, not time spent on the bus. A delay that advances a simulated clock
+    /// instead of sleeping makes the timeouts testable on the host.
+    pub fn new_with_delay(i2c: I2C, address: Address, delay: DELAY) -> Self {
+        STUSB4500::builder(i2c)
+            .address(address)
+            .delay(delay)
+            .build()
+    }
+}
+
+/// Builder for [`STUSB4500`], see [`STUSB4500::builder`]
+pub struct Stusb4500Builder<I2C, DELAY = NoDelay, ALERT = NoAlertPin> {
+    i2c: I2C,
+    address: Address,
+    retries: u8,
+    recovery: Option<BusRecovery<I2C>>,
+    read_only: bool,
+    delay: DELAY,
+    alert: ALERT,
+}
+
+impl<I2C, DELAY, ALERT> Stusb4500Builder<I2C, DELAY, ALERT> {
+    /// I2C address, [`Address::Default`] if not set
+    pub fn address(mut self, address: Address) -> Self {
+        self.address = address;
+        self
+    }
+
+    /// Retry failed I2C transactions, see [`STUSB4500::with_retries`]
+    pub fn retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Call `recover` after `after` consecutive failed I2C transactions (at least 1), then retry
+    ///
+    /// Failures are counted across calls, including retries, and the count restarts after each
+    /// recovery. `recover` gets the bus to e.g. clock out a stuck slave on SCL or reset a bus
+    /// mux. The transaction that triggered it is retried once more on top of the
+    /// [`retries`](Self::retries).
+    pub fn bus_recovery(mut self, after: u8, recover: fn(&mut I2C)) -> Self {
+        self.recovery = Some(BusRecovery {
+            after: after.max(1),
+            recover,
+        });
+        self
+    }
+
+    /// Refuse every register write with [`Error::ReadOnly`], leaving only status reads
+    ///
+    /// The check sits below all driver methods, so NVM access (which needs writes even for
+    /// reading), PD commands and runtime settings all fail without touching the bus. It can't
+    /// be turned off on the built driver. Reading the clear-on-read alert registers still
+    /// clears them.
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Delay for timed sequences and NVM polling, see [`STUSB4500::new_with_delay`]
+    pub fn delay<D>(self, delay: D) -> Stusb4500Builder<I2C, D, ALERT> {
+        Stusb4500Builder {
+            i2c: self.i2c,
+            address: self.address,
+            retries: self.retries,
+            recovery: self.recovery,
+            read_only: self.read_only,
+            delay,
+            alert: self.alert,
+        }
+    }
+
+    /// ALERT pin (active low), used by [`STUSB4500::alert_pending`] and `wait_for_alert`
+    pub fn alert_pin<P>(self, pin: P) -> Stusb4500Builder<I2C, DELAY, P> {
+        Stusb4500Builder {
+            i2c: self.i2c,
+            address: self.address,
+            retries: self.retries,
+            recovery: self.recovery,
+            read_only: self.read_only,
+            delay: self.delay,
+            alert: pin,
+        }
+    }
+
+    pub fn build(self) -> STUSB4500<I2C, DELAY, ALERT> {
+        STUSB4500 {
+            i2c: self.i2c,
+            address: self.address.addr(),
+            retries: self.retries,
+            recovery: self.recovery,
+            failures: 0,
+            read_only: self.read_only,
+            nvm_unlocked: false,
+            nvm_interrupted: false,
+            delay: self.delay,
+            alert: self.alert,
+            #[cfg(feature = "stats")]
+            stats: stats::Stats::default(),
+        }
+    }
+}
+
+impl<I2C, E, DELAY, ALERT> STUSB4500<I2C, DELAY, ALERT>
 where
     I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+    ALERT: hal::digital::InputPin,
 {
-    pub fn new(i2c: I2C, address: Address) -> Self {
-        STUSB4500 {
-            i2c,
-            address: address.addr(),
+    /// Whether the ALERT pin is asserted, i.e. an unmasked alert is waiting in
+    /// [`get_alerts`](Self::get_alerts)
+    pub fn alert_pending(&mut self) -> Result<bool, Error<E>> {
+        self.alert.is_low().map_err(|_| Error::AlertPin)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<I2C, E, DELAY, ALERT> STUSB4500<I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+    ALERT: embedded_hal_async::digital::Wait,
+{
+    /// [`wait_for_event`](Self::wait_for_event) on the ALERT pin given to the builder
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub async fn wait_for_alert(&mut self) -> Result<Alert, Error<E>> {
+        self.alert
+            .wait_for_low()
+            .await
+            .map_err(|_| Error::AlertPin)?;
+        self.take_alerts()
+    }
+}
+
+impl<I2C, E, DELAY, ALERT> STUSB4500<I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    /// Retry failed I2C transactions up to `retries` times
+    ///
+    /// The STUSB4500 may NACK briefly around attach events. Once all retries failed the last
+    /// error is returned as [`Error::RetriesExhausted`].
+    pub fn with_retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Built with [`Stusb4500Builder::read_only`]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Startup sequence: verify the device ID, clear stale alerts, set the alert mask and
+    /// optionally check the NVM content
+    ///
+    /// Returns [`Error::UnknownDevice`] if DEVICE_ID isn't a known variant.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn init(&mut self, options: InitOptions) -> Result<InitReport, Error<E>> {
+        let variant = self.variant()?;
+        if let Variant::Unknown(id) = variant {
+            return Err(Error::UnknownDevice(id));
         }
+
+        self.clear_interrupts()?;
+        self.set_alerts_mask(options.alerts)?;
+
+        let nvm_matches = match options.expected_nvm {
+            Some(expected) => {
+                let sectors = self.with_nvm(|nvm| nvm.read_sectors())?;
+                Some(NvmImage(sectors) == expected)
+            }
+            None => None,
+        };
+
+        Ok(InitReport {
+            variant,
+            nvm_matches,
+        })
     }
 
     /// Read all interrupt registers to clear them
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub fn clear_interrupts(&mut self) -> Result<(), Error<E>> {
         // Read all interrupt registers
         let mut _buf = [0x00; 10];
-        self.i2c
-            .write(self.address, &[Register::PortStatus0 as u8])
-            .map_err(|err| Error::I2CError(err))?;
-        self.i2c
-            .read(self.address, &mut _buf)
-            .map_err(|err| Error::I2CError(err))
+        self.read_bytes(Register::PortStatus0, &mut _buf)
+    }
+
+    /// Chip variant, read from DEVICE_ID
+    ///
+    /// There is no register holding the silicon cut, the variant is the finest identification
+    /// available.
+    pub fn variant(&mut self) -> Result<Variant, Error<E>> {
+        Ok(Variant::from(self.read(Register::DeviceId)?))
+    }
+
+    /// Variant and supported Type-C and USB PD specification revisions
+    pub fn spec_versions(&mut self) -> Result<SpecVersions, Error<E>> {
+        let mut buf = [0x00; 4];
+        self.read_bytes(Register::BcdTypeCRevL, &mut buf)?;
+        Ok(SpecVersions {
+            variant: self.variant()?,
+            typec: u16::from_le_bytes([buf[0], buf[1]]),
+            usb_pd: u16::from_le_bytes([buf[2], buf[3]]),
+        })
+    }
+
+    /// Specification versions and the NVM identification sector in one call
+    ///
+    /// This unlocks the NVM to read sector 0 and locks it again.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn device_info(&mut self) -> Result<DeviceInfo, Error<E>> {
+        let spec_versions = self.spec_versions()?;
+        let nvm_id = self.with_nvm(|nvm| nvm.read_sector(0))?;
+        Ok(DeviceInfo {
+            spec_versions,
+            nvm_id,
+        })
+    }
+
+    /// GPIO pin function, read from the NVM
+    ///
+    /// The function is only loaded from NVM on power-up, so this unlocks the NVM to read sector 1
+    /// and locks it again.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn gpio_cfg(&mut self) -> Result<GpioCfg, Error<E>> {
+        let sector = self.with_nvm(|nvm| nvm.read_sector(1))?;
+        let mut image = NvmImage::DEFAULT;
+        image.0[1] = sector;
+        Ok(NvmConfig::new(image).gpio_cfg())
+    }
+
+    /// Drive the GPIO low or release it (Hi-Z), if configured as [`GpioCfg::SwCtrl`]
+    pub fn set_gpio_low(&mut self, low: bool) -> Result<(), Error<E>> {
+        let value = if low {
+            GpioSwGpio::Low
+        } else {
+            GpioSwGpio::empty()
+        };
+        self.write(Register::GpioSWGpio, value.bits())
+    }
+
+    /// Wait until the device answers with a known DEVICE_ID
+    ///
+    /// After a dead battery power-up the chip may NACK or read back defaults while the NVM is
+    /// loaded. If it never answered within the timeout the last I2C error is returned (not
+    /// present), if it answered with an unknown ID [`Error::NotReady`] is returned.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn wait_until_ready<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        policy: PollPolicy,
+    ) -> Result<Variant, Error<E>> {
+        let mut poll = policy.start();
+        loop {
+            let result = self.variant();
+            let not_ready = matches!(
+                result,
+                Ok(Variant::Unknown(_))
+                    | Err(Error::Register { .. } | Error::RetriesExhausted { .. })
+            );
+            if !not_ready || !poll.wait(delay) {
+                return match result {
+                    Ok(Variant::Unknown(id)) => Err(Error::NotReady(id)),
+                    result => result,
+                };
+            }
+        }
+    }
+
+    /// Is a source attached
+    pub fn is_attached(&mut self) -> Result<bool, Error<E>> {
+        let attached = PortStatus1::from_bits_truncate(self.read(Register::PortStatus1)?)
+            .contains(PortStatus1::Attach);
+        stat!(self.record_attach(attached));
+        Ok(attached)
+    }
+
+    /// Data and power role the port settled into, `None` if not attached
+    ///
+    /// The STUSB4500 doesn't support role swaps, so an attached port is normally a UFP sink.
+    pub fn device_mode(&mut self) -> Result<Option<DeviceMode>, Error<E>> {
+        let mode = DeviceMode::from_register(self.read(Register::PortStatus1)?);
+        stat!(self.record_attach(mode.is_some()));
+        Ok(mode)
+    }
+
+    /// Kind of partner attached, e.g. to tell a debug accessory from a charger
+    pub fn attached_device(&mut self) -> Result<AttachedDevice, Error<E>> {
+        Ok(AttachedDevice::from_register(
+            self.read(Register::PortStatus1)?,
+        ))
+    }
+
+    /// Wait until a source is attached
+    ///
+    /// The attach has to be stable for `debounce_ms` before returning. Returns
+    /// [`Error::Timeout`] if no stable attach was seen within the timeout.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn wait_for_attach<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        policy: PollPolicy,
+        debounce_ms: u32,
+    ) -> Result<(), Error<E>> {
+        let mut poll = policy.start();
+        let mut stable = 0u32;
+        loop {
+            let attached = self.is_attached()?;
+            if attached && stable >= debounce_ms {
+                return Ok(());
+            }
+            let Some(ms) = poll.next_delay_ms() else {
+                return Err(Error::Timeout);
+            };
+            delay.delay_ms(ms);
+            stable = if attached {
+                stable.saturating_add(ms)
+            } else {
+                0
+            };
+        }
+    }
+
+    /// Wait until VBUS is within `tolerance_mv` of `target_mv`
+    ///
+    /// Returns the measured voltage in mV, or [`Error::Timeout`] if VBUS didn't settle within
+    /// the timeout.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(target_mv))
+    )]
+    pub fn wait_for_vbus<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        target_mv: u16,
+        tolerance_mv: u16,
+        policy: PollPolicy,
+    ) -> Result<u16, Error<E>> {
+        self.poll_vbus(target_mv, tolerance_mv, policy, |_, poll| poll.wait(delay))
+    }
+
+    /// Same as [`STUSB4500::wait_for_vbus`] using the driver's delay, e.g. after
+    /// [`configure_sink`](Self::configure_sink) and before enabling the load
+    ///
+    /// The timeout only holds with a real delay, see [`Stusb4500Builder::delay`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(target_mv))
+    )]
+    pub fn wait_for_voltage(
+        &mut self,
+        target_mv: u16,
+        tolerance_mv: u16,
+        policy: PollPolicy,
+    ) -> Result<u16, Error<E>> {
+        self.poll_vbus(target_mv, tolerance_mv, policy, |this, poll| {
+            poll.wait(&mut this.delay)
+        })
+    }
+
+    /// Polling loop of the `wait_for_vbus` variants, `wait` sleeps until the next poll
+    fn poll_vbus(
+        &mut self,
+        target_mv: u16,
+        tolerance_mv: u16,
+        policy: PollPolicy,
+        mut wait: impl FnMut(&mut Self, &mut Poll) -> bool,
+    ) -> Result<u16, Error<E>> {
+        let mut poll = policy.start();
+        loop {
+            if let Some(voltage) = self.vbus_settled(target_mv, tolerance_mv)? {
+                event!(voltage, "VBUS settled");
+                return Ok(voltage);
+            }
+            if !wait(self, &mut poll) {
+                return Err(Error::Timeout);
+            }
+        }
+    }
+
+    fn vbus_settled(&mut self, target_mv: u16, tolerance_mv: u16) -> Result<Option<u16>, Error<E>> {
+        let voltage = self.get_voltage_mv()?;
+        if voltage.abs_diff(target_mv) <= tolerance_mv && self.monitoring_status()?.vbus_valid {
+            Ok(Some(voltage))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Wait until an explicit PD contract is established
+    ///
+    /// A contract is established once the policy engine is in SNK_READY and the RDO is valid.
+    /// Returns [`Error::Timeout`] if no contract was established within the timeout.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn wait_for_contract<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        policy: PollPolicy,
+    ) -> Result<Contract, Error<E>> {
+        let mut poll = policy.start();
+        loop {
+            if let Some(contract) = self.ready_contract()? {
+                return Ok(contract);
+            }
+            if !poll.wait(delay) {
+                return Err(Error::Timeout);
+            }
+        }
+    }
+
+    /// Same as [`STUSB4500::wait_for_contract`] with an async delay, the I2C accesses are still
+    /// blocking
+    #[cfg(feature = "async")]
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub async fn wait_for_contract_async<D: embedded_hal_async::delay::DelayNs>(
+        &mut self,
+        delay: &mut D,
+        policy: PollPolicy,
+    ) -> Result<Contract, Error<E>> {
+        let mut poll = policy.start();
+        loop {
+            if let Some(contract) = self.ready_contract()? {
+                return Ok(contract);
+            }
+            let Some(ms) = poll.next_delay_ms() else {
+                return Err(Error::Timeout);
+            };
+            delay.delay_ms(ms).await;
+        }
+    }
+
+    fn ready_contract(&mut self) -> Result<Option<Contract>, Error<E>> {
+        if !matches!(
+            self.pe_state()?,
+            PeState::SnkReady | PeState::SnkReadySending
+        ) {
+            return Ok(None);
+        }
+        let rdo = self.get_current_rdo()?;
+        if rdo.position() == 0 {
+            return Ok(None);
+        }
+        Ok(Some(Contract {
+            rdo,
+            voltage_mv: self.get_voltage_mv()?,
+        }))
+    }
+
+    /// Power delivery state, consistent while a contract is renegotiated
+    ///
+    /// The RDO and VBUS are only read, and a contract only reported, once the policy engine
+    /// settled. Between a soft reset or new source capabilities and SNK_READY this returns
+    /// [`ContractState::Negotiating`] instead of a mix of the old and new contract, which
+    /// [`get_active_contract`](Self::get_active_contract) may return.
+    pub fn contract_state(&mut self) -> Result<ContractState, Error<E>> {
+        if !self.is_attached()? {
+            return Ok(ContractState::Detached);
+        }
+        self.attached_contract_state()
+    }
+
+    /// [`contract_state`](Self::contract_state) when known to be attached, e.g. from an
+    /// [`AlertSnapshot`]
+    pub(crate) fn attached_contract_state(&mut self) -> Result<ContractState, Error<E>> {
+        let pe = self.pe_state()?;
+        if pe.is_negotiating() {
+            return Ok(ContractState::Negotiating);
+        }
+        let rdo = self.get_current_rdo()?;
+        let voltage_mv = if rdo.position() != 0 {
+            self.get_voltage_mv()?
+        } else {
+            0
+        };
+        Ok(ContractState::decode(true, pe, rdo, voltage_mv))
+    }
+
+    /// Current advertised by the source's Rp, `None` if not attached
+    ///
+    /// Without a PD contract this is the current the sink may draw at 5V.
+    pub fn typec_current(&mut self) -> Result<Option<TypeCCurrent>, Error<E>> {
+        Ok(TypeCCurrent::from_cc_status(self.read(Register::CCStatus)?))
+    }
+
+    /// VBUS monitoring status
+    pub fn monitoring_status(&mut self) -> Result<MonitoringStatus, Error<E>> {
+        let mut buf = [0x00; 2];
+        self.read_bytes(Register::TypeCMonitoringStatus0, &mut buf)?;
+        Ok(MonitoringStatus::from_registers(buf[0], buf[1]))
+    }
+
+    /// Active hardware faults
+    ///
+    /// Reads PORT_STATUS_1 through CC_HW_FAULT_STATUS_1 in one burst. This clears the
+    /// transition bits of the monitoring and fault status registers like
+    /// [`monitoring_status`](Self::monitoring_status) does. Unmask
+    /// [`AlertMask::CCFaultStatus`] to get an alert on CC faults.
+    pub fn faults(&mut self) -> Result<Faults, Error<E>> {
+        let mut buf = [0x00; 6];
+        self.read_bytes(Register::PortStatus1, &mut buf)?;
+        let [port1, mon0, _mon1, _cc, _fault0, fault1] = buf;
+        let faults = Faults::from_registers(port1, mon0, fault1);
+        if !faults.is_empty() {
+            event!(faults = faults.bits(), "faults");
+        }
+        Ok(faults)
     }
 
     /// Set interrupt mask
@@ -90,31 +936,182 @@ where
         self.write(Register::AlertStatus1Mask, alerts.bits())
     }
 
+    /// Currently programmed interrupt mask, reserved bits are kept as read
+    pub fn get_alerts_mask(&mut self) -> Result<AlertMask, Error<E>> {
+        Ok(AlertMask::from_bits_retain(
+            self.read(Register::AlertStatus1Mask)?,
+        ))
+    }
+
     /// Get active interrupt flags
     pub fn get_alerts(&mut self) -> Result<Alert, Error<E>> {
-        Ok(Alert::from_bits_truncate(
-            self.read(Register::AlertStatus1)?,
-        ))
+        let alerts = Alert::from_bits_truncate(self.read(Register::AlertStatus1)?);
+        event!(alerts = alerts.bits(), "alerts");
+        Ok(alerts)
+    }
+
+    /// Read and clear all alert and status registers in one burst, keeping the raw bytes
+    ///
+    /// Like [`get_alerts`](Self::get_alerts), but also returns the status registers the alerts
+    /// refer to, so nothing changes between reading an alert and its cause.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn alert_snapshot(&mut self) -> Result<AlertSnapshot, Error<E>> {
+        // Reading clears the interrupt registers
+        let mut raw = [0x00; 12];
+        self.read_bytes(Register::AlertStatus1, &mut raw)?;
+        let snapshot = AlertSnapshot::from_registers(raw);
+        event!(alerts = snapshot.alerts.bits(), "alerts");
+        stat!(self.record_attach(snapshot.attached));
+        stat!(self.record_hard_reset(snapshot.protocol.hard_reset_received));
+        Ok(snapshot)
+    }
+
+    /// Wait for the ALERT pin (active low) and return the alerts that caused it
+    ///
+    /// The interrupt registers are cleared afterwards so ALERT is released. Only the wait is
+    /// asynchronous, the I2C accesses are still blocking.
+    #[cfg(feature = "async")]
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub async fn wait_for_event<P>(&mut self, alert: &mut P) -> Result<Alert, Error<E>>
+    where
+        P: embedded_hal_async::digital::Wait,
+    {
+        alert.wait_for_low().await.map_err(|_| Error::AlertPin)?;
+        self.take_alerts()
+    }
+
+    /// Read the pending alerts and clear the interrupt registers so ALERT is released
+    #[cfg(feature = "async")]
+    fn take_alerts(&mut self) -> Result<Alert, Error<E>> {
+        let alerts = self.get_alerts()?;
+        self.clear_interrupts()?;
+        Ok(alerts)
+    }
+
+    /// Protocol layer status
+    pub fn prt_status(&mut self) -> Result<ProtocolStatus, Error<E>> {
+        let status = ProtocolStatus::from_register(self.read(Register::PRTStatus)?);
+        stat!(self.record_hard_reset(status.hard_reset_received));
+        Ok(status)
+    }
+
+    /// Policy engine state
+    pub fn pe_state(&mut self) -> Result<PeState, Error<E>> {
+        Ok(PeState::from(self.read(Register::PEFSM)?))
+    }
+
+    /// Type-C connection state
+    ///
+    /// Unlike [`pe_state`](Self::pe_state) this tells a source still being debounced
+    /// ([`TypeCState::AttachWaitSnk`]) apart from an attached one waiting for PD.
+    pub fn typec_state(&mut self) -> Result<TypeCState, Error<E>> {
+        Ok(TypeCState::from(self.read(Register::TypeCStatus)?))
+    }
+
+    /// Enable or disable the internal VBUS discharge on detach and voltage transitions
+    ///
+    /// This is a runtime setting and reverts on reset. The NVM only stores the discharge times,
+    /// see [`config::NvmConfig::set_discharge_time_to_0v_ms`].
+    pub fn set_discharge_on_disconnect(&mut self, enable: bool) -> Result<(), Error<E>> {
+        let mut ctrl = VbusDischargeCtrl::from_bits_retain(self.read(Register::VbusDischargeCtrl)?);
+        ctrl.set(
+            VbusDischargeCtrl::Discharge0V | VbusDischargeCtrl::DischargeTransition,
+            enable,
+        );
+        self.write(Register::VbusDischargeCtrl, ctrl.bits())
+    }
+
+    /// Whether the internal VBUS discharge on detach is enabled
+    pub fn discharge_on_disconnect(&mut self) -> Result<bool, Error<E>> {
+        Ok(
+            VbusDischargeCtrl::from_bits_truncate(self.read(Register::VbusDischargeCtrl)?)
+                .contains(VbusDischargeCtrl::Discharge0V),
+        )
+    }
+
+    /// VBUS_CTRL, reserved bits are kept as read
+    ///
+    /// Expert-level: the chip manages the power path on its own, see
+    /// [`STUSB4500::set_sink_vbus_enable`].
+    pub fn vbus_ctrl(&mut self) -> Result<VbusCtrl, Error<E>> {
+        Ok(VbusCtrl::from_bits_retain(self.read(Register::VbusCtrl)?))
+    }
+
+    /// Write VBUS_CTRL, see [`STUSB4500::vbus_ctrl`]
+    pub fn set_vbus_ctrl(&mut self, ctrl: VbusCtrl) -> Result<(), Error<E>> {
+        self.write(Register::VbusCtrl, ctrl.bits())
+    }
+
+    /// Force the sink power path (VBUS_EN_SNK) on or off
+    ///
+    /// Expert-level: this overrides the power path control of the policy engine, so the load
+    /// may be connected without a valid contract or disconnected while one is active. This is a
+    /// runtime setting and reverts on reset. Only use it for power path experiments.
+    pub fn set_sink_vbus_enable(&mut self, enable: bool) -> Result<(), Error<E>> {
+        let mut ctrl = self.vbus_ctrl()?;
+        ctrl.set(VbusCtrl::SinkVbusEnable, enable);
+        self.set_vbus_ctrl(ctrl)
+    }
+
+    /// Header of the last received PD message
+    pub fn rx_header(&mut self) -> Result<MessageHeader, Error<E>> {
+        let mut buf = [0x00; 2];
+        self.read_bytes(Register::RXHeaderL, &mut buf)?;
+        Ok(MessageHeader::from_bits(u16::from_le_bytes(buf)))
     }
 
     /// Perform a soft reset
     /// Triggers re-negotiation of PDO's.
+    ///
+    /// Doesn't wait for the source, use [`send_command`](Self::send_command) to check that it
+    /// accepted.
     pub fn soft_reset(&mut self) -> Result<(), Error<E>> {
-        self.write(Register::TXHeaderL, 0x0D)?;
+        self.send_control_message(ControlMessage::SoftReset)
+    }
+
+    /// Reset the chip through RESET_CTRL, holding the reset for 25ms with the stored delay
+    ///
+    /// Unlike [`STUSB4500::soft_reset`] this restarts the Type-C and PD state machines, so the
+    /// sink detaches and attaches again. With [`NoDelay`] the reset is only held for the time
+    /// of the next I2C transaction.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn software_reset(&mut self) -> Result<(), Error<E>> {
+        const SW_RESET_EN: u8 = 0x01;
+
+        self.write(Register::ResetCtrl, SW_RESET_EN)?;
+        self.delay.delay_ms(25);
+        self.write(Register::ResetCtrl, 0x00)
+    }
+
+    /// Send a PD control message
+    ///
+    /// Only messages the sink can send without further handling by the driver are allowed:
+    /// SoftReset, GetSourceCap, GetSourceCapExtended, GetSinkCap and GetStatus. Others return
+    /// [`Error::UnsupportedMessage`]. Returns once the message is queued, see
+    /// [`send_command`](Self::send_command) to wait for the reply.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(message = ?message)))]
+    pub fn send_control_message(&mut self, message: ControlMessage) -> Result<(), Error<E>> {
+        match message {
+            ControlMessage::SoftReset
+            | ControlMessage::GetSourceCap
+            | ControlMessage::GetSourceCapExtended
+            | ControlMessage::GetSinkCap
+            | ControlMessage::GetStatus => {}
+            _ => return Err(Error::UnsupportedMessage),
+        }
+        self.write(Register::TXHeaderL, u8::from(message))?;
+        // Send the message in TX_HEADER
         self.write(Register::PDCommandCtrl, 0x26)?;
+        if message == ControlMessage::SoftReset {
+            stat!(self.record_soft_reset());
+        }
         Ok(())
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(pdo = ?pdo)))]
     pub fn set_pdo(&mut self, pdo: PdoChannel, data: &Pdo) -> Result<(), Error<E>> {
         if let Pdo::Fixed { .. } = data {
-            self.write_word(
-                match pdo {
-                    PdoChannel::PDO1 => Register::DPMSNKPDO1,
-                    PdoChannel::PDO2 => Register::DPMSNKPDO2,
-                    PdoChannel::PDO3 => Register::DPMSNKPDO3,
-                },
-                data.bits(),
-            )
+            self.write_word(pdo.register(), data.bits())
         } else {
             // Can only advertise fixed PDOs
             Err(Error::InvalidPdo)
@@ -122,21 +1119,106 @@ where
     }
 
     pub fn get_pdo(&mut self, pdo: PdoChannel) -> Result<Pdo, Error<E>> {
-        Pdo::from_bits(self.read_word(match pdo {
-            PdoChannel::PDO1 => Register::DPMSNKPDO1,
-            PdoChannel::PDO2 => Register::DPMSNKPDO2,
-            PdoChannel::PDO3 => Register::DPMSNKPDO3,
-        })?)
-        .ok_or(Error::InvalidPdo)
+        Pdo::from_bits(self.read_word(pdo.register())?).ok_or(Error::InvalidPdo)
     }
 
+    /// VBUS voltage in V, see [`STUSB4500::get_voltage_mv`] for targets without an FPU
+    #[cfg(feature = "float")]
     pub fn get_voltage(&mut self) -> Result<f64, Error<E>> {
         let volts = self.read(Register::MonitoringCtrl1)?;
         Ok((volts as f64) / 10.0)
     }
 
+    /// VBUS voltage in mV
+    pub fn get_voltage_mv(&mut self) -> Result<u16, Error<E>> {
+        // Register is in 100mV units
+        Ok(status::vbus_mv(self.read(Register::MonitoringCtrl1)?))
+    }
+
     pub fn get_current_rdo(&mut self) -> Result<Rdo, Error<E>> {
-        Ok(Rdo(self.read_word(Register::RDORegStatus)?))
+        let rdo = Rdo(self.read_word(Register::RDORegStatus)?);
+        stat!(self.record_contract(rdo.position() != 0));
+        Ok(rdo)
+    }
+
+    /// Power of the active contract in mW, using the operating current. `None` without a
+    /// contract.
+    pub fn get_negotiated_power(&mut self) -> Result<Option<u32>, Error<E>> {
+        self.negotiated_power(|rdo| rdo.operating_current())
+    }
+
+    /// Power of the active contract in mW, using the max operating current. `None` without a
+    /// contract.
+    pub fn get_negotiated_max_power(&mut self) -> Result<Option<u32>, Error<E>> {
+        self.negotiated_power(|rdo| rdo.max_operating_current())
+    }
+
+    /// Granted VBUS voltage in mV and operating current in mA, `None` without a contract
+    ///
+    /// Reads only the RDO and VBUS registers, for polling loops that don't need the full
+    /// status. The voltage is the measured VBUS.
+    ///
+    /// During a renegotiation the RDO and VBUS may belong to different contracts, see
+    /// [`contract_state`](Self::contract_state).
+    pub fn get_active_contract(&mut self) -> Result<Option<(u16, u32)>, Error<E>> {
+        let rdo = self.get_current_rdo()?;
+        if rdo.position() == 0 {
+            return Ok(None);
+        }
+        Ok(Some((self.get_voltage_mv()?, rdo.operating_current_ma())))
+    }
+
+    fn negotiated_power<F>(&mut self, current: F) -> Result<Option<u32>, Error<E>>
+    where
+        F: FnOnce(&Rdo) -> u32,
+    {
+        let rdo = self.get_current_rdo()?;
+        if rdo.position() == 0 {
+            return Ok(None);
+        }
+        // mV * 10mA = 10uW
+        let voltage = self.get_voltage_mv()? as u32;
+        Ok(Some(voltage * current(&rdo) / 100))
+    }
+
+    /// Check the active contract against the sink PDOs
+    ///
+    /// Returns `None` if the contract was granted without a capability mismatch and VBUS is
+    /// within 5% of the highest priority (last active) sink PDO.
+    pub fn contract_mismatch(&mut self) -> Result<Option<ContractMismatch>, Error<E>> {
+        let rdo = self.get_current_rdo()?;
+        if rdo.position() == 0 {
+            return Ok(Some(ContractMismatch::NoContract));
+        }
+        if rdo.capability_mismatch() {
+            return Ok(Some(ContractMismatch::CapabilityMismatch));
+        }
+
+        let num = self.get_num_pdo()?.clamp(1, 3);
+        let channel = match num {
+            1 => PdoChannel::PDO1,
+            2 => PdoChannel::PDO2,
+            _ => PdoChannel::PDO3,
+        };
+        let requested = match self.get_pdo(channel)? {
+            Pdo::Fixed(pdo) => pdo.voltage() as u16 * 50,
+            _ => return Err(Error::InvalidPdo),
+        };
+        let actual = self.get_voltage_mv()?;
+        if actual.abs_diff(requested) > requested / 20 {
+            return Ok(Some(ContractMismatch::Voltage { requested, actual }));
+        }
+        Ok(None)
+    }
+
+    /// Whether the active contract matches the request, see [`STUSB4500::contract_mismatch`]
+    pub fn contract_matches_request(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.contract_mismatch()?.is_none())
+    }
+
+    /// Number of active sink PDOs
+    pub fn get_num_pdo(&mut self) -> Result<u8, Error<E>> {
+        Ok(status::num_pdo(self.read(Register::DPMPDONumb)?))
     }
 
     pub fn set_num_pdo(&mut self, num: u8) -> Result<(), Error<E>> {
@@ -146,20 +1228,119 @@ where
         }
     }
 
+    /// Read all single byte registers, see [`Register::BYTE_REGISTERS`]
+    ///
+    /// Reading the status registers clears their latched transition bits, like
+    /// [`STUSB4500::clear_interrupts`].
+    #[cfg(feature = "alloc")]
+    pub fn read_all_registers(&mut self) -> Result<alloc::vec::Vec<(Register, u8)>, Error<E>> {
+        Register::BYTE_REGISTERS
+            .iter()
+            .map(|register| Ok((*register, self.read(*register)?)))
+            .collect()
+    }
+
     /// Unlock the NVM for reading and writing
-    pub fn unlock_nvm(&mut self) -> Result<STUSB4500Nvm<I2C>, Error<E>> {
+    ///
+    /// Returns [`Error::NvmLocked`] if the NVM controller doesn't power up after the password was
+    /// written, e.g. on parts protected during provisioning.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn unlock_nvm(&mut self) -> Result<STUSB4500Nvm<'_, I2C, DELAY, ALERT>, Error<E>> {
         STUSB4500Nvm::unlock(self)
     }
 
+    /// Run `f` in an NVM session which is locked again on every exit path
+    ///
+    /// An error from `f` takes precedence over one from locking. If locking fails too the driver
+    /// stays in [`Error::NvmBusy`] until [`lock_nvm`](Self::lock_nvm) succeeds.
+    pub(crate) fn with_nvm<T>(
+        &mut self,
+        f: impl FnOnce(&mut STUSB4500Nvm<'_, I2C, DELAY, ALERT>) -> Result<T, Error<E>>,
+    ) -> Result<T, Error<E>> {
+        let mut nvm = self.unlock_nvm()?;
+        let result = f(&mut nvm);
+        let locked = nvm.lock();
+        let value = result?;
+        locked?;
+        Ok(value)
+    }
+
+    /// Read the NVM and return its [`NvmImage::fingerprint`]
+    pub fn nvm_fingerprint(&mut self) -> Result<u32, Error<E>> {
+        let sectors = self.with_nvm(|nvm| nvm.read_sectors())?;
+        Ok(NvmImage(sectors).fingerprint())
+    }
+
+    /// Program `sectors` unless the NVM already holds them
+    ///
+    /// Only changed sectors are erased and written, and verified afterwards. An up to date NVM is
+    /// left alone, so this is cheap to call on every boot. The NVM is locked again also if
+    /// programming or verification fails.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn write_sectors_if_changed(
+        &mut self,
+        sectors: [[u8; 8]; 5],
+    ) -> Result<NvmReport, Error<E>> {
+        self.with_nvm(|nvm| nvm.write_changed_sectors(sectors, true))
+    }
+
+    /// Load the target image from `source` and [`write_sectors_if_changed`](Self::write_sectors_if_changed)
+    ///
+    /// Returns `None` without accessing the device if the source holds no image.
+    pub fn write_image_from<S: NvmImageSource>(
+        &mut self,
+        source: &mut S,
+    ) -> Result<Option<NvmReport>, SourceError<E, S::Error>> {
+        let Some(image) = source.load_image().map_err(SourceError::Source)? else {
+            return Ok(None);
+        };
+        Ok(Some(self.write_sectors_if_changed(image.0)?))
+    }
+
+    /// Lock the NVM, also if it was left unlocked by a dropped [`STUSB4500Nvm`] or another
+    /// program
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn lock_nvm(&mut self) -> Result<(), Error<E>> {
+        self.write(Register::NvmCtrl0, NvmCtrl0::Enable.bits())?;
+        self.write(Register::NvmCtrl1, 0x00)?;
+        self.write(Register::NvmPassword, 0x00)?;
+        self.nvm_unlocked = false;
+        self.nvm_interrupted = false;
+        Ok(())
+    }
+
+    /// Whether the NVM is currently unlocked on the chip
+    pub fn nvm_is_unlocked(&mut self) -> Result<bool, Error<E>> {
+        let password = self.read(Register::NvmPassword)?;
+        let ctrl = NvmCtrl0::from_bits_truncate(self.read(Register::NvmCtrl0)?);
+        Ok(
+            password == STUSB4500Nvm::<I2C, DELAY, ALERT>::DEFAULT_PASSWORD
+                && ctrl.contains(NvmCtrl0::Power),
+        )
+    }
+
     // *****************************************************************
     // Raw access functions
 
+    /// Only NVM registers may be accessed while the NVM is unlocked
+    ///
+    /// After [`Error::InterruptedProgramming`] the session is locked first instead, once the
+    /// device answers again.
+    fn check_access(&mut self, register: Register) -> Result<(), Error<E>> {
+        if self.nvm_unlocked && !register.is_nvm() {
+            if !self.nvm_interrupted {
+                return Err(Error::NvmBusy);
+            }
+            event!("relock interrupted NVM session");
+            self.lock_nvm()?;
+        }
+        Ok(())
+    }
+
     /// Write a byte register
     pub(crate) fn write(&mut self, register: Register, value: u8) -> Result<(), Error<E>> {
-        let buf = [register as u8, value];
-        self.i2c
-            .write(self.address, &buf)
-            .map_err(|err| Error::I2CError(err))
+        trace!("write {:?} = {:#x}", register, value);
+        self.write_buf(register, &[register as u8, value])
     }
 
     /// Write a word register
@@ -167,32 +1348,88 @@ where
         let mut buf = [0x00; 5];
         buf[0] = register as u8;
         LittleEndian::write_u32(&mut buf[1..], word);
-        self.i2c
-            .write(self.address, &buf)
-            .map_err(|err| Error::I2CError(err))
+        trace!("write {:?} = {:#x}", register, word);
+        self.write_buf(register, &buf)
+    }
+
+    /// Write `buf` starting with the address of `register`
+    pub(crate) fn write_buf(&mut self, register: Register, buf: &[u8]) -> Result<(), Error<E>> {
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+        self.check_access(register)?;
+        let address = self.address;
+        self.retry(register, AccessKind::Write, |i2c| i2c.write(address, buf))
     }
 
     /// Read a byte register
     pub(crate) fn read(&mut self, register: Register) -> Result<u8, Error<E>> {
         let mut buf = [0x00; 1];
-        self.i2c
-            .write(self.address, &[register as u8])
-            .map_err(|err| Error::I2CError(err))?;
-        self.i2c
-            .read(self.address, &mut buf)
-            .map_err(|err| Error::I2CError(err))?;
+        self.read_bytes(register, &mut buf)?;
         Ok(buf[0])
     }
 
     /// Read a word register
     pub(crate) fn read_word(&mut self, register: Register) -> Result<u32, Error<E>> {
         let mut buf = [0x00; 4];
-        self.i2c
-            .write(self.address, &[register as u8])
-            .map_err(|err| Error::I2CError(err))?;
-        self.i2c
-            .read(self.address, &mut buf)
-            .map_err(|err| Error::I2CError(err))?;
+        self.read_bytes(register, &mut buf)?;
         Ok(LittleEndian::read_u32(&buf))
     }
+
+    /// Read consecutive registers starting at `register`
+    pub(crate) fn read_bytes(
+        &mut self,
+        register: Register,
+        buf: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.check_access(register)?;
+        let address = self.address;
+        self.retry(register, AccessKind::Read, |i2c| {
+            i2c.write(address, &[register as u8])?;
+            i2c.read(address, buf)
+        })?;
+        trace!("read {:?} = {:?}", register, buf);
+        Ok(())
+    }
+
+    /// Run an I2C transaction on `reg`, retrying it if configured
+    fn retry<F>(
+        &mut self,
+        reg: Register,
+        kind: AccessKind,
+        mut transaction: F,
+    ) -> Result<(), Error<E>>
+    where
+        F: FnMut(&mut I2C) -> Result<(), E>,
+    {
+        let mut attempt = 0;
+        let mut recovered = false;
+        loop {
+            let source = match transaction(&mut self.i2c) {
+                Ok(()) => {
+                    self.failures = 0;
+                    return Ok(());
+                }
+                Err(source) => source,
+            };
+            self.failures = self.failures.saturating_add(1);
+            if let Some(recovery) = self
+                .recovery
+                .filter(|r| !recovered && self.failures >= r.after)
+            {
+                event!(failures = self.failures, "I2C bus recovery");
+                (recovery.recover)(&mut self.i2c);
+                self.failures = 0;
+                recovered = true;
+                continue;
+            }
+            if self.retries == 0 {
+                return Err(Error::Register { reg, kind, source });
+            }
+            if attempt >= self.retries {
+                return Err(Error::RetriesExhausted { reg, kind, source });
+            }
+            attempt += 1;
+        }
+    }
 }
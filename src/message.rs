@@ -0,0 +1,323 @@
+//! USB PD message headers, as found in RX_HEADER and TX_HEADER
+
+use bitfield::bitfield;
+use byteorder::{ByteOrder, LittleEndian};
+use hal::delay::DelayNs;
+use hal::i2c::I2c;
+
+use crate::{Error, PollPolicy, Register, STUSB4500};
+
+bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct MessageHeader(u16);
+    impl Debug;
+    pub extended, _: 15;
+    /// Number of 32-bit data objects following the header
+    pub u8, data_objects, _: 14, 12;
+    pub u8, message_id, _: 11, 9;
+    /// Power role, true = source
+    pub power_role_source, _: 8;
+    /// Specification revision, 0 = 1.0, 1 = 2.0, 2 = 3.0
+    pub u8, spec_revision, _: 7, 6;
+    /// Data role, true = DFP
+    pub data_role_dfp, _: 5;
+    pub u8, raw_message_type, _: 4, 0;
+}
+
+impl MessageHeader {
+    pub fn from_bits(bits: u16) -> Self {
+        MessageHeader(bits)
+    }
+
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    /// Decoded [`spec_revision`](Self::spec_revision)
+    pub fn revision(&self) -> SpecRevision {
+        match self.spec_revision() {
+            0 => SpecRevision::Rev10,
+            1 => SpecRevision::Rev20,
+            2 => SpecRevision::Rev30,
+            _ => SpecRevision::Reserved,
+        }
+    }
+
+    /// Decoded message type, control messages have no data objects
+    pub fn message_type(&self) -> MessageType {
+        let raw = self.raw_message_type();
+        if self.extended() {
+            MessageType::Extended(raw)
+        } else if self.data_objects() == 0 {
+            MessageType::Control(ControlMessage::from(raw))
+        } else {
+            MessageType::Data(DataMessage::from(raw))
+        }
+    }
+}
+
+/// USB PD specification revision of a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SpecRevision {
+    Rev10,
+    Rev20,
+    /// Revision 3.0 and later, required for PPS
+    Rev30,
+    Reserved,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    Control(ControlMessage),
+    Data(DataMessage),
+    /// Extended message, not decoded further
+    Extended(u8),
+}
+
+/// Control message types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum ControlMessage {
+    GoodCrc,
+    GotoMin,
+    Accept,
+    Reject,
+    Ping,
+    PsRdy,
+    GetSourceCap,
+    GetSinkCap,
+    DrSwap,
+    PrSwap,
+    VconnSwap,
+    Wait,
+    SoftReset,
+    DataReset,
+    DataResetComplete,
+    NotSupported,
+    GetSourceCapExtended,
+    GetStatus,
+    FrSwap,
+    GetPpsStatus,
+    GetCountryCodes,
+    GetSinkCapExtended,
+    /// Reserved message type
+    Unknown(u8),
+}
+
+impl ControlMessage {
+    /// Is `reply` the source's answer to this message when accepted
+    ///
+    /// A soft reset is followed by new capabilities right after the Accept, which may already
+    /// have replaced it in the RX buffer.
+    fn answered_by(self, reply: MessageType) -> bool {
+        match self {
+            ControlMessage::SoftReset => matches!(
+                reply,
+                MessageType::Control(ControlMessage::Accept)
+                    | MessageType::Data(DataMessage::SourceCapabilities)
+            ),
+            ControlMessage::GetSourceCap => {
+                reply == MessageType::Data(DataMessage::SourceCapabilities)
+            }
+            ControlMessage::GetSinkCap => reply == MessageType::Data(DataMessage::SinkCapabilities),
+            // Source_Capabilities_Extended and Status
+            ControlMessage::GetSourceCapExtended => reply == MessageType::Extended(0x01),
+            ControlMessage::GetStatus => reply == MessageType::Extended(0x02),
+            _ => false,
+        }
+    }
+}
+
+impl From<u8> for ControlMessage {
+    fn from(value: u8) -> Self {
+        match value {
+            0x01 => ControlMessage::GoodCrc,
+            0x02 => ControlMessage::GotoMin,
+            0x03 => ControlMessage::Accept,
+            0x04 => ControlMessage::Reject,
+            0x05 => ControlMessage::Ping,
+            0x06 => ControlMessage::PsRdy,
+            0x07 => ControlMessage::GetSourceCap,
+            0x08 => ControlMessage::GetSinkCap,
+            0x09 => ControlMessage::DrSwap,
+            0x0A => ControlMessage::PrSwap,
+            0x0B => ControlMessage::VconnSwap,
+            0x0C => ControlMessage::Wait,
+            0x0D => ControlMessage::SoftReset,
+            0x0E => ControlMessage::DataReset,
+            0x0F => ControlMessage::DataResetComplete,
+            0x10 => ControlMessage::NotSupported,
+            0x11 => ControlMessage::GetSourceCapExtended,
+            0x12 => ControlMessage::GetStatus,
+            0x13 => ControlMessage::FrSwap,
+            0x14 => ControlMessage::GetPpsStatus,
+            0x15 => ControlMessage::GetCountryCodes,
+            0x16 => ControlMessage::GetSinkCapExtended,
+            other => ControlMessage::Unknown(other),
+        }
+    }
+}
+
+impl From<ControlMessage> for u8 {
+    fn from(message: ControlMessage) -> Self {
+        match message {
+            ControlMessage::GoodCrc => 0x01,
+            ControlMessage::GotoMin => 0x02,
+            ControlMessage::Accept => 0x03,
+            ControlMessage::Reject => 0x04,
+            ControlMessage::Ping => 0x05,
+            ControlMessage::PsRdy => 0x06,
+            ControlMessage::GetSourceCap => 0x07,
+            ControlMessage::GetSinkCap => 0x08,
+            ControlMessage::DrSwap => 0x09,
+            ControlMessage::PrSwap => 0x0A,
+            ControlMessage::VconnSwap => 0x0B,
+            ControlMessage::Wait => 0x0C,
+            ControlMessage::SoftReset => 0x0D,
+            ControlMessage::DataReset => 0x0E,
+            ControlMessage::DataResetComplete => 0x0F,
+            ControlMessage::NotSupported => 0x10,
+            ControlMessage::GetSourceCapExtended => 0x11,
+            ControlMessage::GetStatus => 0x12,
+            ControlMessage::FrSwap => 0x13,
+            ControlMessage::GetPpsStatus => 0x14,
+            ControlMessage::GetCountryCodes => 0x15,
+            ControlMessage::GetSinkCapExtended => 0x16,
+            ControlMessage::Unknown(other) => other,
+        }
+    }
+}
+
+/// Data message types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataMessage {
+    SourceCapabilities,
+    Request,
+    Bist,
+    SinkCapabilities,
+    BatteryStatus,
+    Alert,
+    GetCountryInfo,
+    EnterUsb,
+    VendorDefined,
+    /// Reserved message type
+    Unknown(u8),
+}
+
+impl From<u8> for DataMessage {
+    fn from(value: u8) -> Self {
+        match value {
+            0x01 => DataMessage::SourceCapabilities,
+            0x02 => DataMessage::Request,
+            0x03 => DataMessage::Bist,
+            0x04 => DataMessage::SinkCapabilities,
+            0x05 => DataMessage::BatteryStatus,
+            0x06 => DataMessage::Alert,
+            0x07 => DataMessage::GetCountryInfo,
+            0x08 => DataMessage::EnterUsb,
+            0x0F => DataMessage::VendorDefined,
+            other => DataMessage::Unknown(other),
+        }
+    }
+}
+
+/// Last received PD message, see [`STUSB4500::read_rx_buffer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RxMessage {
+    pub header: MessageHeader,
+    objects: [u32; 7],
+}
+
+impl RxMessage {
+    /// Parse RX_HEADER followed by the 28 bytes of RX_DATA_OBJ
+    pub fn from_bytes(buf: &[u8; 30]) -> Self {
+        let (header, data) = buf.split_at(2);
+        let header = MessageHeader::from_bits(LittleEndian::read_u16(header));
+        let mut objects = [0; 7];
+        for (object, bytes) in objects
+            .iter_mut()
+            .zip(data.chunks_exact(4))
+            .take(header.data_objects() as usize)
+        {
+            *object = LittleEndian::read_u32(bytes);
+        }
+        RxMessage { header, objects }
+    }
+
+    pub fn message_type(&self) -> MessageType {
+        self.header.message_type()
+    }
+
+    /// Data objects announced by the header, empty for control messages
+    pub fn objects(&self) -> &[u32] {
+        self.objects
+            .get(..self.header.data_objects() as usize)
+            .unwrap_or(&self.objects)
+    }
+}
+
+impl<I2C, E, DELAY, ALERT> STUSB4500<I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    /// Read the header and data objects of the last received PD message in one burst
+    ///
+    /// The buffer holds whatever the source sent last, e.g. its capabilities or an Accept, which
+    /// makes this usable as a simple PD sniffer together with
+    /// [`prt_status`](Self::prt_status)`().message_received`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn read_rx_buffer(&mut self) -> Result<RxMessage, Error<E>> {
+        let mut buf = [0x00; 30];
+        self.read_bytes(Register::RXHeaderL, &mut buf)?;
+        let message = RxMessage::from_bytes(&buf);
+        event!(header = message.header.bits(), "RX message");
+        Ok(message)
+    }
+
+    /// Header of the last message sent through [`send_control_message`](Self::send_control_message)
+    pub fn tx_header(&mut self) -> Result<MessageHeader, Error<E>> {
+        let mut buf = [0x00; 2];
+        self.read_bytes(Register::TXHeaderL, &mut buf)?;
+        Ok(MessageHeader::from_bits(u16::from_le_bytes(buf)))
+    }
+
+    /// Send a PD control message and wait for the source to answer it, polling under `policy`
+    ///
+    /// Returns the type of the reply, e.g. Accept to a soft reset or Source_Capabilities to
+    /// Get_Source_Cap. A Reject, Wait or Not_Supported fails with [`Error::CommandRejected`],
+    /// no answer within the timeout with [`Error::Timeout`]. Other messages received meanwhile
+    /// are skipped. Stale PRT_STATUS events are cleared before sending.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(message = ?message))
+    )]
+    pub fn send_command<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        message: ControlMessage,
+        policy: PollPolicy,
+    ) -> Result<MessageType, Error<E>> {
+        self.prt_status()?;
+        self.send_control_message(message)?;
+        let mut poll = policy.start();
+        loop {
+            if self.prt_status()?.message_received {
+                let reply = self.rx_header()?.message_type();
+                event!(reply = ?reply, "command reply");
+                match reply {
+                    MessageType::Control(
+                        reply @ (ControlMessage::Reject
+                        | ControlMessage::Wait
+                        | ControlMessage::NotSupported),
+                    ) => return Err(Error::CommandRejected(reply)),
+                    reply if message.answered_by(reply) => return Ok(reply),
+                    _ => {}
+                }
+            }
+            if !poll.wait(delay) {
+                return Err(Error::Timeout);
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Runtime monitoring and discharge setup
+
+use hal::delay::DelayNs;
+use hal::i2c::I2c;
+
+use crate::{AlertMask, Error, Register, STUSB4500};
+
+/// Settings applied by [`STUSB4500::apply_monitoring_defaults`]
+///
+/// The default uses the discharge times of the factory NVM (756ms to 0V, 288ms per
+/// transition) with discharge enabled, and the default [`AlertMask`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonitoringProfile {
+    /// Interrupt mask, see [`STUSB4500::set_alerts_mask`]
+    pub alerts: AlertMask,
+    /// Internal VBUS discharge, see [`STUSB4500::set_discharge_on_disconnect`]
+    pub discharge: bool,
+    /// VBUS discharge time to 0V in ms, rounded down to 84ms steps and clamped to 1260ms
+    pub discharge_time_to_0v_ms: u16,
+    /// VBUS discharge time on a PDO transition in ms, rounded down to 24ms steps and clamped
+    /// to 360ms
+    pub discharge_time_transition_ms: u16,
+}
+
+impl Default for MonitoringProfile {
+    fn default() -> Self {
+        MonitoringProfile {
+            alerts: AlertMask::default(),
+            discharge: true,
+            discharge_time_to_0v_ms: 9 * 84,
+            discharge_time_transition_ms: 12 * 24,
+        }
+    }
+}
+
+impl<I2C, E, DELAY, ALERT> STUSB4500<I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    /// Apply a monitoring profile and clear stale interrupts
+    ///
+    /// These are runtime settings that revert on reset, the NVM isn't touched.
+    pub fn apply_monitoring_defaults(
+        &mut self,
+        profile: &MonitoringProfile,
+    ) -> Result<(), Error<E>> {
+        let to_0v = (profile.discharge_time_to_0v_ms / 84).min(15) as u8;
+        let transition = (profile.discharge_time_transition_ms / 24).min(15) as u8;
+        self.write(Register::VbusDischargeTimeCtrl, to_0v << 4 | transition)?;
+        self.set_discharge_on_disconnect(profile.discharge)?;
+        self.set_alerts_mask(profile.alerts)?;
+        self.clear_interrupts()
+    }
+}
@@ -0,0 +1,180 @@
+//! Contract negotiation helpers
+
+use hal::delay::DelayNs;
+use hal::i2c::I2c;
+
+use crate::pdo::Pdo;
+use crate::{ContractMismatch, ContractState, Error, PdoChannel, STUSB4500};
+
+/// How [`STUSB4500::negotiate_with_retry`] retries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Soft resets to try before giving up
+    pub attempts: u8,
+    /// Time to wait for the new contract after the first soft reset, in ms
+    pub delay_ms: u32,
+    /// Added to the delay after each failed attempt, in ms
+    pub backoff_ms: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 3,
+            delay_ms: 500,
+            backoff_ms: 250,
+        }
+    }
+}
+
+/// Outcome of [`STUSB4500::negotiate_with_retry`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NegotiationSummary {
+    /// Soft resets issued, 0 if the existing contract already matched
+    pub attempts: u8,
+    /// Mismatch after the last attempt, `None` on success
+    pub mismatch: Option<ContractMismatch>,
+}
+
+impl NegotiationSummary {
+    pub fn is_success(&self) -> bool {
+        self.mismatch.is_none()
+    }
+}
+
+impl<I2C, E, DELAY, ALERT> STUSB4500<I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    /// Soft reset until the contract matches the request, see
+    /// [`STUSB4500::contract_mismatch`]
+    ///
+    /// The current contract is checked first, so nothing is reset if it already matches.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn negotiate_with_retry<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        policy: RetryPolicy,
+    ) -> Result<NegotiationSummary, Error<E>> {
+        let mut summary = NegotiationSummary {
+            attempts: 0,
+            mismatch: self.contract_mismatch()?,
+        };
+        let mut wait = policy.delay_ms;
+        while summary.mismatch.is_some() && summary.attempts < policy.attempts {
+            self.soft_reset()?;
+            delay.delay_ms(wait);
+            wait = wait.saturating_add(policy.backoff_ms);
+
+            summary.attempts += 1;
+            summary.mismatch = self.contract_mismatch()?;
+            event!(attempt = summary.attempts, mismatch = ?summary.mismatch, "soft reset");
+        }
+        Ok(summary)
+    }
+
+    /// Ask the source for `voltage_mv` at up to `current_ma`, e.g. `configure_sink(9_000, 3_000)`
+    ///
+    /// PDO2 is set to the request and activated behind the 5V PDO1, then a soft reset starts
+    /// a new negotiation. The voltage is rounded down to 50mV and the current to 10mA, outside
+    /// of 5-20V or above 5A [`Error::OutaRangePdo`] is returned. The settings are lost on
+    /// power loss, use [`wait_for_contract`](Self::wait_for_contract) or
+    /// [`negotiate_with_retry`](Self::negotiate_with_retry) to check the outcome.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn configure_sink(&mut self, voltage_mv: u16, current_ma: u16) -> Result<(), Error<E>> {
+        if !(5_000..=20_000).contains(&voltage_mv) || current_ma > 5_000 {
+            return Err(Error::OutaRangePdo);
+        }
+        let pdo = Pdo::new_fixed(voltage_mv / 50, current_ma / 10);
+        event!(voltage_mv, current_ma, "configure sink");
+        self.set_pdo(PdoChannel::PDO2, &pdo)?;
+        self.set_num_pdo(2)?;
+        self.soft_reset()
+    }
+}
+
+/// Negotiated voltage and current of an explicit contract, see [`ContractWatcher`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub struct ContractValues {
+    /// VBUS requested by the policy engine in mV, in 100mV steps
+    pub voltage_mv: u16,
+    /// Granted operating current in mA
+    pub current_ma: u32,
+}
+
+/// Contract before and after a change, `None` without an explicit contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub struct ContractChange {
+    pub old: Option<ContractValues>,
+    pub new: Option<ContractValues>,
+}
+
+impl ContractChange {
+    /// Whether VBUS moves, e.g. to retune a DC/DC input stage. A change of current only
+    /// returns false.
+    pub fn voltage_changed(&self) -> bool {
+        self.old.map(|c| c.voltage_mv) != self.new.map(|c| c.voltage_mv)
+    }
+}
+
+/// Detects changes of the negotiated contract, e.g. a source rebalancing its power budget
+///
+/// Call [`update`](Self::update) after PD or attach alerts, the `events` services do so
+/// themselves. The first contract seen is reported as
+/// a change from `None`.
+///
+/// A renegotiation in flight is not a change: the last contract is kept until the new one is
+/// ready, so a soft reset doesn't show up as losing power.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ContractWatcher {
+    last: Option<ContractValues>,
+}
+
+impl ContractWatcher {
+    pub const fn new() -> Self {
+        ContractWatcher { last: None }
+    }
+
+    /// Contract seen by the last update
+    pub fn contract(&self) -> Option<ContractValues> {
+        self.last
+    }
+
+    /// Read the [`ContractState`] and compare it with the last contract
+    pub fn update<I2C, E, DELAY, ALERT>(
+        &mut self,
+        stusb: &mut STUSB4500<I2C, DELAY, ALERT>,
+    ) -> Result<Option<ContractChange>, Error<E>>
+    where
+        I2C: I2c<Error = E>,
+        DELAY: DelayNs,
+    {
+        Ok(self.observe(stusb.contract_state()?))
+    }
+
+    /// Compare `state` with the last contract, e.g. from a
+    /// [`StatusView`](crate::StatusView)
+    pub fn observe(&mut self, state: ContractState) -> Option<ContractChange> {
+        let new = match state {
+            ContractState::Negotiating => return None,
+            ContractState::Ready(contract) => Some(ContractValues {
+                voltage_mv: contract.voltage_mv,
+                current_ma: contract.current_ma(),
+            }),
+            ContractState::Detached | ContractState::NoContract => None,
+        };
+        if new == self.last {
+            return None;
+        }
+        let change = ContractChange {
+            old: self.last,
+            new,
+        };
+        event!(old = ?change.old, new = ?change.new, "contract changed");
+        self.last = new;
+        Some(change)
+    }
+}
@@ -1,42 +1,169 @@
+//! NVM programming session, see [`STUSB4500::unlock_nvm`]
+//!
+//! The image types are plain data in [`crate::image`], re-exported here.
+
+use hal::delay::DelayNs;
 use hal::i2c::I2c;
 
-use crate::{Error, NvmCtrl0, NvmCtrl1, NvmCtrl1Opcode, Register, STUSB4500};
+pub use crate::image::{NvmDiff, NvmImage, NvmImageSource, DEFAULT_NVM_DATA};
+use crate::{Error, NoAlertPin, NoDelay, NvmCtrl0, NvmCtrl1, NvmCtrl1Opcode, Register, STUSB4500};
+
+/// Number of times a pending NVM request is polled before giving up with [`Error::NvmTimeout`],
+/// also for [`NvmWrite::poll`]
+pub const NVM_POLL_LIMIT: u32 = 5000;
+
+/// Time between polls of a pending NVM request in µs, using the driver's stored delay
+pub const NVM_POLL_INTERVAL_US: u32 = 100;
+
+const ALL_SECTORS: NvmCtrl1 = NvmCtrl1::EraseSector0
+    .union(NvmCtrl1::EraseSector1)
+    .union(NvmCtrl1::EraseSector2)
+    .union(NvmCtrl1::EraseSector3)
+    .union(NvmCtrl1::EraseSector4);
+
+/// Erase flag of a single sector
+fn erase_flag(sector: u8) -> NvmCtrl1 {
+    NvmCtrl1::from_bits_truncate(NvmCtrl1::EraseSector0.bits() << sector)
+}
+
+fn check_sector<E>(sector: u8) -> Result<(), Error<E>> {
+    if sector < 5 {
+        Ok(())
+    } else {
+        Err(Error::InvalidSector(sector))
+    }
+}
+
+/// Step completed by [`STUSB4500Nvm::write_sectors_with_progress`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NvmProgress {
+    /// All sectors erased
+    Erased,
+    /// Sector programmed
+    Written(u8),
+    /// Sector read back and matched
+    Verified(u8),
+}
+
+/// What programming did to a single sector, see [`NvmReport`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SectorReport {
+    /// Left alone because it already held the requested data
+    pub skipped: bool,
+    pub erased: bool,
+    pub written: bool,
+    /// Read back and matched after writing
+    pub verified: bool,
+}
+
+impl SectorReport {
+    const SKIPPED: SectorReport = SectorReport {
+        skipped: true,
+        erased: false,
+        written: false,
+        verified: false,
+    };
+}
+
+/// E.g. `erased, written, verified` or `skipped (identical)`
+impl core::fmt::Display for SectorReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.skipped {
+            return f.write_str("skipped (identical)");
+        }
+        let steps = [
+            (self.erased, "erased"),
+            (self.written, "written"),
+            (self.verified, "verified"),
+        ];
+        let mut first = true;
+        for (_, step) in steps.iter().filter(|(done, _)| *done) {
+            if !first {
+                f.write_str(", ")?;
+            }
+            f.write_str(step)?;
+            first = false;
+        }
+        if first {
+            f.write_str("untouched")?;
+        }
+        Ok(())
+    }
+}
 
-pub const DEFAULT_NVM_DATA: [[u8; 8]; 5] = [
-    [0x00, 0x00, 0xB0, 0xAB, 0x00, 0x45, 0x00, 0x00],
-    [0x10, 0x40, 0x9C, 0x1C, 0xFF, 0x01, 0x3C, 0xDF],
-    [0x02, 0x40, 0x0F, 0x00, 0x32, 0x00, 0xFC, 0xF1],
-    [0x00, 0x19, 0x56, 0xAF, 0xF5, 0x35, 0x5F, 0x00],
-    [0x00, 0x4B, 0x90, 0x21, 0x43, 0x00, 0x40, 0xFB],
-];
+/// Per-sector outcome of programming the NVM, sector 0 first
+///
+/// Only describes a successful run, a failing one returns the error instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NvmReport(pub [SectorReport; 5]);
 
-pub struct STUSB4500Nvm<'a, I2C> {
-    inner: &'a mut STUSB4500<I2C>,
+impl NvmReport {
+    /// Report of a run that found all sectors identical
+    pub const UNCHANGED: NvmReport = NvmReport([SectorReport::SKIPPED; 5]);
+
+    /// Was any sector written
+    pub fn changed(&self) -> bool {
+        self.0.iter().any(|sector| sector.written)
+    }
+}
+
+/// Error of [`STUSB4500::write_image_from`]
+#[derive(Debug)]
+pub enum SourceError<E, S> {
+    /// Loading the image failed, the NVM wasn't touched
+    Source(S),
+    /// Programming failed
+    Device(Error<E>),
+}
+
+impl<E, S> From<Error<E>> for SourceError<E, S> {
+    fn from(err: Error<E>) -> Self {
+        SourceError::Device(err)
+    }
 }
 
-impl<I2C, E> STUSB4500Nvm<'_, I2C>
+/// Unlocked NVM session
+///
+/// Until [`STUSB4500Nvm::lock`] succeeds, the driver refuses all non-NVM register access with
+/// [`Error::NvmBusy`], even if the session is dropped without locking.
+pub struct STUSB4500Nvm<'a, I2C, DELAY = NoDelay, ALERT = NoAlertPin> {
+    inner: &'a mut STUSB4500<I2C, DELAY, ALERT>,
+}
+
+impl<'a, I2C, E, DELAY, ALERT> STUSB4500Nvm<'a, I2C, DELAY, ALERT>
 where
     I2C: I2c<Error = E>,
+    DELAY: DelayNs,
 {
-    const DEFAULT_PASSWORD: u8 = 0x47;
+    pub(crate) const DEFAULT_PASSWORD: u8 = 0x47;
 
-    pub(crate) fn unlock(inner: &mut STUSB4500<I2C>) -> Result<STUSB4500Nvm<I2C>, Error<E>> {
-        inner.write(Register::NvmPassword, STUSB4500Nvm::<I2C>::DEFAULT_PASSWORD)?;
+    pub(crate) fn unlock(
+        inner: &'a mut STUSB4500<I2C, DELAY, ALERT>,
+    ) -> Result<STUSB4500Nvm<'a, I2C, DELAY, ALERT>, Error<E>> {
+        inner.write(Register::NvmPassword, Self::DEFAULT_PASSWORD)?;
         inner.write(Register::NvmCtrl0, 0x00)?;
         inner.write(
             Register::NvmCtrl0,
             (NvmCtrl0::Power | NvmCtrl0::Enable).bits(),
         )?;
+        // A protected NVM ignores the enable, requests would then never complete
+        let ctrl = NvmCtrl0::from_bits_truncate(inner.read(Register::NvmCtrl0)?);
+        if !ctrl.contains(NvmCtrl0::Power | NvmCtrl0::Enable) {
+            event!("NVM locked");
+            // Best effort, the protection is the more useful error
+            let _ = inner.lock_nvm();
+            return Err(Error::NvmLocked);
+        }
+        inner.nvm_unlocked = true;
 
         Ok(STUSB4500Nvm { inner })
     }
 
     /// Lock the NVM
     pub fn lock(self) -> Result<(), Error<E>> {
-        self.inner
-            .write(Register::NvmCtrl0, NvmCtrl0::Enable.bits())?;
-        self.inner.write(Register::NvmCtrl1, 0x00)?;
-        self.inner.write(Register::NvmPassword, 0x00)
+        self.inner.lock_nvm()
     }
 
     /// Read the NVM data (all five sectors)
@@ -45,14 +172,26 @@ where
     /// application][gui].
     ///
     /// [gui]: https://www.st.com/en/embedded-software/stsw-stusb002.html
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub fn read_sectors(&mut self) -> Result<[[u8; 8]; 5], Error<E>> {
         let mut buf = [[0x00; 8]; 5];
         for (i, sector) in buf.iter_mut().enumerate() {
-            *sector = self.read_sector(i as u8)?;
+            *sector = self.read_sector_unchecked(i as u8)?;
         }
         Ok(buf)
     }
 
+    /// Same as [`read_sectors`](Self::read_sectors) into a flat buffer, sector 0 first
+    pub fn read_nvm(&mut self, buf: &mut [u8; 40]) -> Result<(), Error<E>> {
+        *buf = NvmImage(self.read_sectors()?).to_bytes();
+        Ok(())
+    }
+
+    /// Same as [`write_sectors`](Self::write_sectors) from a flat buffer, sector 0 first
+    pub fn write_nvm(&mut self, buf: &[u8; 40]) -> Result<(), Error<E>> {
+        self.write_sectors(NvmImage::from_bytes(buf).0)
+    }
+
     /// Write the NVM data (all five sectors)
     ///
     /// The NVM data is used to set the configuration on power-up. It can be generated by the [GUI
@@ -60,80 +199,381 @@ where
     ///
     /// [gui]: https://www.st.com/en/embedded-software/stsw-stusb002.html
     pub fn write_sectors(&mut self, sectors: [[u8; 8]; 5]) -> Result<(), Error<E>> {
-        self.erase_sectors()?;
-        for (i, sector) in sectors.iter().enumerate() {
-            self.write_sector(i as u8, sector)?;
+        self.write_sectors_with_progress(sectors, false, |_| {})?;
+        Ok(())
+    }
+
+    /// Write the NVM data (all five sectors), reporting each step to `progress`
+    ///
+    /// With `verify` each sector is read back afterwards, a mismatch fails with
+    /// [`Error::VerifyFailed`]. All sectors are erased and written, even identical ones.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(verify))
+    )]
+    pub fn write_sectors_with_progress<F>(
+        &mut self,
+        sectors: [[u8; 8]; 5],
+        verify: bool,
+        mut progress: F,
+    ) -> Result<NvmReport, Error<E>>
+    where
+        F: FnMut(NvmProgress),
+    {
+        let mut report = NvmReport::default();
+        self.erase(ALL_SECTORS)?;
+        event!("NVM erased");
+        progress(NvmProgress::Erased);
+        for ((i, sector), outcome) in sectors.iter().enumerate().zip(&mut report.0) {
+            outcome.erased = true;
+            self.program_sector(i as u8, sector)?;
+            event!(sector = i, "NVM sector written");
+            progress(NvmProgress::Written(i as u8));
+            outcome.written = true;
+        }
+        if verify {
+            self.verify_sectors(&sectors, &mut report, &mut progress)?;
+        }
+        Ok(report)
+    }
+
+    /// Read back the sectors `report` shows as written
+    fn verify_sectors<F>(
+        &mut self,
+        sectors: &[[u8; 8]; 5],
+        report: &mut NvmReport,
+        progress: &mut F,
+    ) -> Result<(), Error<E>>
+    where
+        F: FnMut(NvmProgress),
+    {
+        for ((i, sector), outcome) in sectors.iter().enumerate().zip(&mut report.0) {
+            if !outcome.written {
+                continue;
+            }
+            let data = self
+                .read_sector_unchecked(i as u8)
+                .map_err(|err| self.interrupted(i as u8, err))?;
+            if data != *sector {
+                event!(sector = i, "NVM verify failed");
+                return Err(Error::VerifyFailed(i as u8));
+            }
+            event!(sector = i, "NVM sector verified");
+            progress(NvmProgress::Verified(i as u8));
+            outcome.verified = true;
         }
         Ok(())
     }
 
+    /// Write and verify the NVM data, restoring the previous content if verification fails
+    ///
+    /// The current content is kept in RAM. If a sector of `sectors` doesn't read back correctly,
+    /// the previous image is programmed and verified again and [`Error::RolledBack`] is returned.
+    /// Any other error, including from the rollback, is returned as is and may leave the NVM
+    /// partly written.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn update_sectors_atomic(&mut self, sectors: [[u8; 8]; 5]) -> Result<NvmReport, Error<E>> {
+        let previous = self.read_sectors()?;
+        if previous == sectors {
+            return Ok(NvmReport::UNCHANGED);
+        }
+        match self.write_sectors_with_progress(sectors, true, |_| {}) {
+            Err(Error::VerifyFailed(sector)) => {
+                event!(sector, "NVM rollback");
+                self.write_sectors_with_progress(previous, true, |_| {})?;
+                Err(Error::RolledBack(sector))
+            }
+            result => result,
+        }
+    }
+
+    /// Read a single sector (0-4)
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(sector))
+    )]
+    pub fn read_sector(&mut self, sector: u8) -> Result<[u8; 8], Error<E>> {
+        check_sector(sector)?;
+        self.read_sector_unchecked(sector)
+    }
+
+    /// Erase and write a single sector (0-4), leaving the others untouched
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(sector))
+    )]
+    pub fn write_sector(&mut self, sector: u8, data: [u8; 8]) -> Result<(), Error<E>> {
+        check_sector(sector)?;
+        self.erase(erase_flag(sector))?;
+        self.program_sector(sector, &data)
+    }
+
+    /// Write the NVM data, erasing and programming only sectors that differ from the current
+    /// content
+    ///
+    /// With `verify` the written sectors are read back afterwards, a mismatch fails with
+    /// [`Error::VerifyFailed`]. Returns what was done to each sector.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(verify))
+    )]
+    pub fn write_changed_sectors(
+        &mut self,
+        sectors: [[u8; 8]; 5],
+        verify: bool,
+    ) -> Result<NvmReport, Error<E>> {
+        let current = self.read_sectors()?;
+        let mut report = NvmReport::default();
+        for ((outcome, current), sector) in report.0.iter_mut().zip(&current).zip(&sectors) {
+            outcome.skipped = current == sector;
+        }
+
+        let erase = (0..5u8)
+            .zip(&report.0)
+            .filter(|(_, outcome)| !outcome.skipped)
+            .fold(NvmCtrl1::empty(), |flags, (i, _)| flags | erase_flag(i));
+        if erase.is_empty() {
+            return Ok(report);
+        }
+
+        self.erase(erase)?;
+        event!(sectors = erase.bits(), "NVM erased");
+        for ((i, sector), outcome) in (0..5u8).zip(&sectors).zip(&mut report.0) {
+            if !outcome.skipped {
+                outcome.erased = true;
+                self.program_sector(i, sector)?;
+                event!(sector = i, "NVM sector written");
+                outcome.written = true;
+            }
+        }
+        if verify {
+            self.verify_sectors(&sectors, &mut report, &mut |_| {})?;
+        }
+        Ok(report)
+    }
+
+    /// Start writing the NVM data (all five sectors) without blocking
+    ///
+    /// Same as [`STUSB4500Nvm::write_sectors`] but returns after issuing the first NVM request.
+    /// Call [`NvmWrite::poll`] until it stops returning [`nb::Error::WouldBlock`] to finish.
+    /// A request still pending after [`NVM_POLL_LIMIT`] polls fails with [`Error::NvmTimeout`].
+    pub fn start_write_sectors<'s>(
+        &'s mut self,
+        sectors: [[u8; 8]; 5],
+    ) -> Result<NvmWrite<'s, 'a, I2C, DELAY, ALERT>, Error<E>> {
+        self.start_erase_sectors()
+            .map_err(|err| self.interrupted(0, err))?;
+        Ok(NvmWrite {
+            nvm: self,
+            sectors,
+            step: WriteStep::LoadSer,
+            pending_polls: 0,
+        })
+    }
+
     fn issue_request(&mut self) -> Result<(), Error<E>> {
         self.issue_request_with_sector(0)
     }
 
     fn issue_request_with_sector(&mut self, sector: u8) -> Result<(), Error<E>> {
+        self.start_request(sector)?;
+        self.wait_request()?;
+        Ok(())
+    }
+
+    fn start_request(&mut self, sector: u8) -> Result<(), Error<E>> {
         self.inner.write(
             Register::NvmCtrl0,
             sector | (NvmCtrl0::Power | NvmCtrl0::Enable | NvmCtrl0::Request).bits(),
-        )?;
+        )
+    }
 
-        while NvmCtrl0::from_bits_truncate(self.inner.read(Register::NvmCtrl0)?)
-            .contains(NvmCtrl0::Request)
-        {}
+    /// Wait for the current request to complete, giving up after [`NVM_POLL_LIMIT`] polls
+    fn wait_request(&mut self) -> Result<(), Error<E>> {
+        for _ in 0..NVM_POLL_LIMIT {
+            if !self.request_pending()? {
+                return Ok(());
+            }
+            self.inner.delay.delay_us(NVM_POLL_INTERVAL_US);
+        }
+        Err(Error::NvmTimeout)
+    }
 
-        Ok(())
+    /// Whether the current request is still running
+    ///
+    /// A chip that browned out comes back with the NVM disabled, the sequence can't continue.
+    fn request_pending(&mut self) -> Result<bool, Error<E>> {
+        let ctrl = NvmCtrl0::from_bits_truncate(self.inner.read(Register::NvmCtrl0)?);
+        if !ctrl.contains(NvmCtrl0::Power | NvmCtrl0::Enable) {
+            self.inner.nvm_interrupted = true;
+            return Err(Error::InterruptedProgramming(0));
+        }
+        Ok(ctrl.contains(NvmCtrl0::Request))
+    }
+
+    /// Report a lost device while accessing `sector` as [`Error::InterruptedProgramming`]
+    fn interrupted(&mut self, sector: u8, err: Error<E>) -> Error<E> {
+        match err {
+            Error::Register { .. }
+            | Error::RetriesExhausted { .. }
+            | Error::InterruptedProgramming(_) => {
+                event!(sector, "NVM access interrupted");
+                self.inner.nvm_interrupted = true;
+                Error::InterruptedProgramming(sector)
+            }
+            err => err,
+        }
     }
 
-    fn read_sector(&mut self, sector: u8) -> Result<[u8; 8], Error<E>> {
+    /// Write an opcode (plus opcode specific flags) to NVM_CTRL_1
+    fn write_opcode(&mut self, opcode: NvmCtrl1Opcode, flags: NvmCtrl1) -> Result<(), Error<E>> {
+        trace!("NVM opcode {:?}", opcode);
         self.inner
-            .write(Register::NvmCtrl1, NvmCtrl1Opcode::ReadSector as u8)?;
-        self.issue_request_with_sector(sector)?;
+            .write(Register::NvmCtrl1, opcode as u8 | flags.bits())
+    }
+
+    fn read_sector_unchecked(&mut self, sector: u8) -> Result<[u8; 8], Error<E>> {
+        self.write_opcode(NvmCtrl1Opcode::ReadSector, NvmCtrl1::empty())?;
+        self.issue_request_with_sector(sector)
+            .map_err(|err| match err {
+                Error::InterruptedProgramming(_) => Error::InterruptedProgramming(sector),
+                err => err,
+            })?;
 
         let mut buf = [0x00; 8];
-        self.inner
-            .i2c
-            .write(self.inner.address, &[Register::RWBuffer as u8])
-            .map_err(|err| Error::I2CError(err))?;
-        self.inner
-            .i2c
-            .read(self.inner.address, &mut buf)
-            .map_err(|err| Error::I2CError(err))?;
+        self.inner.read_bytes(Register::RWBuffer, &mut buf)?;
         Ok(buf)
     }
 
-    fn write_sector(&mut self, sector: u8, data: &[u8; 8]) -> Result<(), Error<E>> {
+    fn program_sector(&mut self, sector: u8, data: &[u8; 8]) -> Result<(), Error<E>> {
+        self.start_load_sector(data)
+            .and_then(|()| self.wait_request())
+            .and_then(|()| self.start_program_sector(sector))
+            .and_then(|()| self.wait_request())
+            .map_err(|err| self.interrupted(sector, err))
+    }
+
+    /// Load sector data into the Program Load Register
+    fn start_load_sector(&mut self, data: &[u8; 8]) -> Result<(), Error<E>> {
         let mut buf = [0x00; 9];
         buf[0] = Register::RWBuffer as u8;
         buf[1..].copy_from_slice(data);
 
-        self.inner
-            .i2c
-            .write(self.inner.address, &buf)
-            .map_err(|err| Error::I2CError(err))?;
-        self.inner
-            .write(Register::NvmCtrl1, NvmCtrl1Opcode::LoadPlr as u8)?;
-        self.issue_request()?;
+        trace!("write {:?} = {:?}", Register::RWBuffer, data);
+        self.inner.write_buf(Register::RWBuffer, &buf)?;
+        self.write_opcode(NvmCtrl1Opcode::LoadPlr, NvmCtrl1::empty())?;
+        self.start_request(0)
+    }
 
-        self.inner
-            .write(Register::NvmCtrl1, NvmCtrl1Opcode::WriteSector as u8)?;
-        self.issue_request_with_sector(sector)
+    /// Program the Program Load Register into a sector
+    fn start_program_sector(&mut self, sector: u8) -> Result<(), Error<E>> {
+        self.write_opcode(NvmCtrl1Opcode::WriteSector, NvmCtrl1::empty())?;
+        self.start_request(sector)
     }
 
-    fn erase_sectors(&mut self) -> Result<(), Error<E>> {
-        self.inner.write(
-            Register::NvmCtrl1,
-            NvmCtrl1Opcode::LoadSer as u8
-                | (NvmCtrl1::EraseSector0
-                    | NvmCtrl1::EraseSector1
-                    | NvmCtrl1::EraseSector2
-                    | NvmCtrl1::EraseSector3
-                    | NvmCtrl1::EraseSector4)
-                    .bits(),
-        )?;
-        self.issue_request()?;
+    fn erase(&mut self, sectors: NvmCtrl1) -> Result<(), Error<E>> {
+        self.start_erase(sectors)
+            .and_then(|()| self.wait_request())
+            .and_then(|()| self.write_opcode(NvmCtrl1Opcode::EraseSectors, NvmCtrl1::empty()))
+            .and_then(|()| self.issue_request())
+            .map_err(|err| {
+                // Lowest sector being erased
+                let sector = (sectors.bits() / NvmCtrl1::EraseSector0.bits()).trailing_zeros();
+                self.interrupted(sector.min(4) as u8, err)
+            })
+    }
 
-        self.inner
-            .write(Register::NvmCtrl1, NvmCtrl1Opcode::EraseSectors as u8)?;
-        self.issue_request()
+    /// Load the Sector Erase Register with all sectors
+    fn start_erase_sectors(&mut self) -> Result<(), Error<E>> {
+        self.start_erase(ALL_SECTORS)
+    }
+
+    /// Load the Sector Erase Register
+    fn start_erase(&mut self, sectors: NvmCtrl1) -> Result<(), Error<E>> {
+        self.write_opcode(NvmCtrl1Opcode::LoadSer, sectors)?;
+        self.start_request(0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WriteStep {
+    LoadSer,
+    Erase,
+    LoadPlr(u8),
+    Program(u8),
+    Done,
+}
+
+/// Non-blocking NVM write, see [`STUSB4500Nvm::start_write_sectors`]
+pub struct NvmWrite<'s, 'a, I2C, DELAY = NoDelay, ALERT = NoAlertPin> {
+    nvm: &'s mut STUSB4500Nvm<'a, I2C, DELAY, ALERT>,
+    sectors: [[u8; 8]; 5],
+    step: WriteStep,
+    /// Polls that found the current request still pending
+    pending_polls: u32,
+}
+
+impl<I2C, E, DELAY, ALERT> NvmWrite<'_, '_, I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    /// Advance the write, starting the next NVM request once the previous one has completed
+    ///
+    /// Returns `Ok(())` once all sectors have been written.
+    pub fn poll(&mut self) -> nb::Result<(), Error<E>> {
+        let sector = match self.step {
+            WriteStep::LoadPlr(sector) | WriteStep::Program(sector) => sector,
+            _ => 0,
+        };
+        self.advance().map_err(|err| match err {
+            nb::Error::Other(err) => nb::Error::Other(self.nvm.interrupted(sector, err)),
+            nb::Error::WouldBlock => nb::Error::WouldBlock,
+        })
+    }
+
+    fn advance(&mut self) -> nb::Result<(), Error<E>> {
+        if self.step == WriteStep::Done {
+            return Ok(());
+        }
+        if self.nvm.request_pending()? {
+            self.pending_polls += 1;
+            if self.pending_polls > NVM_POLL_LIMIT {
+                return Err(nb::Error::Other(Error::NvmTimeout));
+            }
+            return Err(nb::Error::WouldBlock);
+        }
+        self.pending_polls = 0;
+
+        self.step = match self.step {
+            WriteStep::LoadSer => {
+                self.nvm
+                    .write_opcode(NvmCtrl1Opcode::EraseSectors, NvmCtrl1::empty())?;
+                self.nvm.start_request(0)?;
+                WriteStep::Erase
+            }
+            WriteStep::Erase => {
+                let [first, ..] = self.sectors;
+                self.nvm.start_load_sector(&first)?;
+                WriteStep::LoadPlr(0)
+            }
+            WriteStep::LoadPlr(sector) => {
+                self.nvm.start_program_sector(sector)?;
+                WriteStep::Program(sector)
+            }
+            WriteStep::Program(sector) => match self.sectors.get(sector as usize + 1) {
+                Some(next) => {
+                    self.nvm.start_load_sector(next)?;
+                    WriteStep::LoadPlr(sector + 1)
+                }
+                None => WriteStep::Done,
+            },
+            WriteStep::Done => WriteStep::Done,
+        };
+
+        match self.step {
+            WriteStep::Done => Ok(()),
+            _ => Err(nb::Error::WouldBlock),
+        }
     }
 }
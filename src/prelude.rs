@@ -0,0 +1,23 @@
+//! Commonly used types
+//!
+//! ```ignore
+//! use stusb4500::prelude::*;
+//! ```
+
+pub use crate::charger::{CurrentLimiter, InputCurrentLimit};
+pub use crate::config::NvmConfig;
+pub use crate::events::{AlertService, Event};
+pub use crate::image::{NvmImage, NvmImageSource};
+pub use crate::negotiate::{ContractWatcher, NegotiationSummary, RetryPolicy};
+pub use crate::nvm::NvmReport;
+pub use crate::pdo::{AugmentedPdo, BatteryPdo, FixedPdo, Pdo, VariablePdo};
+pub use crate::profile::Profile;
+pub use crate::rdo::Rdo;
+pub use crate::registers::{Alert, AlertMask, Register};
+pub use crate::runtime::RuntimeConfig;
+pub use crate::status::{
+    AlertSnapshot, AttachedDevice, Contract, ContractMismatch, ContractState, DeviceMode, Faults,
+    MonitoringStatus, PeState, ProtocolStatus, StatusView, TypeCCurrent, TypeCState,
+};
+pub use crate::variant::{DeviceInfo, Variant};
+pub use crate::{AccessKind, Address, Error, PdoChannel, PollPolicy, STUSB4500};
@@ -0,0 +1,112 @@
+//! Named sets of sink PDOs switched at runtime
+//!
+//! A [`Profile`] bundles the PDOs and options of one operating mode, e.g. full power on a dock
+//! and 5V only while hot. [`STUSB4500::apply_profile`] writes it and renegotiates:
+//!
+//! ```ignore
+//! let dock = Profile::new("desk-dock 20V", &[Pdo::new_fixed(100, 300), Pdo::new_fixed(400, 225)])?;
+//! let cool = Profile::new("battery-save 5V", &[Pdo::new_fixed(100, 150)])?;
+//!
+//! stusb.apply_profile(if too_hot { &cool } else { &dock })?;
+//! ```
+//!
+//! Like all runtime settings a profile is lost on reset, the NVM configuration applies again.
+
+use hal::delay::DelayNs;
+use hal::i2c::I2c;
+
+use crate::pdo::{validate_pdo_set, Pdo, PdoSetError};
+use crate::{Error, PdoChannel, STUSB4500};
+
+/// Up to three sink PDOs plus options, see the [module documentation](self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Profile {
+    /// Shown in logs, not sent to the device
+    pub name: &'static str,
+    pdos: [Pdo; 3],
+    count: u8,
+    /// Enable or disable the VBUS discharge, `None` leaves it as is
+    pub discharge_on_disconnect: Option<bool>,
+    /// Soft reset after writing so the new PDOs take effect right away, enabled by default
+    pub renegotiate: bool,
+}
+
+impl Profile {
+    /// Profile advertising `pdos`, which have to pass [`validate_pdo_set`]
+    pub fn new(name: &'static str, pdos: &[Pdo]) -> Result<Self, PdoSetError> {
+        validate_pdo_set(pdos)?;
+        let mut stored = [Pdo::new_fixed(100, 0); 3];
+        for (stored, pdo) in stored.iter_mut().zip(pdos) {
+            *stored = *pdo;
+        }
+        Ok(Profile {
+            name,
+            pdos: stored,
+            count: pdos.len() as u8,
+            discharge_on_disconnect: None,
+            renegotiate: true,
+        })
+    }
+
+    /// The advertised PDOs, PDO1 first
+    pub fn pdos(&self) -> &[Pdo] {
+        self.pdos.get(..usize::from(self.count)).unwrap_or(&[])
+    }
+
+    /// Set [`discharge_on_disconnect`](Self::discharge_on_disconnect)
+    pub fn with_discharge_on_disconnect(mut self, enable: bool) -> Self {
+        self.discharge_on_disconnect = Some(enable);
+        self
+    }
+
+    /// Only write the PDOs, they are used from the next negotiation on
+    pub fn without_renegotiation(mut self) -> Self {
+        self.renegotiate = false;
+        self
+    }
+}
+
+impl<I2C, E, DELAY, ALERT> STUSB4500<I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    /// Write the PDOs and options of `profile`, then soft reset unless
+    /// [`renegotiate`](Profile::renegotiate) is cleared
+    ///
+    /// PDOs beyond the profile's count are left as is but not advertised. Check the outcome with
+    /// [`wait_for_contract`](Self::wait_for_contract) or
+    /// [`negotiate_with_retry`](Self::negotiate_with_retry).
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn apply_profile(&mut self, profile: &Profile) -> Result<(), Error<E>> {
+        event!(profile = profile.name, "apply profile");
+        for (channel, pdo) in PdoChannel::iter().zip(profile.pdos()) {
+            self.set_pdo(channel, pdo)?;
+        }
+        self.set_num_pdo(profile.count)?;
+        if let Some(enable) = profile.discharge_on_disconnect {
+            self.set_discharge_on_disconnect(enable)?;
+        }
+        if profile.renegotiate {
+            self.soft_reset()?;
+        }
+        Ok(())
+    }
+
+    /// Advertise only PDO1 and renegotiate, so VBUS stays at 5V
+    ///
+    /// The runtime counterpart of
+    /// [`NvmConfig::set_five_volt_only`](crate::config::NvmConfig::set_five_volt_only), lost on
+    /// reset like a profile. Program the NVM where the limit has to hold from power-up. The PDO
+    /// registers are left as is.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn set_five_volt_only(&mut self) -> Result<(), Error<E>> {
+        self.set_num_pdo(1)?;
+        self.soft_reset()
+    }
+
+    /// Whether only PDO1 is advertised
+    pub fn is_five_volt_only(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.get_num_pdo()? == 1)
+    }
+}
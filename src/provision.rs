@@ -0,0 +1,57 @@
+//! Host side provisioning with a machine readable report
+
+use std::vec::Vec;
+
+use hal::i2c::I2c;
+
+use crate::config::NvmConfig;
+use crate::image::{NvmDiff, NvmImage};
+use crate::{Address, Error, STUSB4500};
+
+/// Outcome of [`provision_device`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvisionReport {
+    /// NVM content before programming
+    pub previous: NvmImage,
+    /// NVM content that was programmed
+    pub written: NvmImage,
+    /// Bytes that changed
+    pub changes: Vec<NvmDiff>,
+    /// The NVM read back equal to `written`
+    pub verified: bool,
+}
+
+impl ProvisionReport {
+    /// Number of bytes that changed
+    pub fn bytes_changed(&self) -> usize {
+        self.changes.len()
+    }
+}
+
+/// Unlock, erase, write, verify and re-lock the NVM of the device at `address`
+///
+/// Pass `&mut bus` to keep using the bus afterwards. A failed verification is reported in
+/// [`ProvisionReport::verified`] rather than as an error.
+pub fn provision_device<I2C, E>(
+    bus: I2C,
+    address: Address,
+    config: &NvmConfig,
+) -> Result<ProvisionReport, Error<E>>
+where
+    I2C: I2c<Error = E>,
+{
+    let written = config.image();
+    let mut device = STUSB4500::new(bus, address);
+    let (previous, readback) = device.with_nvm(|nvm| {
+        let previous = NvmImage(nvm.read_sectors()?);
+        nvm.write_sectors(written.0)?;
+        Ok((previous, NvmImage(nvm.read_sectors()?)))
+    })?;
+
+    Ok(ProvisionReport {
+        previous,
+        written,
+        changes: previous.diff(&written).collect(),
+        verified: readback == written,
+    })
+}
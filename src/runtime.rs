@@ -0,0 +1,92 @@
+//! Save and restore the runtime register configuration
+//!
+//! Runtime settings revert to the NVM configuration on every reset. Save them once after setup
+//! and write them back after [`STUSB4500::software_reset`] instead of repeating each setup
+//! call:
+//!
+//! ```ignore
+//! let saved = stusb.save_runtime_config()?;
+//! stusb.software_reset()?;
+//! stusb.restore_runtime_config(&saved)?;
+//! stusb.soft_reset()?;
+//! ```
+
+use byteorder::{ByteOrder, LittleEndian};
+use hal::delay::DelayNs;
+use hal::i2c::I2c;
+
+use crate::registers::{GpioSwGpio, VbusDischargeCtrl};
+use crate::{AlertMask, Error, Register, STUSB4500};
+
+/// Mutable runtime registers, see the [module documentation](self)
+///
+/// Values are kept as read, reserved bits included, so a restore writes back exactly what was
+/// saved. VBUS_CTRL isn't included, the policy engine drives the power path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    /// DPM_SNK_PDO1 to DPM_SNK_PDO3, raw so unset PDOs round trip too
+    pub pdos: [u32; 3],
+    /// DPM_PDO_NUMB
+    pub pdo_count: u8,
+    /// ALERT_STATUS_1_MASK
+    pub alerts_mask: AlertMask,
+    /// MONITORING_CTRL_0
+    pub monitoring_ctrl0: u8,
+    /// MONITORING_CTRL_2, the VBUS monitoring window
+    pub monitoring_ctrl2: u8,
+    /// VBUS_DISCHARGE_TIME_CTRL
+    pub discharge_time: u8,
+    /// VBUS_DISCHARGE_CTRL
+    pub discharge: VbusDischargeCtrl,
+    /// GPIO_SW_GPIO
+    pub gpio: GpioSwGpio,
+}
+
+impl<I2C, E, DELAY, ALERT> STUSB4500<I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    /// Read the runtime configuration, 5 reads
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn save_runtime_config(&mut self) -> Result<RuntimeConfig, Error<E>> {
+        let mut pdos = [0x00; 12];
+        self.read_bytes(Register::DPMSNKPDO1, &mut pdos)?;
+        // MONITORING_CTRL_0 through VBUS_DISCHARGE_CTRL
+        let mut monitoring = [0x00; 7];
+        self.read_bytes(Register::MonitoringCtrl0, &mut monitoring)?;
+        let [ctrl0, _ctrl1, ctrl2, _reset, _reserved, discharge_time, discharge] = monitoring;
+        let mut pdo_words = [0; 3];
+        LittleEndian::read_u32_into(&pdos, &mut pdo_words);
+        Ok(RuntimeConfig {
+            pdos: pdo_words,
+            pdo_count: self.read(Register::DPMPDONumb)?,
+            alerts_mask: AlertMask::from_bits_retain(self.read(Register::AlertStatus1Mask)?),
+            monitoring_ctrl0: ctrl0,
+            monitoring_ctrl2: ctrl2,
+            discharge_time,
+            discharge: VbusDischargeCtrl::from_bits_retain(discharge),
+            gpio: GpioSwGpio::from_bits_retain(self.read(Register::GpioSWGpio)?),
+        })
+    }
+
+    /// Write back a [`RuntimeConfig`], 8 writes
+    ///
+    /// The PDOs only take effect on the next negotiation, call
+    /// [`soft_reset`](Self::soft_reset) to renegotiate right away. Interrupts latched before
+    /// the restore are left as is.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn restore_runtime_config(&mut self, config: &RuntimeConfig) -> Result<(), Error<E>> {
+        let mut pdos = [0x00; 13];
+        pdos[0] = Register::DPMSNKPDO1 as u8;
+        LittleEndian::write_u32_into(&config.pdos, &mut pdos[1..]);
+        self.write_buf(Register::DPMSNKPDO1, &pdos)?;
+        self.write(Register::DPMPDONumb, config.pdo_count)?;
+        self.write(Register::AlertStatus1Mask, config.alerts_mask.bits())?;
+        self.write(Register::MonitoringCtrl0, config.monitoring_ctrl0)?;
+        self.write(Register::MonitoringCtrl2, config.monitoring_ctrl2)?;
+        self.write(Register::VbusDischargeTimeCtrl, config.discharge_time)?;
+        self.write(Register::VbusDischargeCtrl, config.discharge.bits())?;
+        self.write(Register::GpioSWGpio, config.gpio.bits())
+    }
+}
@@ -0,0 +1,203 @@
+//! Source capabilities
+//!
+//! Source fixed and programmable PDOs use a different layout than the sink PDOs in
+//! [`crate::pdo`]. Variable and battery PDOs are the same for both roles and reuse the sink types.
+
+use bitfield::bitfield;
+use byteorder::{ByteOrder, LittleEndian};
+use hal::delay::DelayNs;
+use hal::i2c::I2c;
+
+use crate::message::{ControlMessage, MessageHeader, SpecRevision};
+use crate::pdo::{BatteryPdo, InvalidPdoBits, VariablePdo};
+use crate::{Error, PollPolicy, Register, STUSB4500};
+
+bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct SourceFixedPdo(u32);
+    impl Debug;
+    // The fields default to u16
+    pub fixed, _: 31, 30;
+    pub dual_role_power, _: 29;
+    pub usb_suspend_supported, _: 28;
+    pub unconstrained_power, _: 27;
+    pub usb_communications_capable, _: 26;
+    pub dual_role_data, _: 25;
+    pub unchunked_extended_messages, _: 24;
+    pub epr_mode_capable, _: 23;
+    pub _reserved, _: 22;
+    /// Peak current capability, 0 = max current, 1-3 = increasing overload capability
+    pub peak_current, _: 21, 20;
+    /// Voltage in 50mV units
+    pub voltage, _: 19, 10;
+    /// Maximum current in 10mA units
+    pub max_current, _: 9, 0;
+}
+
+impl SourceFixedPdo {
+    /// Voltage in mV
+    pub fn voltage_mv(&self) -> u32 {
+        self.voltage() * 50
+    }
+
+    /// Maximum current in mA
+    pub fn max_current_ma(&self) -> u32 {
+        self.max_current() * 10
+    }
+}
+
+bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct SourcePpsPdo(u32);
+    impl Debug;
+    // The fields default to u16
+    pub augmented, _: 31, 30;
+    pub apdo_type, _: 29, 28;
+    pub power_limited, _: 27;
+    pub _reserved2, _: 26, 25;
+    /// Voltage in 100mV units
+    pub max_voltage, _: 24, 17;
+    pub _reserved1, _: 16;
+    /// Voltage in 100mV units
+    pub min_voltage, _: 15, 8;
+    pub _reserved0, _: 7;
+    /// Current in 50mA units
+    pub max_current, _: 6, 0;
+}
+
+/// Power data object advertised by a source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourcePdo {
+    Fixed(SourceFixedPdo),
+    Variable(VariablePdo),
+    Battery(BatteryPdo),
+    /// SPR programmable power supply
+    Pps(SourcePpsPdo),
+    /// Other augmented PDOs (e.g. AVS), kept raw
+    Augmented(u32),
+}
+
+impl SourcePdo {
+    /// Raw PDO
+    pub fn bits(&self) -> u32 {
+        u32::from(*self)
+    }
+}
+
+impl TryFrom<u32> for SourcePdo {
+    type Error = InvalidPdoBits;
+
+    fn try_from(bits: u32) -> Result<Self, Self::Error> {
+        match (bits >> 30, (bits >> 28) & 0x3) {
+            // A zero object is padding, not a 0V PDO
+            _ if bits == 0 => Err(InvalidPdoBits(bits)),
+            (0, _) => Ok(SourcePdo::Fixed(SourceFixedPdo(bits))),
+            (1, _) => Ok(SourcePdo::Variable(VariablePdo(bits))),
+            (2, _) => Ok(SourcePdo::Battery(BatteryPdo(bits))),
+            (_, 0) => Ok(SourcePdo::Pps(SourcePpsPdo(bits))),
+            _ => Ok(SourcePdo::Augmented(bits)),
+        }
+    }
+}
+
+impl From<SourcePdo> for u32 {
+    fn from(pdo: SourcePdo) -> Self {
+        match pdo {
+            SourcePdo::Fixed(a) => a.0,
+            SourcePdo::Variable(a) => a.0,
+            SourcePdo::Battery(a) => a.0,
+            SourcePdo::Pps(a) => a.0,
+            SourcePdo::Augmented(bits) => bits,
+        }
+    }
+}
+
+/// Source capabilities message, up to seven PDOs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceCapabilities {
+    header: MessageHeader,
+    count: u8,
+    pdos: [u32; 7],
+}
+
+impl SourceCapabilities {
+    /// Header of the message the capabilities were read from
+    pub fn header(&self) -> MessageHeader {
+        self.header
+    }
+
+    /// PD revision the source sent the capabilities with
+    ///
+    /// A 2.0 source ignores programmable requests even if it lists a PPS APDO.
+    pub fn revision(&self) -> SpecRevision {
+        self.header.revision()
+    }
+
+    /// Whether a PPS request is worth attempting: PD 3.0 and at least one PPS APDO
+    pub fn supports_pps(&self) -> bool {
+        self.revision() >= SpecRevision::Rev30
+            && self.iter().any(|pdo| matches!(pdo, SourcePdo::Pps(_)))
+    }
+
+    /// Number of PDOs, as announced by the message header
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Raw PDOs
+    pub fn raw(&self) -> &[u32] {
+        self.pdos.get(..self.count as usize).unwrap_or(&self.pdos)
+    }
+
+    /// Decoded PDOs, skipping invalid ones
+    pub fn iter(&self) -> impl Iterator<Item = SourcePdo> + '_ {
+        self.raw()
+            .iter()
+            .filter_map(|bits| SourcePdo::try_from(*bits).ok())
+    }
+}
+
+impl<I2C, E, DELAY, ALERT> STUSB4500<I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    /// Send Get_Source_Cap and return the source capabilities from the response
+    ///
+    /// See [`send_command`](Self::send_command) for the polling and errors. The chip evaluates
+    /// the new capabilities on its own, which may change the active contract.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn request_source_capabilities<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        policy: PollPolicy,
+    ) -> Result<SourceCapabilities, Error<E>> {
+        self.send_command(delay, ControlMessage::GetSourceCap, policy)?;
+        let header = self.rx_header()?;
+        self.read_source_capabilities(header)
+    }
+
+    fn read_source_capabilities(
+        &mut self,
+        header: MessageHeader,
+    ) -> Result<SourceCapabilities, Error<E>> {
+        let count = header.data_objects().min(7);
+        let mut buf = [0x00; 28];
+        let (data, _) = buf.split_at_mut(count as usize * 4);
+        self.read_bytes(Register::RXDataObj, data)?;
+
+        let mut pdos = [0; 7];
+        for (pdo, bytes) in pdos.iter_mut().zip(buf.chunks_exact(4)) {
+            *pdo = LittleEndian::read_u32(bytes);
+        }
+        Ok(SourceCapabilities {
+            header,
+            count,
+            pdos,
+        })
+    }
+}
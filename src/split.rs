@@ -0,0 +1,179 @@
+//! Status and control halves sharing one driver
+//!
+//! [`SharedStusb4500::split`] hands out a [`StatusHandle`] for monitoring and a
+//! [`ControlHandle`] for (re)negotiation. Both borrow the driver through a `RefCell` for the
+//! duration of a single call, so they can live in different tasks of the same executor.
+
+use core::cell::RefCell;
+
+use hal::delay::DelayNs;
+use hal::i2c::I2c;
+
+use crate::profile::Profile;
+use crate::{
+    Alert, AlertMask, ContractState, Error, MonitoringStatus, NoAlertPin, NoDelay, Pdo, PdoChannel,
+    PeState, ProtocolStatus, Rdo, STUSB4500,
+};
+
+/// Driver shared by a [`StatusHandle`] and a [`ControlHandle`]
+pub struct SharedStusb4500<I2C, DELAY = NoDelay, ALERT = NoAlertPin> {
+    inner: RefCell<STUSB4500<I2C, DELAY, ALERT>>,
+}
+
+impl<I2C, E, DELAY, ALERT> SharedStusb4500<I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    pub fn new(driver: STUSB4500<I2C, DELAY, ALERT>) -> Self {
+        SharedStusb4500 {
+            inner: RefCell::new(driver),
+        }
+    }
+
+    /// Release the driver
+    pub fn release(self) -> STUSB4500<I2C, DELAY, ALERT> {
+        self.inner.into_inner()
+    }
+
+    /// Split into a read-only status handle and a control handle
+    pub fn split(
+        &self,
+    ) -> (
+        StatusHandle<'_, I2C, DELAY, ALERT>,
+        ControlHandle<'_, I2C, DELAY, ALERT>,
+    ) {
+        (
+            StatusHandle { inner: &self.inner },
+            ControlHandle { inner: &self.inner },
+        )
+    }
+}
+
+impl<I2C, E, DELAY, ALERT> From<STUSB4500<I2C, DELAY, ALERT>> for SharedStusb4500<I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    fn from(driver: STUSB4500<I2C, DELAY, ALERT>) -> Self {
+        SharedStusb4500::new(driver)
+    }
+}
+
+/// Read-only access to the status registers, see [`SharedStusb4500::split`]
+pub struct StatusHandle<'a, I2C, DELAY = NoDelay, ALERT = NoAlertPin> {
+    inner: &'a RefCell<STUSB4500<I2C, DELAY, ALERT>>,
+}
+
+impl<I2C, E, DELAY, ALERT> StatusHandle<'_, I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    fn with<R>(&self, f: impl FnOnce(&mut STUSB4500<I2C, DELAY, ALERT>) -> R) -> R {
+        f(&mut self.inner.borrow_mut())
+    }
+
+    /// See [`STUSB4500::is_attached`]
+    pub fn is_attached(&self) -> Result<bool, Error<E>> {
+        self.with(|d| d.is_attached())
+    }
+
+    /// See [`STUSB4500::monitoring_status`]
+    pub fn monitoring_status(&self) -> Result<MonitoringStatus, Error<E>> {
+        self.with(|d| d.monitoring_status())
+    }
+
+    /// See [`STUSB4500::get_alerts`]
+    pub fn get_alerts(&self) -> Result<Alert, Error<E>> {
+        self.with(|d| d.get_alerts())
+    }
+
+    /// See [`STUSB4500::prt_status`]
+    pub fn prt_status(&self) -> Result<ProtocolStatus, Error<E>> {
+        self.with(|d| d.prt_status())
+    }
+
+    /// See [`STUSB4500::pe_state`]
+    pub fn pe_state(&self) -> Result<PeState, Error<E>> {
+        self.with(|d| d.pe_state())
+    }
+
+    /// See [`STUSB4500::get_pdo`]
+    pub fn get_pdo(&self, pdo: PdoChannel) -> Result<Pdo, Error<E>> {
+        self.with(|d| d.get_pdo(pdo))
+    }
+
+    /// See [`STUSB4500::get_voltage_mv`]
+    pub fn get_voltage_mv(&self) -> Result<u16, Error<E>> {
+        self.with(|d| d.get_voltage_mv())
+    }
+
+    /// See [`STUSB4500::get_current_rdo`]
+    pub fn get_current_rdo(&self) -> Result<Rdo, Error<E>> {
+        self.with(|d| d.get_current_rdo())
+    }
+
+    /// See [`STUSB4500::get_negotiated_power`]
+    pub fn get_negotiated_power(&self) -> Result<Option<u32>, Error<E>> {
+        self.with(|d| d.get_negotiated_power())
+    }
+
+    /// See [`STUSB4500::contract_state`]
+    pub fn contract_state(&self) -> Result<ContractState, Error<E>> {
+        self.with(|d| d.contract_state())
+    }
+}
+
+/// Control access to the driver, see [`SharedStusb4500::split`]
+pub struct ControlHandle<'a, I2C, DELAY = NoDelay, ALERT = NoAlertPin> {
+    inner: &'a RefCell<STUSB4500<I2C, DELAY, ALERT>>,
+}
+
+impl<I2C, E, DELAY, ALERT> ControlHandle<'_, I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    /// Run `f` with exclusive access to the driver, for anything not wrapped here
+    ///
+    /// Panics if called re-entrantly from a [`StatusHandle`] or [`ControlHandle`] call.
+    pub fn with<R>(&self, f: impl FnOnce(&mut STUSB4500<I2C, DELAY, ALERT>) -> R) -> R {
+        f(&mut self.inner.borrow_mut())
+    }
+
+    /// See [`STUSB4500::soft_reset`]
+    pub fn soft_reset(&self) -> Result<(), Error<E>> {
+        self.with(|d| d.soft_reset())
+    }
+
+    /// See [`STUSB4500::set_pdo`]
+    pub fn set_pdo(&self, pdo: PdoChannel, data: &Pdo) -> Result<(), Error<E>> {
+        self.with(|d| d.set_pdo(pdo, data))
+    }
+
+    /// See [`STUSB4500::set_num_pdo`]
+    pub fn set_num_pdo(&self, num: u8) -> Result<(), Error<E>> {
+        self.with(|d| d.set_num_pdo(num))
+    }
+
+    /// See [`STUSB4500::apply_profile`]
+    pub fn apply_profile(&self, profile: &Profile) -> Result<(), Error<E>> {
+        self.with(|d| d.apply_profile(profile))
+    }
+
+    /// See [`STUSB4500::set_five_volt_only`]
+    pub fn set_five_volt_only(&self) -> Result<(), Error<E>> {
+        self.with(|d| d.set_five_volt_only())
+    }
+
+    /// See [`STUSB4500::set_alerts_mask`]
+    pub fn set_alerts_mask(&self, alerts: AlertMask) -> Result<(), Error<E>> {
+        self.with(|d| d.set_alerts_mask(alerts))
+    }
+
+    /// See [`STUSB4500::clear_interrupts`]
+    pub fn clear_interrupts(&self) -> Result<(), Error<E>> {
+        self.with(|d| d.clear_interrupts())
+    }
+}
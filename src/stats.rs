@@ -0,0 +1,71 @@
+//! Negotiation statistics, enabled by the `stats` feature
+
+use hal::delay::DelayNs;
+use hal::i2c::I2c;
+
+use crate::STUSB4500;
+
+/// Counters of what the driver observed, see [`STUSB4500::stats`]
+///
+/// Attaches and contracts are counted when a read shows them newly established, so a detach
+/// and re-attach between two reads is missed. Counters saturate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Stats {
+    /// Explicit contracts established (RDO position became non-zero)
+    pub contracts: u32,
+    /// Soft resets sent
+    pub soft_resets: u32,
+    /// Hard resets received from the source
+    pub hard_resets: u32,
+    /// Source attaches
+    pub attaches: u32,
+    attached: bool,
+    contract: bool,
+}
+
+impl Stats {
+    pub(crate) fn record_attach(&mut self, attached: bool) {
+        if attached && !self.attached {
+            self.attaches = self.attaches.saturating_add(1);
+        }
+        self.attached = attached;
+    }
+
+    pub(crate) fn record_contract(&mut self, contract: bool) {
+        if contract && !self.contract {
+            self.contracts = self.contracts.saturating_add(1);
+        }
+        self.contract = contract;
+    }
+
+    pub(crate) fn record_soft_reset(&mut self) {
+        self.soft_resets = self.soft_resets.saturating_add(1);
+    }
+
+    pub(crate) fn record_hard_reset(&mut self, received: bool) {
+        if received {
+            self.hard_resets = self.hard_resets.saturating_add(1);
+        }
+    }
+}
+
+impl<I2C, E, DELAY, ALERT> STUSB4500<I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    /// Counters since the driver was created or [`reset_stats`](Self::reset_stats)
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Zero the counters, keeping the last seen attach and contract state
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats {
+            attached: self.stats.attached,
+            contract: self.stats.contract,
+            ..Stats::default()
+        };
+    }
+}
@@ -0,0 +1,648 @@
+//! Decoded status registers
+
+#![allow(non_upper_case_globals)]
+
+use bitflags::bitflags;
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::pdo::Pdo;
+use crate::rdo::Rdo;
+use crate::registers::*;
+use crate::PdoChannel;
+
+/// VBUS monitoring status (TYPEC_MONITORING_STATUS_0/1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub struct MonitoringStatus {
+    /// VBUS is within the valid range of the sink
+    pub vbus_valid: bool,
+    /// VBUS is below vSafe0V
+    pub vbus_vsafe0v: bool,
+    /// VBUS is ready, i.e. the power path is enabled
+    pub vbus_ready: bool,
+    /// VBUS is below the low threshold of the negotiated voltage
+    pub vbus_low: bool,
+    /// VBUS is above the high threshold of the negotiated voltage
+    pub vbus_high: bool,
+    /// `vbus_valid` changed since the last read
+    pub vbus_valid_changed: bool,
+    /// `vbus_vsafe0v` changed since the last read
+    pub vbus_vsafe0v_changed: bool,
+    /// `vbus_ready` changed since the last read
+    pub vbus_ready_changed: bool,
+}
+
+impl MonitoringStatus {
+    pub fn from_registers(status0: u8, status1: u8) -> Self {
+        let status0 = TypeCMonitoringStatus0::from_bits_truncate(status0);
+        let status1 = TypeCMonitoringStatus1::from_bits_truncate(status1);
+        MonitoringStatus {
+            vbus_valid: status1.contains(TypeCMonitoringStatus1::VbusValidSnk),
+            vbus_vsafe0v: status1.contains(TypeCMonitoringStatus1::VbusVSafe0V),
+            vbus_ready: status1.contains(TypeCMonitoringStatus1::VbusReady),
+            vbus_low: status0.contains(TypeCMonitoringStatus0::VbusLowStatus),
+            vbus_high: status0.contains(TypeCMonitoringStatus0::VbusHighStatus),
+            vbus_valid_changed: status0.contains(TypeCMonitoringStatus0::VbusValidSnkTrans),
+            vbus_vsafe0v_changed: status0.contains(TypeCMonitoringStatus0::VbusVSafe0VTrans),
+            vbus_ready_changed: status0.contains(TypeCMonitoringStatus0::VbusReadyTrans),
+        }
+    }
+}
+
+bitflags! {
+    /// Hardware fault conditions, see [`STUSB4500::faults`](crate::STUSB4500::faults)
+    ///
+    /// The chip doesn't report thermal shutdown, an over-temperature shows up as a reset and
+    /// detach instead.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Faults: u8 {
+        /// Over-voltage on the CC pins, e.g. CC shorted to VBUS by a faulty cable (VPU_OVP_FAULT)
+        const CcOverVoltage    = 0b0000_0001;
+        /// VBUS couldn't be discharged (VBUS_DISCH_FAULT)
+        const VbusDischarge    = 0b0000_0010;
+        /// VBUS above the over-voltage threshold of the negotiated voltage (VBUS_HIGH_STATUS)
+        const VbusOverVoltage  = 0b0000_0100;
+        /// VBUS below the under-voltage threshold while attached, e.g. an overloaded or
+        /// shorted output (VBUS_LOW_STATUS)
+        const VbusUnderVoltage = 0b0000_1000;
+    }
+}
+
+impl Faults {
+    /// Decode PORT_STATUS_1, TYPEC_MONITORING_STATUS_0 and CC_HW_FAULT_STATUS_1
+    ///
+    /// VBUS is always low without a source, so [`Faults::VbusUnderVoltage`] is only reported
+    /// while attached.
+    pub fn from_registers(port_status1: u8, monitoring0: u8, cc_hw_fault1: u8) -> Self {
+        let attached = PortStatus1::from_bits_truncate(port_status1).contains(PortStatus1::Attach);
+        let monitoring0 = TypeCMonitoringStatus0::from_bits_truncate(monitoring0);
+        let cc_hw_fault1 = CCHWFaultStatus1::from_bits_truncate(cc_hw_fault1);
+
+        let mut faults = Faults::empty();
+        faults.set(
+            Faults::CcOverVoltage,
+            cc_hw_fault1.contains(CCHWFaultStatus1::VpuOvpFault),
+        );
+        faults.set(
+            Faults::VbusDischarge,
+            cc_hw_fault1.contains(CCHWFaultStatus1::VbusDischFault),
+        );
+        faults.set(
+            Faults::VbusOverVoltage,
+            monitoring0.contains(TypeCMonitoringStatus0::VbusHighStatus),
+        );
+        faults.set(
+            Faults::VbusUnderVoltage,
+            attached && monitoring0.contains(TypeCMonitoringStatus0::VbusLowStatus),
+        );
+        faults
+    }
+}
+
+/// Policy engine state (PE_FSM)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum PeState {
+    Init,
+    SoftReset,
+    HardReset,
+    SendSoftReset,
+    Bist,
+    SnkStartup,
+    SnkDiscovery,
+    SnkWaitForCapabilities,
+    SnkEvaluateCapabilities,
+    SnkSelectCapabilities,
+    SnkTransitionSink,
+    SnkReady,
+    SnkReadySending,
+    HardResetShutdown,
+    HardResetRecovery,
+    ErrorRecovery,
+    /// Undocumented state
+    Unknown(u8),
+}
+
+impl From<u8> for PeState {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => PeState::Init,
+            0x01 => PeState::SoftReset,
+            0x02 => PeState::HardReset,
+            0x03 => PeState::SendSoftReset,
+            0x04 => PeState::Bist,
+            0x12 => PeState::SnkStartup,
+            0x13 => PeState::SnkDiscovery,
+            0x14 => PeState::SnkWaitForCapabilities,
+            0x15 => PeState::SnkEvaluateCapabilities,
+            0x16 => PeState::SnkSelectCapabilities,
+            0x17 => PeState::SnkTransitionSink,
+            0x18 => PeState::SnkReady,
+            0x19 => PeState::SnkReadySending,
+            0x3A => PeState::HardResetShutdown,
+            0x3B => PeState::HardResetRecovery,
+            0x40 => PeState::ErrorRecovery,
+            other => PeState::Unknown(other),
+        }
+    }
+}
+
+impl PeState {
+    /// Whether the policy engine is between a reset or attach and SNK_READY
+    ///
+    /// RDO and VBUS change independently in this window, e.g. the RDO still holds the old
+    /// contract while VBUS already moves to the new one.
+    pub fn is_negotiating(&self) -> bool {
+        matches!(
+            self,
+            PeState::SoftReset
+                | PeState::HardReset
+                | PeState::SendSoftReset
+                | PeState::SnkStartup
+                | PeState::SnkDiscovery
+                | PeState::SnkWaitForCapabilities
+                | PeState::SnkEvaluateCapabilities
+                | PeState::SnkSelectCapabilities
+                | PeState::SnkTransitionSink
+                | PeState::HardResetShutdown
+                | PeState::HardResetRecovery
+        )
+    }
+}
+
+/// Type-C connection state machine (TYPEC_STATUS TYPEC_FSM_STATE)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum TypeCState {
+    /// Nothing attached
+    UnattachedSnk,
+    /// Rp detected on a CC line, debouncing before attaching
+    AttachWaitSnk,
+    /// Attached to a source, PD negotiation may follow
+    AttachedSnk,
+    /// Attached to a debug accessory
+    DebugAccessorySnk,
+    TrySrc,
+    UnattachedAccessory,
+    AttachWaitAccessory,
+    ErrorRecovery,
+    /// Undocumented state
+    Unknown(u8),
+}
+
+impl From<u8> for TypeCState {
+    fn from(value: u8) -> Self {
+        match value & 0x1F {
+            0x00 => TypeCState::UnattachedSnk,
+            0x01 => TypeCState::AttachWaitSnk,
+            0x02 => TypeCState::AttachedSnk,
+            0x03 => TypeCState::DebugAccessorySnk,
+            0x0C => TypeCState::TrySrc,
+            0x0D => TypeCState::UnattachedAccessory,
+            0x0E => TypeCState::AttachWaitAccessory,
+            0x13 => TypeCState::ErrorRecovery,
+            other => TypeCState::Unknown(other),
+        }
+    }
+}
+
+/// Protocol layer status (PRT_STATUS)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub struct ProtocolStatus {
+    /// A PD message was received
+    pub message_received: bool,
+    /// A hard reset was received from the source
+    pub hard_reset_received: bool,
+    /// A BIST message was received
+    pub bist_received: bool,
+}
+
+impl ProtocolStatus {
+    pub fn from_register(status: u8) -> Self {
+        let status = PRTStatus::from_bits_truncate(status);
+        ProtocolStatus {
+            message_received: status.contains(PRTStatus::MsgReceived),
+            hard_reset_received: status.contains(PRTStatus::HwResetReceived),
+            bist_received: status.contains(PRTStatus::BistReceived),
+        }
+    }
+}
+
+/// Why the active contract doesn't match what the sink asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum ContractMismatch {
+    /// No contract has been established
+    NoContract,
+    /// The RDO capability mismatch bit is set, the source can't provide the requested power
+    CapabilityMismatch,
+    /// VBUS isn't at the voltage of the highest priority sink PDO
+    Voltage {
+        /// Voltage of the highest priority sink PDO in mV
+        requested: u16,
+        /// Measured VBUS in mV
+        actual: u16,
+    },
+}
+
+/// Explicit PD contract, see [`STUSB4500::wait_for_contract`](crate::STUSB4500::wait_for_contract)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Contract {
+    pub rdo: Rdo,
+    /// Measured VBUS in mV
+    pub voltage_mv: u16,
+}
+
+impl Contract {
+    /// Granted operating current in mA
+    pub fn current_ma(&self) -> u32 {
+        self.rdo.operating_current_ma()
+    }
+
+    /// Granted power in mW, from the measured VBUS
+    pub fn power_mw(&self) -> u32 {
+        self.rdo.requested_power_mw(self.voltage_mv)
+    }
+}
+
+/// Power delivery state, see [`STUSB4500::contract_state`](crate::STUSB4500::contract_state)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum ContractState {
+    /// No source attached
+    Detached,
+    /// A contract is being (re)negotiated, e.g. after a soft reset or a new source capabilities
+    /// message. RDO and VBUS are transient and not reported. VBUS stays up across a soft reset,
+    /// only a hard reset turns it off.
+    ///
+    /// A source without PD leaves the policy engine waiting for capabilities, so bound waits
+    /// for [`Ready`](Self::Ready) with a timeout.
+    Negotiating,
+    /// Attached without an explicit contract, only Type-C power is available
+    NoContract,
+    /// Explicit contract in SNK_READY
+    Ready(Contract),
+}
+
+impl ContractState {
+    pub(crate) fn decode(attached: bool, pe: PeState, rdo: Rdo, voltage_mv: u16) -> Self {
+        if !attached {
+            return ContractState::Detached;
+        }
+        if pe.is_negotiating() {
+            return ContractState::Negotiating;
+        }
+        let ready = matches!(pe, PeState::SnkReady | PeState::SnkReadySending);
+        match (ready, rdo.position()) {
+            // The RDO is latched right after SNK_READY
+            (true, 0) => ContractState::Negotiating,
+            (true, _) => ContractState::Ready(Contract { rdo, voltage_mv }),
+            (false, _) => ContractState::NoContract,
+        }
+    }
+
+    /// The contract if [`Ready`](Self::Ready)
+    pub fn contract(&self) -> Option<&Contract> {
+        match self {
+            ContractState::Ready(contract) => Some(contract),
+            _ => None,
+        }
+    }
+
+    pub fn is_negotiating(&self) -> bool {
+        matches!(self, ContractState::Negotiating)
+    }
+}
+
+/// `Contract { voltage_mv, current_ma }`, the RDO has no `uDebug`
+#[cfg(feature = "ufmt")]
+impl ufmt::uDebug for Contract {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.debug_struct("Contract")?
+            .field("voltage_mv", &self.voltage_mv)?
+            .field("current_ma", &self.current_ma())?
+            .finish()
+    }
+}
+
+/// USB data role (PORT_STATUS_1 DATA_MODE)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum DataRole {
+    /// Upstream facing port, i.e. a USB device
+    Ufp,
+    /// Downstream facing port, i.e. a USB host
+    Dfp,
+}
+
+/// Power role (PORT_STATUS_1 POWER_MODE)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum PowerRole {
+    Sink,
+    Source,
+}
+
+/// Attached partner (PORT_STATUS_1 ATTACHED_DEVICE)
+///
+/// Values follow ST's Type-C port controller register map, codes the STUSB4500 documents as
+/// reserved decode as [`AttachedDevice::Reserved`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum AttachedDevice {
+    None,
+    /// Attached in sink mode, the partner is a source
+    Sink,
+    Source,
+    DebugAccessory,
+    AudioAccessory,
+    PoweredAccessory,
+    Reserved(u8),
+}
+
+impl AttachedDevice {
+    /// Decode PORT_STATUS_1
+    pub fn from_register(status: u8) -> Self {
+        match status >> 5 {
+            0 => AttachedDevice::None,
+            1 => AttachedDevice::Sink,
+            2 => AttachedDevice::Source,
+            3 => AttachedDevice::DebugAccessory,
+            4 => AttachedDevice::AudioAccessory,
+            5 => AttachedDevice::PoweredAccessory,
+            other => AttachedDevice::Reserved(other),
+        }
+    }
+}
+
+/// Roles of an attached port, see [`STUSB4500::device_mode`](crate::STUSB4500::device_mode)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub struct DeviceMode {
+    pub data_role: DataRole,
+    pub power_role: PowerRole,
+}
+
+impl DeviceMode {
+    /// Decode PORT_STATUS_1, `None` if not attached
+    pub fn from_register(status: u8) -> Option<Self> {
+        let status = PortStatus1::from_bits_truncate(status);
+        if !status.contains(PortStatus1::Attach) {
+            return None;
+        }
+        Some(DeviceMode {
+            data_role: if status.contains(PortStatus1::DataMode) {
+                DataRole::Dfp
+            } else {
+                DataRole::Ufp
+            },
+            power_role: if status.contains(PortStatus1::PowerMode) {
+                PowerRole::Source
+            } else {
+                PowerRole::Sink
+            },
+        })
+    }
+}
+
+/// Current advertised by the source's Rp (CC_STATUS)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum TypeCCurrent {
+    /// Default USB power, 500mA (USB 2.0) or 900mA (USB 3.x)
+    Default,
+    /// 1.5A at 5V
+    Current1A5,
+    /// 3.0A at 5V
+    Current3A0,
+}
+
+impl TypeCCurrent {
+    /// Decode CC_STATUS, `None` if both CC lines are open
+    pub fn from_cc_status(status: u8) -> Option<Self> {
+        let cc1 = status & 0x03;
+        let cc2 = (status >> 2) & 0x03;
+        match cc1.max(cc2) {
+            1 => Some(TypeCCurrent::Default),
+            2 => Some(TypeCCurrent::Current1A5),
+            3 => Some(TypeCCurrent::Current3A0),
+            _ => None,
+        }
+    }
+
+    /// Current in mA, using 500mA for default USB power
+    pub fn current_ma(&self) -> u16 {
+        match self {
+            TypeCCurrent::Default => 500,
+            TypeCCurrent::Current1A5 => 1500,
+            TypeCCurrent::Current3A0 => 3000,
+        }
+    }
+}
+
+/// ALERT_STATUS_1 through PRT_STATUS from one burst read, see
+/// [`STUSB4500::alert_snapshot`](crate::STUSB4500::alert_snapshot)
+///
+/// `raw` keeps the bytes as read for logging, `raw[i]` is register `ALERT_STATUS_1 + i`. The
+/// other fields decode them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlertSnapshot {
+    pub raw: [u8; 12],
+    /// Active alerts not masked by ALERT_STATUS_1_MASK
+    pub alerts: Alert,
+    pub attached: bool,
+    pub attached_device: AttachedDevice,
+    pub monitoring: MonitoringStatus,
+    pub cc_current: Option<TypeCCurrent>,
+    pub typec: TypeCState,
+    pub protocol: ProtocolStatus,
+    pub faults: Faults,
+}
+
+impl AlertSnapshot {
+    pub fn from_registers(raw: [u8; 12]) -> Self {
+        let [alert, mask, _port0, port1, mon0, mon1, cc, _fault0, fault1, _pd_typec, typec, prt] =
+            raw;
+        AlertSnapshot {
+            raw,
+            alerts: Alert::from_bits_truncate(alert & !mask),
+            attached: PortStatus1::from_bits_truncate(port1).contains(PortStatus1::Attach),
+            attached_device: AttachedDevice::from_register(port1),
+            monitoring: MonitoringStatus::from_registers(mon0, mon1),
+            cc_current: TypeCCurrent::from_cc_status(cc),
+            typec: TypeCState::from(typec),
+            protocol: ProtocolStatus::from_register(prt),
+            faults: Faults::from_registers(port1, mon0, fault1),
+        }
+    }
+}
+
+/// Decode MONITORING_CTRL_1 (VBUS_SNK, 100mV units) to mV
+pub(crate) fn vbus_mv(monitoring_ctrl1: u8) -> u16 {
+    u16::from(monitoring_ctrl1) * 100
+}
+
+/// Decode DPM_PDO_NUMB
+pub(crate) fn num_pdo(dpm_pdo_numb: u8) -> u8 {
+    dpm_pdo_numb & 0x07
+}
+
+/// Status decoders over a register window mirrored elsewhere, e.g. by DMA or a coprocessor
+///
+/// `regs[i]` is the register at address `i`, so the window has to start at address 0x00 and
+/// cover at least [`StatusView::LEN`] bytes up to the end of RDO_REG_STATUS. Nothing is read
+/// from the bus and read-to-clear registers are not cleared. The decoding is the same as the
+/// driver's:
+///
+/// ```ignore
+/// let view = StatusView::from_slice(&mirror).ok_or(TooShort)?;
+/// if view.is_attached() {
+///     let contract = view.active_contract();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusView<'a> {
+    regs: &'a [u8],
+}
+
+impl<'a> StatusView<'a> {
+    /// Bytes needed for a view, register addresses 0x00 through 0x94
+    pub const LEN: usize = Register::RDORegStatus as usize + 4;
+
+    /// View over `regs`, `None` if shorter than [`LEN`](Self::LEN)
+    pub fn from_slice(regs: &'a [u8]) -> Option<Self> {
+        if regs.len() < Self::LEN {
+            return None;
+        }
+        Some(StatusView { regs })
+    }
+
+    /// Raw value of `register`
+    pub fn raw(&self, register: Register) -> u8 {
+        self.regs.get(register as usize).copied().unwrap_or(0)
+    }
+
+    fn word(&self, register: Register) -> u32 {
+        let start = register as usize;
+        self.regs
+            .get(start..start + 4)
+            .map_or(0, LittleEndian::read_u32)
+    }
+
+    /// See [`STUSB4500::get_alerts`](crate::STUSB4500::get_alerts)
+    pub fn alerts(&self) -> Alert {
+        Alert::from_bits_truncate(self.raw(Register::AlertStatus1))
+    }
+
+    /// See [`STUSB4500::get_alerts_mask`](crate::STUSB4500::get_alerts_mask)
+    pub fn alerts_mask(&self) -> AlertMask {
+        AlertMask::from_bits_retain(self.raw(Register::AlertStatus1Mask))
+    }
+
+    /// ALERT_STATUS_1 through PRT_STATUS decoded like
+    /// [`STUSB4500::alert_snapshot`](crate::STUSB4500::alert_snapshot)
+    pub fn alert_snapshot(&self) -> AlertSnapshot {
+        let mut raw = [0x00; 12];
+        let start = Register::AlertStatus1 as usize;
+        if let Some(window) = self.regs.get(start..start + raw.len()) {
+            raw.copy_from_slice(window);
+        }
+        AlertSnapshot::from_registers(raw)
+    }
+
+    /// See [`STUSB4500::is_attached`](crate::STUSB4500::is_attached)
+    pub fn is_attached(&self) -> bool {
+        PortStatus1::from_bits_truncate(self.raw(Register::PortStatus1))
+            .contains(PortStatus1::Attach)
+    }
+
+    /// See [`STUSB4500::device_mode`](crate::STUSB4500::device_mode)
+    pub fn device_mode(&self) -> Option<DeviceMode> {
+        DeviceMode::from_register(self.raw(Register::PortStatus1))
+    }
+
+    /// See [`STUSB4500::attached_device`](crate::STUSB4500::attached_device)
+    pub fn attached_device(&self) -> AttachedDevice {
+        AttachedDevice::from_register(self.raw(Register::PortStatus1))
+    }
+
+    /// See [`STUSB4500::monitoring_status`](crate::STUSB4500::monitoring_status)
+    pub fn monitoring_status(&self) -> MonitoringStatus {
+        MonitoringStatus::from_registers(
+            self.raw(Register::TypeCMonitoringStatus0),
+            self.raw(Register::TypeCMonitoringStatus1),
+        )
+    }
+
+    /// See [`STUSB4500::typec_current`](crate::STUSB4500::typec_current)
+    pub fn typec_current(&self) -> Option<TypeCCurrent> {
+        TypeCCurrent::from_cc_status(self.raw(Register::CCStatus))
+    }
+
+    /// See [`STUSB4500::typec_state`](crate::STUSB4500::typec_state)
+    pub fn typec_state(&self) -> TypeCState {
+        TypeCState::from(self.raw(Register::TypeCStatus))
+    }
+
+    /// See [`STUSB4500::prt_status`](crate::STUSB4500::prt_status)
+    pub fn prt_status(&self) -> ProtocolStatus {
+        ProtocolStatus::from_register(self.raw(Register::PRTStatus))
+    }
+
+    /// See [`STUSB4500::faults`](crate::STUSB4500::faults)
+    pub fn faults(&self) -> Faults {
+        Faults::from_registers(
+            self.raw(Register::PortStatus1),
+            self.raw(Register::TypeCMonitoringStatus0),
+            self.raw(Register::CCHWFaultStatus1),
+        )
+    }
+
+    /// See [`STUSB4500::pe_state`](crate::STUSB4500::pe_state)
+    pub fn pe_state(&self) -> PeState {
+        PeState::from(self.raw(Register::PEFSM))
+    }
+
+    /// See [`STUSB4500::get_voltage_mv`](crate::STUSB4500::get_voltage_mv)
+    pub fn voltage_mv(&self) -> u16 {
+        vbus_mv(self.raw(Register::MonitoringCtrl1))
+    }
+
+    /// See [`STUSB4500::get_num_pdo`](crate::STUSB4500::get_num_pdo)
+    pub fn num_pdo(&self) -> u8 {
+        num_pdo(self.raw(Register::DPMPDONumb))
+    }
+
+    /// See [`STUSB4500::get_pdo`](crate::STUSB4500::get_pdo), `None` for an invalid PDO
+    pub fn pdo(&self, channel: PdoChannel) -> Option<Pdo> {
+        Pdo::from_bits(self.word(channel.register()))
+    }
+
+    /// See [`STUSB4500::get_current_rdo`](crate::STUSB4500::get_current_rdo)
+    pub fn current_rdo(&self) -> Rdo {
+        Rdo(self.word(Register::RDORegStatus))
+    }
+
+    /// See [`STUSB4500::contract_state`](crate::STUSB4500::contract_state)
+    pub fn contract_state(&self) -> ContractState {
+        ContractState::decode(
+            self.is_attached(),
+            self.pe_state(),
+            self.current_rdo(),
+            self.voltage_mv(),
+        )
+    }
+
+    /// See [`STUSB4500::get_active_contract`](crate::STUSB4500::get_active_contract)
+    pub fn active_contract(&self) -> Option<(u16, u32)> {
+        let rdo = self.current_rdo();
+        if rdo.position() == 0 {
+            return None;
+        }
+        Some((self.voltage_mv(), rdo.operating_current_ma()))
+    }
+}
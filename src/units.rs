@@ -0,0 +1,32 @@
+//! Unit typed API using [`uom`] quantities, enabled by the `uom` feature
+//!
+//! The PDO and [`NvmConfig`](crate::config::NvmConfig) conversions live in
+//! `stusb4500_types::units`, this adds the driver's measurements.
+
+use hal::delay::DelayNs;
+use hal::i2c::I2c;
+use uom::si::electric_potential::millivolt;
+use uom::si::f32::{ElectricPotential, Power};
+use uom::si::power::milliwatt;
+
+use crate::{Error, STUSB4500};
+
+impl<I2C, E, DELAY, ALERT> STUSB4500<I2C, DELAY, ALERT>
+where
+    I2C: I2c<Error = E>,
+    DELAY: DelayNs,
+{
+    /// VBUS voltage
+    pub fn get_voltage_si(&mut self) -> Result<ElectricPotential, Error<E>> {
+        Ok(ElectricPotential::new::<millivolt>(
+            self.get_voltage_mv()? as f32
+        ))
+    }
+
+    /// Power of the active contract, `None` without a contract
+    pub fn get_negotiated_power_si(&mut self) -> Result<Option<Power>, Error<E>> {
+        Ok(self
+            .get_negotiated_power()?
+            .map(|mw| Power::new::<milliwatt>(mw as f32)))
+    }
+}
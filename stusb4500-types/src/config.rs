@@ -0,0 +1,1060 @@
+//! Typed view of the NVM configuration
+//!
+//! [`NvmConfig`] decodes and encodes the documented fields of an [`NvmImage`] in place, all other
+//! bits are left untouched. Field locations follow ST's NVM map (as used by the [GUI][gui]).
+//!
+//! [gui]: https://www.st.com/en/embedded-software/stsw-stusb002.html
+
+use crate::image::NvmImage;
+use crate::pdo::Pdo;
+use crate::PdoChannel;
+
+/// Errors from setting [`NvmConfig`] fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// Value can't be represented by the NVM field
+    OutOfRange,
+    /// PDO1 is always 5V
+    FixedPdo1,
+    /// Only fixed PDOs can be stored in the NVM
+    UnsupportedPdo,
+    /// The VBUS monitoring window of the PDO doesn't enclose its voltage, see
+    /// [`NvmConfig::check_vbus_monitoring`]
+    MonitoringWindow(PdoChannel),
+    /// No documented field has this name, see [`NvmConfig::set_field_raw`]
+    UnknownField,
+}
+
+impl core::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConfigError::OutOfRange => write!(f, "value out of range"),
+            ConfigError::FixedPdo1 => write!(f, "PDO1 is always 5V"),
+            ConfigError::UnsupportedPdo => write!(f, "only fixed PDOs can be stored in the NVM"),
+            ConfigError::MonitoringWindow(pdo) => write!(
+                f,
+                "VBUS monitoring thresholds of {:?} don't enclose its voltage",
+                pdo
+            ),
+            ConfigError::UnknownField => write!(f, "unknown NVM field"),
+        }
+    }
+}
+
+/// Options for [`NvmConfig::from_pdos`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FromPdosOptions {
+    /// Image providing all fields not set from the PDOs
+    pub base: NvmImage,
+    /// Flexible current in mA, PDOs requesting exactly this current use I_SNK_PDO_FLEX
+    pub flex_current: Option<u16>,
+}
+
+/// POWER_OK pin configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerOk {
+    Config1 = 0,
+    Config2 = 2,
+    Config3 = 3,
+}
+
+/// GPIO pin function (GPIO_CFG)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpioCfg {
+    /// Output driven by software, see `STUSB4500::set_gpio_low`
+    SwCtrl = 0,
+    /// Low while the Type-C state machine is in error recovery
+    ErrorRecovery = 1,
+    /// Debug accessory attached
+    Debug = 2,
+    /// Low while sinking power, like VBUS_EN_SNK
+    SinkPower = 3,
+}
+
+/// Decoded value of an NVM field, see [`FieldValue`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decoded {
+    Count(u8),
+    Millivolts(u16),
+    Milliamps(u16),
+    /// The PDO uses I_SNK_PDO_FLEX
+    FlexCurrent,
+    Milliseconds(u16),
+    Percent(u8),
+    Flag(bool),
+    PowerOk(PowerOk),
+    Gpio(GpioCfg),
+    /// Free for application use
+    Data(u16),
+}
+
+/// A documented NVM field in raw and decoded form, see [`NvmConfig::fields`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldValue {
+    /// Datasheet/GUI name, as in the `serde` representation
+    pub name: &'static str,
+    /// Bits as stored, the parts of fields split across bytes joined low part first
+    pub raw: u16,
+    /// `None` if the raw value is reserved or outside the documented range
+    pub decoded: Option<Decoded>,
+}
+
+impl FieldValue {
+    /// Whether the raw value has a documented meaning
+    pub fn is_recognized(&self) -> bool {
+        self.decoded.is_some()
+    }
+}
+
+/// Location of a field: sector, byte in sector and mask within the byte
+#[derive(Clone, Copy)]
+struct Field(usize, usize, u8);
+
+/// A documented field, its parts low bits first
+struct FieldDef {
+    name: &'static str,
+    parts: &'static [Field],
+    decode: fn(&NvmConfig) -> Option<Decoded>,
+}
+
+const VENDOR_DATA_L: Field = Field(0, 0, 0xFF);
+const VENDOR_DATA_H: Field = Field(0, 1, 0xFF);
+const VBUS_DISCH_TIME_TO_0V: Field = Field(1, 2, 0xF0);
+const VBUS_DISCH_TIME_TRANSITION: Field = Field(1, 2, 0x0F);
+const I_SNK_PDO1: Field = Field(3, 2, 0xF0);
+const SNK_UNCONS_POWER: Field = Field(3, 2, 0x08);
+const DPM_SNK_PDO_NUMB: Field = Field(3, 2, 0x06);
+const USB_COMM_CAPABLE: Field = Field(3, 2, 0x01);
+const I_SNK_PDO2: Field = Field(3, 4, 0x0F);
+const I_SNK_PDO3: Field = Field(3, 5, 0xF0);
+const V_SNK_PDO2_L: Field = Field(4, 0, 0xC0);
+const V_SNK_PDO2_H: Field = Field(4, 1, 0xFF);
+const V_SNK_PDO3_L: Field = Field(4, 2, 0xFF);
+const V_SNK_PDO3_H: Field = Field(4, 3, 0x03);
+const I_SNK_PDO_FLEX_L: Field = Field(4, 3, 0xFC);
+const I_SNK_PDO_FLEX_H: Field = Field(4, 4, 0x0F);
+const POWER_OK_CFG: Field = Field(4, 4, 0x60);
+const GPIO_CFG: Field = Field(1, 0, 0x30);
+const SHIFT_VBUS_HL1: Field = Field(3, 3, 0xF0);
+const SHIFT_VBUS_LL2: Field = Field(3, 4, 0xF0);
+const SHIFT_VBUS_HL2: Field = Field(3, 5, 0x0F);
+const SHIFT_VBUS_LL3: Field = Field(3, 6, 0x0F);
+const SHIFT_VBUS_HL3: Field = Field(3, 6, 0xF0);
+const POWER_ONLY_ABOVE_5V: Field = Field(4, 6, 0x08);
+const REQ_SRC_CURRENT: Field = Field(4, 6, 0x10);
+
+const fn field(
+    name: &'static str,
+    parts: &'static [Field],
+    decode: fn(&NvmConfig) -> Option<Decoded>,
+) -> FieldDef {
+    FieldDef {
+        name,
+        parts,
+        decode,
+    }
+}
+
+fn current(current: Option<u16>) -> Option<Decoded> {
+    Some(current.map_or(Decoded::FlexCurrent, Decoded::Milliamps))
+}
+
+fn voltage(voltage: u16) -> Option<Decoded> {
+    (5000..=20000)
+        .contains(&voltage)
+        .then_some(Decoded::Millivolts(voltage))
+}
+
+/// All documented fields in NVM order, the other bits are reserved
+const FIELDS: [FieldDef; 21] = [
+    field("VENDOR_DATA", &[VENDOR_DATA_L, VENDOR_DATA_H], |c| {
+        Some(Decoded::Data(c.vendor_data()))
+    }),
+    field("GPIO_CFG", &[GPIO_CFG], |c| {
+        Some(Decoded::Gpio(c.gpio_cfg()))
+    }),
+    field("VBUS_DISCH_TIME_TO_0V", &[VBUS_DISCH_TIME_TO_0V], |c| {
+        Some(Decoded::Milliseconds(c.discharge_time_to_0v_ms()))
+    }),
+    field(
+        "VBUS_DISCH_TIME_TRANSITION",
+        &[VBUS_DISCH_TIME_TRANSITION],
+        |c| Some(Decoded::Milliseconds(c.discharge_time_transition_ms())),
+    ),
+    field("I_SNK_PDO1", &[I_SNK_PDO1], |c| {
+        current(c.pdo_current_ma(PdoChannel::PDO1))
+    }),
+    field("SNK_UNCONS_POWER", &[SNK_UNCONS_POWER], |c| {
+        Some(Decoded::Flag(c.unconstrained_power()))
+    }),
+    field("DPM_SNK_PDO_NUMB", &[DPM_SNK_PDO_NUMB], |c| {
+        (1..=3)
+            .contains(&c.pdo_count())
+            .then_some(Decoded::Count(c.pdo_count()))
+    }),
+    field("USB_COMM_CAPABLE", &[USB_COMM_CAPABLE], |c| {
+        Some(Decoded::Flag(c.usb_comm_capable()))
+    }),
+    field("SHIFT_VBUS_HL1", &[SHIFT_VBUS_HL1], |c| {
+        Some(Decoded::Percent(
+            c.vbus_high_shift_percent(PdoChannel::PDO1),
+        ))
+    }),
+    field("I_SNK_PDO2", &[I_SNK_PDO2], |c| {
+        current(c.pdo_current_ma(PdoChannel::PDO2))
+    }),
+    field("SHIFT_VBUS_LL2", &[SHIFT_VBUS_LL2], |c| {
+        c.vbus_low_shift_percent(PdoChannel::PDO2)
+            .map(Decoded::Percent)
+    }),
+    field("SHIFT_VBUS_HL2", &[SHIFT_VBUS_HL2], |c| {
+        Some(Decoded::Percent(
+            c.vbus_high_shift_percent(PdoChannel::PDO2),
+        ))
+    }),
+    field("I_SNK_PDO3", &[I_SNK_PDO3], |c| {
+        current(c.pdo_current_ma(PdoChannel::PDO3))
+    }),
+    field("SHIFT_VBUS_LL3", &[SHIFT_VBUS_LL3], |c| {
+        c.vbus_low_shift_percent(PdoChannel::PDO3)
+            .map(Decoded::Percent)
+    }),
+    field("SHIFT_VBUS_HL3", &[SHIFT_VBUS_HL3], |c| {
+        Some(Decoded::Percent(
+            c.vbus_high_shift_percent(PdoChannel::PDO3),
+        ))
+    }),
+    field("V_SNK_PDO2", &[V_SNK_PDO2_L, V_SNK_PDO2_H], |c| {
+        voltage(c.pdo_voltage_mv(PdoChannel::PDO2))
+    }),
+    field("V_SNK_PDO3", &[V_SNK_PDO3_L, V_SNK_PDO3_H], |c| {
+        voltage(c.pdo_voltage_mv(PdoChannel::PDO3))
+    }),
+    field(
+        "I_SNK_PDO_FLEX",
+        &[I_SNK_PDO_FLEX_L, I_SNK_PDO_FLEX_H],
+        |c| {
+            let current = c.flex_current_ma();
+            (current <= 5000).then_some(Decoded::Milliamps(current))
+        },
+    ),
+    field("POWER_OK_CFG", &[POWER_OK_CFG], |c| {
+        c.power_ok().map(Decoded::PowerOk)
+    }),
+    field("REQ_SRC_CURRENT", &[REQ_SRC_CURRENT], |c| {
+        Some(Decoded::Flag(c.req_src_current()))
+    }),
+    field("POWER_ONLY_ABOVE_5V", &[POWER_ONLY_ABOVE_5V], |c| {
+        Some(Decoded::Flag(c.power_only_above_5v()))
+    }),
+];
+
+/// Typed NVM configuration
+///
+/// Wraps an [`NvmImage`] so decoding and re-encoding an image is always byte exact.
+///
+/// With the `serde` feature it (de)serializes to named fields matching the datasheet/GUI
+/// parameters, voltages in mV, currents in mA (0 = use I_SNK_PDO_FLEX) and times in ms:
+///
+/// ```json
+/// {
+///   "NVM": [[0, 0, 176, 171, 0, 69, 0, 0], ...],
+///   "DPM_SNK_PDO_NUMB": 3,
+///   "V_SNK_PDO2": 15000,
+///   "V_SNK_PDO3": 20000,
+///   "I_SNK_PDO1": 1500,
+///   "I_SNK_PDO2": 1500,
+///   "I_SNK_PDO3": 1000,
+///   "I_SNK_PDO_FLEX": 2000,
+///   "SNK_UNCONS_POWER": false,
+///   "USB_COMM_CAPABLE": false,
+///   "POWER_ONLY_ABOVE_5V": false,
+///   "REQ_SRC_CURRENT": false,
+///   "POWER_OK_CFG": 2,
+///   "GPIO_CFG": 1,
+///   "VBUS_DISCH_TIME_TO_0V": 756,
+///   "VBUS_DISCH_TIME_TRANSITION": 288,
+///   "VENDOR_DATA": 0
+/// }
+/// ```
+///
+/// `NVM` holds the raw image including undocumented bits. When deserializing all fields are
+/// optional: missing fields keep their value from `NVM`, or from the factory image if `NVM` is
+/// missing too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "fields::Fields", into = "fields::Fields")
+)]
+pub struct NvmConfig {
+    image: NvmImage,
+}
+
+impl NvmConfig {
+    /// Factory default configuration
+    pub const DEFAULT: NvmConfig = NvmConfig::new(NvmImage::DEFAULT);
+
+    pub const fn new(image: NvmImage) -> Self {
+        NvmConfig { image }
+    }
+
+    /// Start building a configuration from the factory default
+    pub fn builder() -> NvmConfigBuilder {
+        NvmConfigBuilder {
+            config: NvmConfig::default(),
+            error: None,
+        }
+    }
+
+    /// The encoded NVM image
+    pub const fn image(&self) -> NvmImage {
+        self.image
+    }
+
+    /// Map one to three fixed sink PDOs to the NVM fields
+    ///
+    /// The first PDO must be 5V, its unconstrained power and USB communications capable flags
+    /// are stored as SNK_UNCONS_POWER and USB_COMM_CAPABLE. Currents that don't use the
+    /// flexible current are rounded down to the NVM current steps.
+    pub fn from_pdos(pdos: &[Pdo], options: FromPdosOptions) -> Result<Self, ConfigError> {
+        let mut config = NvmConfig::new(options.base);
+        config.set_pdo_count(pdos.len() as u8)?;
+        if let Some(current) = options.flex_current {
+            config.set_flex_current_ma(current)?;
+        }
+
+        for (channel, pdo) in PdoChannel::iter().zip(pdos) {
+            let Pdo::Fixed(pdo) = pdo else {
+                return Err(ConfigError::UnsupportedPdo);
+            };
+            let voltage = pdo.voltage() as u16 * 50;
+            let current = pdo.current() as u16 * 10;
+
+            if channel == PdoChannel::PDO1 {
+                if voltage != 5000 {
+                    return Err(ConfigError::FixedPdo1);
+                }
+                config.set_unconstrained_power(pdo.unconstrained_power());
+                config.set_usb_comm_capable(pdo.usb_communications_capable());
+            } else {
+                config.set_pdo_voltage_mv(channel, voltage)?;
+            }
+
+            if options.flex_current == Some(current) {
+                config.set_pdo_flex_current(channel);
+            } else {
+                config.set_pdo_current_ma(channel, current)?;
+            }
+        }
+        Ok(config)
+    }
+
+    fn get(&self, Field(sector, byte, mask): Field) -> u8 {
+        let byte = self.image.0.get(sector).and_then(|s| s.get(byte));
+        (byte.copied().unwrap_or_default() & mask) >> mask.trailing_zeros()
+    }
+
+    fn set(&mut self, Field(sector, byte, mask): Field, value: u8) {
+        if let Some(byte) = self.image.0.get_mut(sector).and_then(|s| s.get_mut(byte)) {
+            *byte = (*byte & !mask) | ((value << mask.trailing_zeros()) & mask);
+        }
+    }
+
+    /// Number of sink PDOs advertised (1-3)
+    pub fn pdo_count(&self) -> u8 {
+        self.get(DPM_SNK_PDO_NUMB)
+    }
+
+    pub fn set_pdo_count(&mut self, count: u8) -> Result<(), ConfigError> {
+        match count {
+            1..=3 => {
+                self.set(DPM_SNK_PDO_NUMB, count);
+                Ok(())
+            }
+            _ => Err(ConfigError::OutOfRange),
+        }
+    }
+
+    /// PDO voltage in mV
+    pub fn pdo_voltage_mv(&self, pdo: PdoChannel) -> u16 {
+        let units = match pdo {
+            PdoChannel::PDO1 => return 5000,
+            PdoChannel::PDO2 => {
+                (self.get(V_SNK_PDO2_H) as u16) << 2 | self.get(V_SNK_PDO2_L) as u16
+            }
+            PdoChannel::PDO3 => {
+                (self.get(V_SNK_PDO3_H) as u16) << 8 | self.get(V_SNK_PDO3_L) as u16
+            }
+        };
+        units * 50
+    }
+
+    /// Set PDO voltage in mV (5-20V), rounded down to 50mV steps.
+    pub fn set_pdo_voltage_mv(&mut self, pdo: PdoChannel, voltage: u16) -> Result<(), ConfigError> {
+        if !(5000..=20000).contains(&voltage) {
+            return Err(ConfigError::OutOfRange);
+        }
+        let units = voltage / 50;
+        match pdo {
+            PdoChannel::PDO1 => return Err(ConfigError::FixedPdo1),
+            PdoChannel::PDO2 => {
+                self.set(V_SNK_PDO2_L, units as u8);
+                self.set(V_SNK_PDO2_H, (units >> 2) as u8);
+            }
+            PdoChannel::PDO3 => {
+                self.set(V_SNK_PDO3_L, units as u8);
+                self.set(V_SNK_PDO3_H, (units >> 8) as u8);
+            }
+        }
+        Ok(())
+    }
+
+    fn current_field(pdo: PdoChannel) -> Field {
+        match pdo {
+            PdoChannel::PDO1 => I_SNK_PDO1,
+            PdoChannel::PDO2 => I_SNK_PDO2,
+            PdoChannel::PDO3 => I_SNK_PDO3,
+        }
+    }
+
+    /// PDO current in mA, `None` if the PDO uses the flexible current
+    pub fn pdo_current_ma(&self, pdo: PdoChannel) -> Option<u16> {
+        match self.get(Self::current_field(pdo)) as u16 {
+            0 => None,
+            n @ 1..=10 => Some(250 + n * 250),
+            n => Some(n * 500 - 2500),
+        }
+    }
+
+    /// Set PDO current in mA (0.5-5A)
+    ///
+    /// The NVM uses 250mA steps up to 3A and 500mA steps above, values in between are rounded
+    /// down.
+    pub fn set_pdo_current_ma(&mut self, pdo: PdoChannel, current: u16) -> Result<(), ConfigError> {
+        let code = match current {
+            500..=2999 => current / 250 - 1,
+            3000..=5000 => current / 500 + 5,
+            _ => return Err(ConfigError::OutOfRange),
+        };
+        self.set(Self::current_field(pdo), code as u8);
+        Ok(())
+    }
+
+    /// The flexible current in mA (I_SNK_PDO_FLEX), shared by all PDOs using it
+    pub fn flex_current_ma(&self) -> u16 {
+        ((self.get(I_SNK_PDO_FLEX_H) as u16) << 6 | self.get(I_SNK_PDO_FLEX_L) as u16) * 10
+    }
+
+    /// Set the flexible current in mA (0-5A), rounded down to 10mA steps
+    pub fn set_flex_current_ma(&mut self, current: u16) -> Result<(), ConfigError> {
+        if current > 5000 {
+            return Err(ConfigError::OutOfRange);
+        }
+        let units = current / 10;
+        self.set(I_SNK_PDO_FLEX_L, units as u8);
+        self.set(I_SNK_PDO_FLEX_H, (units >> 6) as u8);
+        Ok(())
+    }
+
+    /// Make a PDO use the flexible current instead of its own current field
+    pub fn set_pdo_flex_current(&mut self, pdo: PdoChannel) {
+        self.set(Self::current_field(pdo), 0);
+    }
+
+    /// Current in mA requested by a PDO, resolving the flexible current
+    pub fn pdo_effective_current_ma(&self, pdo: PdoChannel) -> u16 {
+        self.pdo_current_ma(pdo)
+            .unwrap_or_else(|| self.flex_current_ma())
+    }
+
+    /// VBUS discharge time to 0V in ms (DISCH_TIME_TO_0V)
+    pub fn discharge_time_to_0v_ms(&self) -> u16 {
+        self.get(VBUS_DISCH_TIME_TO_0V) as u16 * 84
+    }
+
+    /// Set VBUS discharge time to 0V in ms (0-1260ms), rounded down to 84ms steps
+    pub fn set_discharge_time_to_0v_ms(&mut self, time: u16) -> Result<(), ConfigError> {
+        if time > 15 * 84 {
+            return Err(ConfigError::OutOfRange);
+        }
+        self.set(VBUS_DISCH_TIME_TO_0V, (time / 84) as u8);
+        Ok(())
+    }
+
+    /// VBUS discharge time on a PDO transition in ms (DISCH_TIME_TRANSITION)
+    pub fn discharge_time_transition_ms(&self) -> u16 {
+        self.get(VBUS_DISCH_TIME_TRANSITION) as u16 * 24
+    }
+
+    /// Set VBUS discharge time on a PDO transition in ms (0-360ms), rounded down to 24ms steps
+    pub fn set_discharge_time_transition_ms(&mut self, time: u16) -> Result<(), ConfigError> {
+        if time > 15 * 24 {
+            return Err(ConfigError::OutOfRange);
+        }
+        self.set(VBUS_DISCH_TIME_TRANSITION, (time / 24) as u8);
+        Ok(())
+    }
+
+    /// Over voltage threshold above the PDO voltage in percent (SHIFT_VBUS_HLx)
+    pub fn vbus_high_shift_percent(&self, pdo: PdoChannel) -> u8 {
+        let field = match pdo {
+            PdoChannel::PDO1 => SHIFT_VBUS_HL1,
+            PdoChannel::PDO2 => SHIFT_VBUS_HL2,
+            PdoChannel::PDO3 => SHIFT_VBUS_HL3,
+        };
+        self.get(field) + 5
+    }
+
+    /// Set the over voltage threshold above the PDO voltage in percent (5-20%)
+    pub fn set_vbus_high_shift_percent(
+        &mut self,
+        pdo: PdoChannel,
+        percent: u8,
+    ) -> Result<(), ConfigError> {
+        if !(5..=20).contains(&percent) {
+            return Err(ConfigError::OutOfRange);
+        }
+        let field = match pdo {
+            PdoChannel::PDO1 => SHIFT_VBUS_HL1,
+            PdoChannel::PDO2 => SHIFT_VBUS_HL2,
+            PdoChannel::PDO3 => SHIFT_VBUS_HL3,
+        };
+        self.set(field, percent - 5);
+        Ok(())
+    }
+
+    /// Under voltage threshold below the PDO voltage in percent (SHIFT_VBUS_LLx), `None` for
+    /// PDO1 which uses the fixed 5V UVLO
+    pub fn vbus_low_shift_percent(&self, pdo: PdoChannel) -> Option<u8> {
+        match pdo {
+            PdoChannel::PDO1 => None,
+            PdoChannel::PDO2 => Some(self.get(SHIFT_VBUS_LL2) + 5),
+            PdoChannel::PDO3 => Some(self.get(SHIFT_VBUS_LL3) + 5),
+        }
+    }
+
+    /// Set the under voltage threshold below the PDO voltage in percent (5-20%), PDO1 can't be
+    /// changed
+    pub fn set_vbus_low_shift_percent(
+        &mut self,
+        pdo: PdoChannel,
+        percent: u8,
+    ) -> Result<(), ConfigError> {
+        let field = match pdo {
+            PdoChannel::PDO1 => return Err(ConfigError::FixedPdo1),
+            PdoChannel::PDO2 => SHIFT_VBUS_LL2,
+            PdoChannel::PDO3 => SHIFT_VBUS_LL3,
+        };
+        if !(5..=20).contains(&percent) {
+            return Err(ConfigError::OutOfRange);
+        }
+        self.set(field, percent - 5);
+        Ok(())
+    }
+
+    /// Under and over voltage thresholds of a PDO in mV, rounded down
+    pub fn vbus_thresholds_mv(&self, pdo: PdoChannel) -> (Option<u16>, u16) {
+        let voltage = self.pdo_voltage_mv(pdo) as u32;
+        let low = self
+            .vbus_low_shift_percent(pdo)
+            .map(|percent| (voltage * (100 - percent as u32) / 100) as u16);
+        let high = voltage * (100 + self.vbus_high_shift_percent(pdo) as u32) / 100;
+        (low, high as u16)
+    }
+
+    /// Check that the VBUS monitoring window of each active PDO encloses its voltage
+    ///
+    /// The chip only compares VBUS against the window of the current contract, so the windows of
+    /// different PDOs may overlap.
+    pub fn check_vbus_monitoring(&self) -> Result<(), ConfigError> {
+        let active = self.pdo_count().clamp(1, 3) as usize;
+        for pdo in PdoChannel::iter().take(active) {
+            let voltage = self.pdo_voltage_mv(pdo);
+            let (low, high) = self.vbus_thresholds_mv(pdo);
+            if low.is_some_and(|low| low >= voltage) || high <= voltage {
+                return Err(ConfigError::MonitoringWindow(pdo));
+            }
+        }
+        Ok(())
+    }
+
+    /// Derive the VBUS monitoring thresholds from the active PDO voltages
+    ///
+    /// The gap between two neighbouring PDOs is split in the middle, each side gets its half
+    /// clamped to the 5-20% range. The over voltage threshold of the highest PDO is kept.
+    pub fn set_vbus_monitoring_auto(&mut self) -> Result<(), ConfigError> {
+        self.check_vbus_monitoring()?;
+        let active = self.pdo_count().clamp(1, 3) as usize;
+        let mut channels = PdoChannel::iter().take(active);
+        let Some(mut lower) = channels.next() else {
+            return Ok(());
+        };
+        for upper in channels {
+            let low_mv = self.pdo_voltage_mv(lower) as u32;
+            let high_mv = self.pdo_voltage_mv(upper) as u32;
+            let half = low_mv.abs_diff(high_mv) / 2;
+            let high_shift = (half * 100 / low_mv).clamp(5, 20) as u8;
+            let low_shift = (half * 100 / high_mv).clamp(5, 20) as u8;
+            self.set_vbus_high_shift_percent(lower, high_shift)?;
+            self.set_vbus_low_shift_percent(upper, low_shift)?;
+            lower = upper;
+        }
+        Ok(())
+    }
+
+    /// POWER_OK pin configuration, `None` for the reserved setting
+    pub fn power_ok(&self) -> Option<PowerOk> {
+        match self.get(POWER_OK_CFG) {
+            0 => Some(PowerOk::Config1),
+            2 => Some(PowerOk::Config2),
+            3 => Some(PowerOk::Config3),
+            _ => None,
+        }
+    }
+
+    pub fn set_power_ok(&mut self, cfg: PowerOk) {
+        self.set(POWER_OK_CFG, cfg as u8);
+    }
+
+    /// GPIO pin function
+    pub fn gpio_cfg(&self) -> GpioCfg {
+        match self.get(GPIO_CFG) {
+            0 => GpioCfg::SwCtrl,
+            1 => GpioCfg::ErrorRecovery,
+            2 => GpioCfg::Debug,
+            _ => GpioCfg::SinkPower,
+        }
+    }
+
+    pub fn set_gpio_cfg(&mut self, cfg: GpioCfg) {
+        self.set(GPIO_CFG, cfg as u8);
+    }
+
+    /// Sink has an external power source (SNK_UNCONS_POWER)
+    pub fn unconstrained_power(&self) -> bool {
+        self.get(SNK_UNCONS_POWER) != 0
+    }
+
+    pub fn set_unconstrained_power(&mut self, unconstrained: bool) {
+        self.set(SNK_UNCONS_POWER, unconstrained as u8);
+    }
+
+    pub fn usb_comm_capable(&self) -> bool {
+        self.get(USB_COMM_CAPABLE) != 0
+    }
+
+    pub fn set_usb_comm_capable(&mut self, capable: bool) {
+        self.set(USB_COMM_CAPABLE, capable as u8);
+    }
+
+    /// Only close the VBUS power path when a PDO above 5V is negotiated
+    pub fn power_only_above_5v(&self) -> bool {
+        self.get(POWER_ONLY_ABOVE_5V) != 0
+    }
+
+    pub fn set_power_only_above_5v(&mut self, enable: bool) {
+        self.set(POWER_ONLY_ABOVE_5V, enable as u8);
+    }
+
+    /// Whether only PDO1 is advertised and the power path closes at 5V, see
+    /// [`set_five_volt_only`](Self::set_five_volt_only)
+    pub fn five_volt_only(&self) -> bool {
+        self.pdo_count() == 1 && !self.power_only_above_5v()
+    }
+
+    /// Advertise only PDO1, so the sink never requests more than 5V
+    ///
+    /// The STUSB4500 has no setting to turn its policy engine off. With PDO1 as the only sink
+    /// PDO it requests vSafe5V from PD sources and acts as a plain Type-C sink on the others,
+    /// and a source only raises VBUS on the sink's request. PDO2 and PDO3 are kept but not
+    /// advertised. POWER_ONLY_ABOVE_5V is cleared, it would keep the power path open.
+    pub fn set_five_volt_only(&mut self) {
+        self.set(DPM_SNK_PDO_NUMB, 1);
+        self.set_power_only_above_5v(false);
+    }
+
+    /// Request the maximum current offered by the source instead of the PDO current
+    pub fn req_src_current(&self) -> bool {
+        self.get(REQ_SRC_CURRENT) != 0
+    }
+
+    pub fn set_req_src_current(&mut self, enable: bool) {
+        self.set(REQ_SRC_CURRENT, enable as u8);
+    }
+
+    /// Vendor data stored in the first two bytes of sector 0 (0xC0-0xC1)
+    ///
+    /// These bytes are zero in the factory image and aren't used by the device configuration,
+    /// so they can hold e.g. a board revision or serial number. Read it back at runtime with
+    /// `STUSB4500Nvm::read_sectors` in the driver.
+    pub fn vendor_data(&self) -> u16 {
+        (self.get(VENDOR_DATA_H) as u16) << 8 | self.get(VENDOR_DATA_L) as u16
+    }
+
+    pub fn set_vendor_data(&mut self, data: u16) {
+        self.set(VENDOR_DATA_L, data as u8);
+        self.set(VENDOR_DATA_H, (data >> 8) as u8);
+    }
+}
+
+impl NvmConfig {
+    /// All documented fields with their raw bits and decoded value, in NVM order
+    ///
+    /// Reserved bits in between are kept as stored, see [`undocumented`](Self::undocumented).
+    pub fn fields(&self) -> impl Iterator<Item = FieldValue> + '_ {
+        FIELDS.iter().map(move |def| {
+            let mut raw = 0u16;
+            let mut shift = 0;
+            for part in def.parts {
+                raw |= u16::from(self.get(*part)) << shift;
+                shift += part.2.count_ones();
+            }
+            FieldValue {
+                name: def.name,
+                raw,
+                decoded: (def.decode)(self),
+            }
+        })
+    }
+
+    /// Set a documented field by its datasheet/GUI name to a raw value as in
+    /// [`FieldValue::raw`], the reverse of [`fields`](Self::fields)
+    pub fn set_field_raw(&mut self, name: &str, raw: u16) -> Result<(), ConfigError> {
+        let def = FIELDS
+            .iter()
+            .find(|def| def.name == name)
+            .ok_or(ConfigError::UnknownField)?;
+        let bits: u32 = def.parts.iter().map(|part| part.2.count_ones()).sum();
+        if u32::from(raw) >> bits != 0 {
+            return Err(ConfigError::OutOfRange);
+        }
+        let mut rest = raw;
+        for part in def.parts {
+            let width = part.2.count_ones();
+            self.set(*part, (rest & ((1 << width) - 1)) as u8);
+            rest >>= width;
+        }
+        Ok(())
+    }
+
+    /// Whether every documented field holds a documented value
+    pub fn is_recognized(&self) -> bool {
+        self.fields().all(|field| field.is_recognized())
+    }
+
+    /// Bits outside the documented fields, as stored, all others cleared
+    ///
+    /// Images generated by the ST GUI set some of these. No setter touches them, so they are
+    /// written back unchanged.
+    pub fn undocumented(&self) -> NvmImage {
+        let mut image = self.image;
+        for Field(sector, byte, mask) in FIELDS.iter().flat_map(|def| def.parts) {
+            if let Some(byte) = image.0.get_mut(*sector).and_then(|s| s.get_mut(*byte)) {
+                *byte &= !mask;
+            }
+        }
+        image
+    }
+}
+
+impl From<NvmImage> for NvmConfig {
+    fn from(image: NvmImage) -> Self {
+        NvmConfig::new(image)
+    }
+}
+
+impl From<NvmConfig> for NvmImage {
+    fn from(config: NvmConfig) -> Self {
+        config.image
+    }
+}
+
+/// Builder for [`NvmConfig`], see [`NvmConfig::builder`]
+///
+/// The first invalid value is reported by [`NvmConfigBuilder::build`].
+pub struct NvmConfigBuilder {
+    config: NvmConfig,
+    error: Option<ConfigError>,
+}
+
+impl NvmConfigBuilder {
+    fn apply<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut NvmConfig) -> Result<(), ConfigError>,
+    {
+        if self.error.is_none() {
+            self.error = f(&mut self.config).err();
+        }
+        self
+    }
+
+    /// PDO1 current in mA, the voltage is always 5V
+    pub fn pdo1(self, current: u16) -> Self {
+        self.apply(|c| c.set_pdo_current_ma(PdoChannel::PDO1, current))
+    }
+
+    /// PDO2 voltage in mV and current in mA
+    pub fn pdo2(self, voltage: u16, current: u16) -> Self {
+        self.apply(|c| {
+            c.set_pdo_voltage_mv(PdoChannel::PDO2, voltage)?;
+            c.set_pdo_current_ma(PdoChannel::PDO2, current)
+        })
+    }
+
+    /// PDO3 voltage in mV and current in mA
+    pub fn pdo3(self, voltage: u16, current: u16) -> Self {
+        self.apply(|c| {
+            c.set_pdo_voltage_mv(PdoChannel::PDO3, voltage)?;
+            c.set_pdo_current_ma(PdoChannel::PDO3, current)
+        })
+    }
+
+    /// Flexible current in mA used by PDOs set with [`NvmConfigBuilder::flex_pdo`]
+    pub fn flex_current(self, current: u16) -> Self {
+        self.apply(|c| c.set_flex_current_ma(current))
+    }
+
+    /// Make a PDO use the flexible current
+    pub fn flex_pdo(self, pdo: PdoChannel) -> Self {
+        self.apply(|c| {
+            c.set_pdo_flex_current(pdo);
+            Ok(())
+        })
+    }
+
+    /// VBUS discharge time to 0V in ms
+    pub fn discharge_time_to_0v(self, time: u16) -> Self {
+        self.apply(|c| c.set_discharge_time_to_0v_ms(time))
+    }
+
+    /// VBUS discharge time on a PDO transition in ms
+    pub fn discharge_time_transition(self, time: u16) -> Self {
+        self.apply(|c| c.set_discharge_time_transition_ms(time))
+    }
+
+    pub fn pdo_count(self, count: u8) -> Self {
+        self.apply(|c| c.set_pdo_count(count))
+    }
+
+    /// Derive the VBUS monitoring thresholds from the PDOs set so far, see
+    /// [`NvmConfig::set_vbus_monitoring_auto`]
+    pub fn vbus_monitoring_auto(self) -> Self {
+        self.apply(|c| c.set_vbus_monitoring_auto())
+    }
+
+    pub fn power_ok(self, cfg: PowerOk) -> Self {
+        self.apply(|c| {
+            c.set_power_ok(cfg);
+            Ok(())
+        })
+    }
+
+    pub fn gpio_cfg(self, cfg: GpioCfg) -> Self {
+        self.apply(|c| {
+            c.set_gpio_cfg(cfg);
+            Ok(())
+        })
+    }
+
+    pub fn unconstrained_power(self, unconstrained: bool) -> Self {
+        self.apply(|c| {
+            c.set_unconstrained_power(unconstrained);
+            Ok(())
+        })
+    }
+
+    pub fn usb_comm_capable(self, capable: bool) -> Self {
+        self.apply(|c| {
+            c.set_usb_comm_capable(capable);
+            Ok(())
+        })
+    }
+
+    pub fn power_only_above_5v(self, enable: bool) -> Self {
+        self.apply(|c| {
+            c.set_power_only_above_5v(enable);
+            Ok(())
+        })
+    }
+
+    /// See [`NvmConfig::set_five_volt_only`]
+    pub fn five_volt_only(self) -> Self {
+        self.apply(|c| {
+            c.set_five_volt_only();
+            Ok(())
+        })
+    }
+
+    pub fn req_src_current(self, enable: bool) -> Self {
+        self.apply(|c| {
+            c.set_req_src_current(enable);
+            Ok(())
+        })
+    }
+
+    pub fn vendor_data(self, data: u16) -> Self {
+        self.apply(|c| {
+            c.set_vendor_data(data);
+            Ok(())
+        })
+    }
+
+    pub fn build(self) -> Result<NvmConfig, ConfigError> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.config),
+        }
+    }
+}
+
+#[cfg(feature = "postcard")]
+impl NvmConfig {
+    /// Serialize with [postcard](https://docs.rs/postcard) into `buf`, returning the used part
+    ///
+    /// Uses the same fields as the JSON representation, around 70 bytes.
+    pub fn to_postcard<'b>(&self, buf: &'b mut [u8]) -> Result<&'b mut [u8], postcard::Error> {
+        postcard::to_slice(self, buf)
+    }
+
+    /// Deserialize from [`NvmConfig::to_postcard`] output
+    pub fn from_postcard(bytes: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes(bytes)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod fields {
+    use super::*;
+
+    /// Named fields of [`NvmConfig`], see its documentation for the schema
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(non_snake_case)]
+    pub struct Fields {
+        #[serde(default)]
+        NVM: Option<[[u8; 8]; 5]>,
+        DPM_SNK_PDO_NUMB: Option<u8>,
+        V_SNK_PDO2: Option<u16>,
+        V_SNK_PDO3: Option<u16>,
+        I_SNK_PDO1: Option<u16>,
+        I_SNK_PDO2: Option<u16>,
+        I_SNK_PDO3: Option<u16>,
+        I_SNK_PDO_FLEX: Option<u16>,
+        SNK_UNCONS_POWER: Option<bool>,
+        USB_COMM_CAPABLE: Option<bool>,
+        POWER_ONLY_ABOVE_5V: Option<bool>,
+        REQ_SRC_CURRENT: Option<bool>,
+        POWER_OK_CFG: Option<u8>,
+        GPIO_CFG: Option<u8>,
+        VBUS_DISCH_TIME_TO_0V: Option<u16>,
+        VBUS_DISCH_TIME_TRANSITION: Option<u16>,
+        VENDOR_DATA: Option<u16>,
+    }
+
+    impl From<NvmConfig> for Fields {
+        fn from(c: NvmConfig) -> Self {
+            let current = |ch| Some(c.pdo_current_ma(ch).unwrap_or(0));
+            Fields {
+                NVM: Some(c.image.0),
+                DPM_SNK_PDO_NUMB: Some(c.pdo_count()),
+                V_SNK_PDO2: Some(c.pdo_voltage_mv(PdoChannel::PDO2)),
+                V_SNK_PDO3: Some(c.pdo_voltage_mv(PdoChannel::PDO3)),
+                I_SNK_PDO1: current(PdoChannel::PDO1),
+                I_SNK_PDO2: current(PdoChannel::PDO2),
+                I_SNK_PDO3: current(PdoChannel::PDO3),
+                I_SNK_PDO_FLEX: Some(c.flex_current_ma()),
+                SNK_UNCONS_POWER: Some(c.unconstrained_power()),
+                USB_COMM_CAPABLE: Some(c.usb_comm_capable()),
+                POWER_ONLY_ABOVE_5V: Some(c.power_only_above_5v()),
+                REQ_SRC_CURRENT: Some(c.req_src_current()),
+                POWER_OK_CFG: Some(c.get(POWER_OK_CFG)),
+                GPIO_CFG: Some(c.get(GPIO_CFG)),
+                VBUS_DISCH_TIME_TO_0V: Some(c.discharge_time_to_0v_ms()),
+                VBUS_DISCH_TIME_TRANSITION: Some(c.discharge_time_transition_ms()),
+                VENDOR_DATA: Some(c.vendor_data()),
+            }
+        }
+    }
+
+    impl TryFrom<Fields> for NvmConfig {
+        type Error = ConfigError;
+
+        /// Fields equal to the decoded `NVM` value are skipped, so any image round-trips
+        fn try_from(f: Fields) -> Result<Self, Self::Error> {
+            let base = NvmConfig::new(f.NVM.map(NvmImage).unwrap_or_default());
+            let mut c = base;
+
+            if let Some(v) = f.DPM_SNK_PDO_NUMB.filter(|v| *v != base.pdo_count()) {
+                c.set_pdo_count(v)?;
+            }
+            for (ch, v) in [
+                (PdoChannel::PDO2, f.V_SNK_PDO2),
+                (PdoChannel::PDO3, f.V_SNK_PDO3),
+            ] {
+                if let Some(v) = v.filter(|v| *v != base.pdo_voltage_mv(ch)) {
+                    c.set_pdo_voltage_mv(ch, v)?;
+                }
+            }
+            for (ch, v) in [
+                (PdoChannel::PDO1, f.I_SNK_PDO1),
+                (PdoChannel::PDO2, f.I_SNK_PDO2),
+                (PdoChannel::PDO3, f.I_SNK_PDO3),
+            ] {
+                match v.filter(|v| *v != base.pdo_current_ma(ch).unwrap_or(0)) {
+                    Some(0) => c.set_pdo_flex_current(ch),
+                    Some(v) => c.set_pdo_current_ma(ch, v)?,
+                    None => {}
+                }
+            }
+            if let Some(v) = f.I_SNK_PDO_FLEX.filter(|v| *v != base.flex_current_ma()) {
+                c.set_flex_current_ma(v)?;
+            }
+            if let Some(v) = f.SNK_UNCONS_POWER {
+                c.set_unconstrained_power(v);
+            }
+            if let Some(v) = f.USB_COMM_CAPABLE {
+                c.set_usb_comm_capable(v);
+            }
+            if let Some(v) = f.POWER_ONLY_ABOVE_5V {
+                c.set_power_only_above_5v(v);
+            }
+            if let Some(v) = f.REQ_SRC_CURRENT {
+                c.set_req_src_current(v);
+            }
+            if let Some(v) = f.POWER_OK_CFG.filter(|v| *v != base.get(POWER_OK_CFG)) {
+                c.set_power_ok(match v {
+                    0 => PowerOk::Config1,
+                    2 => PowerOk::Config2,
+                    3 => PowerOk::Config3,
+                    _ => return Err(ConfigError::OutOfRange),
+                });
+            }
+            if let Some(v) = f.GPIO_CFG {
+                c.set_gpio_cfg(match v {
+                    0 => GpioCfg::SwCtrl,
+                    1 => GpioCfg::ErrorRecovery,
+                    2 => GpioCfg::Debug,
+                    3 => GpioCfg::SinkPower,
+                    _ => return Err(ConfigError::OutOfRange),
+                });
+            }
+            if let Some(v) = f
+                .VBUS_DISCH_TIME_TO_0V
+                .filter(|v| *v != base.discharge_time_to_0v_ms())
+            {
+                c.set_discharge_time_to_0v_ms(v)?;
+            }
+            if let Some(v) = f
+                .VBUS_DISCH_TIME_TRANSITION
+                .filter(|v| *v != base.discharge_time_transition_ms())
+            {
+                c.set_discharge_time_transition_ms(v)?;
+            }
+            if let Some(v) = f.VENDOR_DATA {
+                c.set_vendor_data(v);
+            }
+            Ok(c)
+        }
+    }
+}
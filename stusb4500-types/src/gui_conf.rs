@@ -0,0 +1,162 @@
+//! Text NVM configuration files as used with the ST [GUI][gui]
+//!
+//! Parameters are named as in ST's NVM map and hold the raw field value, see
+//! [`NvmConfig::fields`]. Raw NVM bytes, addressed 0xC0-0xE7 (sector 0 byte 0 to sector 4 byte
+//! 7), can be given too, one per line or a whole sector per line:
+//!
+//! ```text
+//! [NVM]
+//! 0xC0 = 0x00   ; one byte per line
+//! 0x10 0x40 0x9C 0x1C 0xFF 0x01 0x3C 0xDF   ; or a whole sector per line, in order
+//!
+//! [Parameters]
+//! DPM_SNK_PDO_NUMB = 3
+//! V_SNK_PDO2 = 0x12C   ; 15V in 50mV units
+//! ```
+//!
+//! Comments start with `;` or `#` and `[section]` headers are ignored. Parameters are applied on
+//! top of the NVM bytes, or of the factory image if there are none. If any bytes are given all 40
+//! must be. [`NvmConfig::to_gui_conf`] emits the bytes, to keep undocumented bits, followed by
+//! the parameters.
+//!
+//! [gui]: https://www.st.com/en/embedded-software/stsw-stusb002.html
+
+use core::fmt::Write;
+use std::string::String;
+
+use crate::config::{ConfigError, NvmConfig};
+use crate::image::NvmImage;
+
+/// Address of the first NVM byte
+const NVM_BASE: u8 = 0xC0;
+
+/// Errors parsing a configuration file, with the 1-based line number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuiConfError {
+    /// Line is neither `addr = value`, `NAME = value` nor a sector of eight bytes
+    InvalidLine(usize),
+    /// Address outside of 0xC0-0xE7, more than five sector lines or a parameter value too large
+    /// for its field
+    OutOfRange(usize),
+    /// Parameter name isn't a documented NVM field
+    UnknownParameter(usize),
+    /// Some but not all 40 bytes were given
+    Incomplete,
+}
+
+fn parse_byte(token: &str) -> Option<u8> {
+    let token = token.trim();
+    let hex = token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+        .unwrap_or(token);
+    u8::from_str_radix(hex, 16).ok()
+}
+
+/// Parameter value, decimal or hex with a `0x` prefix
+fn parse_value(token: &str) -> Option<u16> {
+    let token = token.trim();
+    match token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+    {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => token.parse().ok(),
+    }
+}
+
+/// Non-empty lines without comments and section headers, with their 1-based line number
+fn content_lines(conf: &str) -> impl Iterator<Item = (usize, &str)> {
+    conf.lines().enumerate().filter_map(|(n, line)| {
+        let line = line.split([';', '#']).next().unwrap_or_default().trim();
+        (!line.is_empty() && !line.starts_with('[')).then_some((n + 1, line))
+    })
+}
+
+/// `NAME = value` line with a field name rather than a byte address
+fn parameter(line: &str) -> Option<(&str, &str)> {
+    let (name, value) = line.split_once('=')?;
+    let name = name.trim();
+    parse_byte(name).is_none().then_some((name, value))
+}
+
+impl NvmConfig {
+    /// Parse a text configuration file, see the [module documentation](self)
+    pub fn from_gui_conf(conf: &str) -> Result<Self, GuiConfError> {
+        let mut image = NvmImage([[0; 8]; 5]);
+        let mut seen = [[false; 8]; 5];
+        let mut next_sector = 0;
+
+        for (n, line) in content_lines(conf) {
+            if parameter(line).is_some() {
+                continue;
+            }
+            if let Some((addr, value)) = line.split_once('=') {
+                let addr = parse_byte(addr).ok_or(GuiConfError::InvalidLine(n))?;
+                let value = parse_byte(value).ok_or(GuiConfError::InvalidLine(n))?;
+                let offset = addr
+                    .checked_sub(NVM_BASE)
+                    .ok_or(GuiConfError::OutOfRange(n))? as usize;
+                let (byte, seen) = image
+                    .0
+                    .as_flattened_mut()
+                    .get_mut(offset)
+                    .zip(seen.as_flattened_mut().get_mut(offset))
+                    .ok_or(GuiConfError::OutOfRange(n))?;
+                *byte = value;
+                *seen = true;
+            } else {
+                let (sector, seen) = image
+                    .0
+                    .get_mut(next_sector)
+                    .zip(seen.get_mut(next_sector))
+                    .ok_or(GuiConfError::OutOfRange(n))?;
+                let mut tokens = line.split_whitespace();
+                for byte in sector.iter_mut() {
+                    *byte = tokens
+                        .next()
+                        .and_then(parse_byte)
+                        .ok_or(GuiConfError::InvalidLine(n))?;
+                }
+                if tokens.next().is_some() {
+                    return Err(GuiConfError::InvalidLine(n));
+                }
+                *seen = [true; 8];
+                next_sector += 1;
+            }
+        }
+
+        let mut config = if seen.iter().flatten().all(|seen| *seen) {
+            NvmConfig::new(image)
+        } else if seen.iter().flatten().any(|seen| *seen) {
+            return Err(GuiConfError::Incomplete);
+        } else {
+            NvmConfig::DEFAULT
+        };
+
+        for (n, line) in content_lines(conf) {
+            let Some((name, value)) = parameter(line) else {
+                continue;
+            };
+            let value = parse_value(value).ok_or(GuiConfError::InvalidLine(n))?;
+            config.set_field_raw(name, value).map_err(|err| match err {
+                ConfigError::UnknownField => GuiConfError::UnknownParameter(n),
+                _ => GuiConfError::OutOfRange(n),
+            })?;
+        }
+        Ok(config)
+    }
+
+    /// Emit a text configuration file, see the [module documentation](self)
+    pub fn to_gui_conf(&self) -> String {
+        let mut conf = String::from("[NVM]\n");
+        for (i, byte) in self.image().0.iter().flatten().enumerate() {
+            let _ = writeln!(conf, "0x{:02X} = 0x{:02X}", NVM_BASE + i as u8, byte);
+        }
+        conf.push_str("\n[Parameters]\n");
+        for field in self.fields() {
+            let _ = writeln!(conf, "{} = {}", field.name, field.raw);
+        }
+        conf
+    }
+}
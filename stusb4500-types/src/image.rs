@@ -0,0 +1,189 @@
+//! NVM content as plain data
+//!
+//! Nothing here needs a device: images can be built, compared and (through
+//! [`crate::config::NvmConfig`]) edited on the host, e.g. by config generation tools or tests.
+//! The driver's `nvm` module programs them.
+//!
+//! Firmware that provisions its own STUSB4500 keeps the target image in its flash or EEPROM and
+//! implements [`NvmImageSource`] for it:
+//!
+//! ```ignore
+//! // At boot
+//! if let Some(report) = stusb.write_image_from(&mut eeprom)? {
+//!     log_nvm_update(report);
+//! }
+//! ```
+
+use core::convert::Infallible;
+
+use crate::variant::Variant;
+
+/// Default NVM content of the STUSB4500, see [`NvmImage::DEFAULT`]
+pub const DEFAULT_NVM_DATA: [[u8; 8]; 5] = [
+    [0x00, 0x00, 0xB0, 0xAB, 0x00, 0x45, 0x00, 0x00],
+    [0x10, 0x40, 0x9C, 0x1C, 0xFF, 0x01, 0x3C, 0xDF],
+    [0x02, 0x40, 0x0F, 0x00, 0x32, 0x00, 0xFC, 0xF1],
+    [0x00, 0x19, 0x56, 0xAF, 0xF5, 0x35, 0x5F, 0x00],
+    [0x00, 0x4B, 0x90, 0x21, 0x43, 0x00, 0x40, 0xFB],
+];
+
+/// NVM content, five sectors of eight bytes each
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NvmImage(pub [[u8; 8]; 5]);
+
+/// A byte that differs between two [`NvmImage`]s
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NvmDiff {
+    pub sector: u8,
+    pub offset: u8,
+    pub old: u8,
+    pub new: u8,
+}
+
+impl NvmImage {
+    /// Image from 40 bytes, sector 0 first
+    pub fn from_bytes(bytes: &[u8; 40]) -> Self {
+        let mut image = NvmImage([[0x00; 8]; 5]);
+        image.0.as_flattened_mut().copy_from_slice(bytes);
+        image
+    }
+
+    /// All 40 bytes, sector 0 first
+    pub fn to_bytes(&self) -> [u8; 40] {
+        let mut bytes = [0x00; 40];
+        bytes.copy_from_slice(self.0.as_flattened());
+        bytes
+    }
+
+    /// CRC-32 (IEEE) of all 40 bytes, sector 0 first
+    pub fn fingerprint(&self) -> u32 {
+        let mut crc = !0u32;
+        for byte in self.0.iter().flatten() {
+            crc ^= *byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc
+    }
+
+    /// Iterate over all bytes that differ between `self` (old) and `other` (new)
+    pub fn diff<'a>(&'a self, other: &'a NvmImage) -> impl Iterator<Item = NvmDiff> + 'a {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .enumerate()
+            .flat_map(|(sector, (old, new))| {
+                old.iter()
+                    .zip(new.iter())
+                    .enumerate()
+                    .filter(|(_, (old, new))| old != new)
+                    .map(move |(offset, (old, new))| NvmDiff {
+                        sector: sector as u8,
+                        offset: offset as u8,
+                        old: *old,
+                        new: *new,
+                    })
+            })
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl NvmImage {
+    /// All 40 bytes, sector 0 first
+    pub fn to_vec(&self) -> alloc::vec::Vec<u8> {
+        self.to_bytes().to_vec()
+    }
+}
+
+impl NvmImage {
+    /// Factory image of the STUSB4500
+    pub const DEFAULT: NvmImage = NvmImage(DEFAULT_NVM_DATA);
+
+    /// Factory image of `variant`, if known
+    ///
+    /// [`DEFAULT_NVM_DATA`] was read from a STUSB4500 and is not known to match the other
+    /// variants, so this returns `None` for them rather than a subtly wrong image.
+    pub const fn factory_default(variant: Variant) -> Option<NvmImage> {
+        match variant {
+            Variant::Stusb4500 => Some(NvmImage::DEFAULT),
+            Variant::Stusb4500B | Variant::Unknown(_) => None,
+        }
+    }
+}
+
+impl Default for NvmImage {
+    fn default() -> Self {
+        NvmImage::DEFAULT
+    }
+}
+
+impl From<[[u8; 8]; 5]> for NvmImage {
+    fn from(sectors: [[u8; 8]; 5]) -> Self {
+        NvmImage(sectors)
+    }
+}
+
+impl From<NvmImage> for [[u8; 8]; 5] {
+    fn from(image: NvmImage) -> Self {
+        image.0
+    }
+}
+
+/// Storage the target NVM image is loaded from, see the [module documentation](self)
+pub trait NvmImageSource {
+    type Error;
+
+    /// Load the image, `None` if the storage holds none, e.g. erased flash
+    fn load_image(&mut self) -> Result<Option<NvmImage>, Self::Error>;
+}
+
+/// An image built into the firmware
+impl NvmImageSource for NvmImage {
+    type Error = Infallible;
+
+    fn load_image(&mut self) -> Result<Option<NvmImage>, Infallible> {
+        Ok(Some(*self))
+    }
+}
+
+/// Fewer than 40 bytes stored, with the number found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShortImage(pub usize);
+
+/// The first 40 bytes, e.g. memory mapped flash or `include_bytes!`. All bytes 0xFF is erased
+/// flash and holds no image.
+impl NvmImageSource for &[u8] {
+    type Error = ShortImage;
+
+    fn load_image(&mut self) -> Result<Option<NvmImage>, ShortImage> {
+        let bytes: &[u8; 40] = self
+            .get(..40)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(ShortImage(self.len()))?;
+        if bytes.iter().all(|byte| *byte == 0xFF) {
+            return Ok(None);
+        }
+        Ok(Some(NvmImage::from_bytes(bytes)))
+    }
+}
+
+/// 40 bytes from a reader, e.g. a file written by `stusb4500-utils read`
+#[cfg(feature = "std")]
+pub struct ReadSource<R>(pub R);
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> NvmImageSource for ReadSource<R> {
+    type Error = std::io::Error;
+
+    fn load_image(&mut self) -> Result<Option<NvmImage>, std::io::Error> {
+        let mut bytes = [0x00; 40];
+        self.0.read_exact(&mut bytes)?;
+        Ok(Some(NvmImage::from_bytes(&bytes)))
+    }
+}
@@ -0,0 +1,81 @@
+//! Register map, PDO/RDO and NVM configuration types of the STUSB4500
+//!
+//! The pure data layer of the `stusb4500` driver, without I2C access, for host tools, test
+//! generators and other drivers. `stusb4500` re-exports all of it under the same paths.
+#![no_std]
+// Decoding must not panic on arbitrary register or NVM contents
+#![deny(
+    clippy::indexing_slicing,
+    clippy::unwrap_used,
+    clippy::expect_used,
+    clippy::panic,
+    clippy::unreachable,
+    clippy::todo,
+    clippy::unimplemented
+)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod config;
+#[cfg(feature = "std")]
+pub mod gui_conf;
+pub mod image;
+pub mod pdo;
+pub mod rdo;
+pub mod registers;
+#[cfg(feature = "uom")]
+pub mod units;
+pub mod variant;
+
+use registers::Register;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PdoChannel {
+    PDO1,
+    PDO2,
+    PDO3,
+}
+
+impl PdoChannel {
+    pub const ALL: [PdoChannel; 3] = [PdoChannel::PDO1, PdoChannel::PDO2, PdoChannel::PDO3];
+
+    /// Iterate over all channels in order
+    pub fn iter() -> impl Iterator<Item = PdoChannel> {
+        Self::ALL.into_iter()
+    }
+
+    /// First byte of the channel's DPM_SNK_PDO register
+    pub const fn register(self) -> Register {
+        match self {
+            PdoChannel::PDO1 => Register::DPMSNKPDO1,
+            PdoChannel::PDO2 => Register::DPMSNKPDO2,
+            PdoChannel::PDO3 => Register::DPMSNKPDO3,
+        }
+    }
+}
+
+/// Zero based index, PDO1 = 0
+impl From<PdoChannel> for usize {
+    fn from(pdo: PdoChannel) -> Self {
+        pdo as usize
+    }
+}
+
+/// PDO index outside of 0-2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidPdoChannel(pub u8);
+
+/// Zero based index, PDO1 = 0
+impl TryFrom<u8> for PdoChannel {
+    type Error = InvalidPdoChannel;
+
+    fn try_from(index: u8) -> Result<Self, Self::Error> {
+        PdoChannel::ALL
+            .get(index as usize)
+            .copied()
+            .ok_or(InvalidPdoChannel(index))
+    }
+}
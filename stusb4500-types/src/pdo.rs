@@ -0,0 +1,705 @@
+use core::fmt::Display;
+
+use bitfield::bitfield;
+
+#[derive(Debug, Clone, Copy, PartialOrd, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum FastSwapSupport {
+    #[default]
+    NotSupported = 0,
+    DefaultUsb = 1,
+    _1A5_5V = 2,
+    _3A0_5V = 3,
+}
+
+impl Display for FastSwapSupport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FastSwapSupport::NotSupported => write!(f, "Not supported"),
+            FastSwapSupport::DefaultUsb => write!(f, "0.5A @ 5V"),
+            FastSwapSupport::_1A5_5V => write!(f, "1.5A @ 5V"),
+            FastSwapSupport::_3A0_5V => write!(f, "3.0A @ 5V"),
+        }
+    }
+}
+
+impl From<FastSwapSupport> for u32 {
+    fn from(value: FastSwapSupport) -> Self {
+        match value {
+            FastSwapSupport::NotSupported => 0,
+            FastSwapSupport::DefaultUsb => 1,
+            FastSwapSupport::_1A5_5V => 2,
+            FastSwapSupport::_3A0_5V => 3,
+        }
+    }
+}
+
+impl From<u32> for FastSwapSupport {
+    fn from(value: u32) -> Self {
+        match value & 0x3 {
+            0 => FastSwapSupport::NotSupported,
+            1 => FastSwapSupport::DefaultUsb,
+            2 => FastSwapSupport::_1A5_5V,
+            _ => FastSwapSupport::_3A0_5V,
+        }
+    }
+}
+
+/// Value doesn't fit in the PDO field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfRange;
+
+/// Checked versions of the raw bitfield setters, failing instead of truncating
+macro_rules! checked_setters {
+    ($pdo:ty { $($checked:ident => $set:ident: $bits:literal),* $(,)? }) => {
+        impl $pdo {
+            $(
+                #[doc = concat!("Checked `", stringify!($set), "`, fails if `value` needs more than ", stringify!($bits), " bits")]
+                pub fn $checked(&mut self, value: u32) -> Result<(), OutOfRange> {
+                    if value >> $bits != 0 {
+                        return Err(OutOfRange);
+                    }
+                    self.$set(value);
+                    Ok(())
+                }
+            )*
+        }
+    };
+}
+
+/// Type bits 31:30 of a fixed sink PDO
+pub const PDO_SNK_FIXED: u32 = 0x0 << 30;
+
+bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct FixedPdo(u32);
+    impl Debug;
+    // The fields default to u16
+    pub fixed, _: 31, 30;
+    pub dual_role_power, set_dual_role_power: 29;
+    pub higher_capability, set_higher_capability: 28;
+    pub unconstrained_power, set_unconstrained_power: 27;
+    pub usb_communications_capable, set_usb_communications_capable: 26;
+    pub dual_role_data, set_dual_role_data: 25;
+    pub u32, from into FastSwapSupport, fast_role_swap, set_fast_role_swap: 24, 23;
+    pub _reserved, _: 22, 20;
+    /// Voltage in 50mV units, the setter truncates to 10 bits
+    pub voltage, set_voltage: 19, 10;
+    /// Current in 10mA units, the setter truncates to 10 bits
+    pub current, set_current: 9, 0;
+}
+
+impl Default for FixedPdo {
+    fn default() -> Self {
+        Self(PDO_SNK_FIXED)
+    }
+}
+
+impl FixedPdo {
+    /// Voltage in 50mV units and current in 10mA units, truncated to the field widths. See
+    /// [`FixedPdo::try_from_raw_units`] for a checked version.
+    pub const fn from_raw_units(voltage: u16, current: u16) -> Self {
+        Self(PDO_SNK_FIXED | ((voltage as u32 & 0x3ff) << 10) | (current as u32 & 0x3ff))
+    }
+
+    /// Voltage in 50mV units and current in 10mA units
+    pub fn try_from_raw_units(voltage: u16, current: u16) -> Result<Self, OutOfRange> {
+        let mut pdo: Self = Default::default();
+        pdo.try_set_voltage(voltage as u32)?;
+        pdo.try_set_current(current as u32)?;
+        Ok(pdo)
+    }
+
+    /// Same as [`FixedPdo::from_raw_units`]
+    #[deprecated(note = "use `FixedPdo::from_raw_units`")]
+    pub const fn new(voltage: u16, current: u16) -> Self {
+        Self::from_raw_units(voltage, current)
+    }
+
+    /// Same as [`FixedPdo::try_from_raw_units`]
+    #[deprecated(note = "use `FixedPdo::try_from_raw_units`")]
+    pub fn try_new(voltage: u16, current: u16) -> Result<Self, OutOfRange> {
+        Self::try_from_raw_units(voltage, current)
+    }
+}
+
+checked_setters!(FixedPdo {
+    try_set_voltage => set_voltage: 10,
+    try_set_current => set_current: 10,
+});
+
+/// Type bits 31:30 of a variable sink PDO
+pub const PDO_SNK_VARIABLE: u32 = 0x1 << 30;
+bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct VariablePdo(u32);
+    impl Debug;
+    // The fields default to u16
+    pub variable, _: 31, 30;
+    /// Voltage in 50mV units, the setter truncates to 10 bits
+    pub max_voltage, set_max_voltage: 29, 20;
+    /// Voltage in 50mV units, the setter truncates to 10 bits
+    pub min_voltage, set_min_voltage: 19, 10;
+    /// Current in 10mA units, the setter truncates to 10 bits
+    pub current, set_current: 9, 0;
+}
+
+impl Default for VariablePdo {
+    fn default() -> Self {
+        Self(PDO_SNK_VARIABLE)
+    }
+}
+
+impl VariablePdo {
+    /// Voltages in mV and current in mA, rounded down to 50mV and 10mA units and truncated to
+    /// the field widths. See [`VariablePdo::try_new`] for a checked version.
+    pub const fn new(min_voltage: u16, max_voltage: u16, current: u16) -> Self {
+        Self(
+            PDO_SNK_VARIABLE
+                | (((max_voltage as u32 / 50) & 0x3ff) << 20)
+                | (((min_voltage as u32 / 50) & 0x3ff) << 10)
+                | ((current as u32 / 10) & 0x3ff),
+        )
+    }
+
+    /// Voltages in mV and current in mA, rounded down to 50mV and 10mA units
+    ///
+    /// Fails if a value doesn't fit or `min_voltage > max_voltage`.
+    pub fn try_new(min_voltage: u16, max_voltage: u16, current: u16) -> Result<Self, OutOfRange> {
+        if min_voltage > max_voltage {
+            return Err(OutOfRange);
+        }
+        let mut pdo: Self = Default::default();
+        pdo.try_set_min_voltage(min_voltage as u32 / 50)?;
+        pdo.try_set_max_voltage(max_voltage as u32 / 50)?;
+        pdo.try_set_current(current as u32 / 10)?;
+        Ok(pdo)
+    }
+
+    /// Minimum voltage in mV
+    pub fn min_voltage_mv(&self) -> u32 {
+        self.min_voltage() * 50
+    }
+
+    /// Maximum voltage in mV
+    pub fn max_voltage_mv(&self) -> u32 {
+        self.max_voltage() * 50
+    }
+
+    /// Current in mA
+    pub fn current_ma(&self) -> u32 {
+        self.current() * 10
+    }
+}
+
+checked_setters!(VariablePdo {
+    try_set_max_voltage => set_max_voltage: 10,
+    try_set_min_voltage => set_min_voltage: 10,
+    try_set_current => set_current: 10,
+});
+
+/// Type bits 31:30 of a battery sink PDO
+pub const PDO_SNK_BATTERY: u32 = 0x2 << 30;
+bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct BatteryPdo(u32);
+    impl Debug;
+    // The fields default to u16
+    pub battery, _: 31, 30;
+    /// Voltage in 50mV units, the setter truncates to 10 bits
+    pub max_voltage, set_max_voltage: 29, 20;
+    /// Voltage in 50mV units, the setter truncates to 10 bits
+    pub min_voltage, set_min_voltage: 19, 10;
+    /// Power in 250mW units, the setter truncates to 10 bits
+    pub power, set_power: 9, 0;
+}
+impl Default for BatteryPdo {
+    fn default() -> Self {
+        Self(PDO_SNK_BATTERY)
+    }
+}
+
+impl BatteryPdo {
+    /// Voltages in mV and power in mW, rounded down to 50mV and 250mW units and truncated to
+    /// the field widths. See [`BatteryPdo::try_new`] for a checked version.
+    pub const fn new(min_voltage: u16, max_voltage: u16, power: u32) -> Self {
+        Self(
+            PDO_SNK_BATTERY
+                | (((max_voltage as u32 / 50) & 0x3ff) << 20)
+                | (((min_voltage as u32 / 50) & 0x3ff) << 10)
+                | ((power / 250) & 0x3ff),
+        )
+    }
+
+    /// Voltages in mV and power in mW, rounded down to 50mV and 250mW units
+    ///
+    /// Fails if a value doesn't fit or `min_voltage > max_voltage`.
+    pub fn try_new(min_voltage: u16, max_voltage: u16, power: u32) -> Result<Self, OutOfRange> {
+        if min_voltage > max_voltage {
+            return Err(OutOfRange);
+        }
+        let mut pdo: Self = Default::default();
+        pdo.try_set_min_voltage(min_voltage as u32 / 50)?;
+        pdo.try_set_max_voltage(max_voltage as u32 / 50)?;
+        pdo.try_set_power(power / 250)?;
+        Ok(pdo)
+    }
+
+    /// Minimum voltage in mV
+    pub fn min_voltage_mv(&self) -> u32 {
+        self.min_voltage() * 50
+    }
+
+    /// Maximum voltage in mV
+    pub fn max_voltage_mv(&self) -> u32 {
+        self.max_voltage() * 50
+    }
+
+    /// Power in mW
+    pub fn power_mw(&self) -> u32 {
+        self.power() * 250
+    }
+}
+
+checked_setters!(BatteryPdo {
+    try_set_max_voltage => set_max_voltage: 10,
+    try_set_min_voltage => set_min_voltage: 10,
+    try_set_power => set_power: 10,
+});
+
+/// Type bits 31:30 of a augmented sink PDO
+pub const PDO_SNK_AUGMENTED: u32 = 0x3 << 30;
+bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct AugmentedPdo(u32);
+    impl Debug;
+    // The fields default to u16
+    pub battery, _: 31, 30;
+    pub progdev, _: 29, 28;
+    pub reserved2, _: 27, 25;
+    /// Voltage in 100mV units, the setter truncates to 8 bits
+    pub max_voltage, set_max_voltage: 24, 17;
+    pub reserved1, _: 16;
+    /// Voltage in 100mV units, the setter truncates to 8 bits
+    pub min_voltage, set_min_voltage: 15, 8;
+    pub reserved0, _: 7;
+    /// Current in 50mA units, the setter truncates to 7 bits
+    pub max_current, set_max_current: 6, 0;
+}
+
+impl Default for AugmentedPdo {
+    fn default() -> Self {
+        Self(PDO_SNK_AUGMENTED)
+    }
+}
+
+checked_setters!(AugmentedPdo {
+    try_set_max_voltage => set_max_voltage: 8,
+    try_set_min_voltage => set_min_voltage: 8,
+    try_set_max_current => set_max_current: 7,
+});
+
+/// With the `serde` feature a PDO (de)serializes as its raw `u32`
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u32", into = "u32")
+)]
+pub enum Pdo {
+    Fixed(FixedPdo),
+    Variable(VariablePdo),
+    Battery(BatteryPdo),
+    Augmented(AugmentedPdo),
+}
+impl Pdo {
+    /// Fixed supply PDO in raw units, see [`FixedPdo::from_raw_units`]
+    pub const fn new_fixed(voltage: u16, current: u16) -> Self {
+        Pdo::Fixed(FixedPdo::from_raw_units(voltage, current))
+    }
+
+    /// Variable supply PDO, see [`VariablePdo::try_new`]
+    pub fn try_new_variable(
+        min_voltage: u16,
+        max_voltage: u16,
+        current: u16,
+    ) -> Result<Self, OutOfRange> {
+        VariablePdo::try_new(min_voltage, max_voltage, current).map(Pdo::Variable)
+    }
+
+    /// Battery PDO, see [`BatteryPdo::try_new`]
+    pub fn try_new_battery(
+        min_voltage: u16,
+        max_voltage: u16,
+        power: u32,
+    ) -> Result<Self, OutOfRange> {
+        BatteryPdo::try_new(min_voltage, max_voltage, power).map(Pdo::Battery)
+    }
+
+    pub fn dual_role_power(&mut self, dual: bool) -> &mut Self {
+        if let Pdo::Fixed(x) = self {
+            x.set_dual_role_power(dual);
+        }
+        self
+    }
+
+    pub fn dual_role_data(&mut self, dual: bool) -> &mut Self {
+        if let Pdo::Fixed(x) = self {
+            x.set_dual_role_data(dual);
+        }
+        self
+    }
+
+    pub fn usb_communications_capable(&mut self, capable: bool) -> &mut Self {
+        if let Pdo::Fixed(x) = self {
+            x.set_usb_communications_capable(capable);
+        }
+        self
+    }
+
+    pub fn higher_capability(&mut self, capable: bool) -> &mut Self {
+        if let Pdo::Fixed(x) = self {
+            x.set_higher_capability(capable);
+        }
+        self
+    }
+
+    pub fn unconstrained_power(&mut self, unconstrained: bool) -> &mut Self {
+        if let Pdo::Fixed(x) = self {
+            x.set_unconstrained_power(unconstrained);
+        }
+        self
+    }
+
+    /// Minimum voltage in mV of a variable, battery or augmented PDO
+    ///
+    /// Like the other unit setters this rounds down to the variant's units and truncates to the
+    /// field width, use the variant's `try_set_*` setters for a checked version.
+    pub fn min_voltage_mv(&mut self, voltage: u16) -> &mut Self {
+        match self {
+            Pdo::Variable(x) => x.set_min_voltage(voltage as u32 / 50),
+            Pdo::Battery(x) => x.set_min_voltage(voltage as u32 / 50),
+            Pdo::Augmented(x) => x.set_min_voltage(voltage as u32 / 100),
+            Pdo::Fixed(_) => {}
+        }
+        self
+    }
+
+    /// Maximum voltage in mV of a variable, battery or augmented PDO
+    pub fn max_voltage_mv(&mut self, voltage: u16) -> &mut Self {
+        match self {
+            Pdo::Variable(x) => x.set_max_voltage(voltage as u32 / 50),
+            Pdo::Battery(x) => x.set_max_voltage(voltage as u32 / 50),
+            Pdo::Augmented(x) => x.set_max_voltage(voltage as u32 / 100),
+            Pdo::Fixed(_) => {}
+        }
+        self
+    }
+
+    /// Current in mA of a fixed, variable or augmented PDO
+    pub fn current_ma(&mut self, current: u16) -> &mut Self {
+        match self {
+            Pdo::Fixed(x) => x.set_current(current as u32 / 10),
+            Pdo::Variable(x) => x.set_current(current as u32 / 10),
+            Pdo::Augmented(x) => x.set_max_current(current as u32 / 50),
+            Pdo::Battery(_) => {}
+        }
+        self
+    }
+
+    /// Power in mW of a battery PDO
+    pub fn power_mw(&mut self, power: u32) -> &mut Self {
+        if let Pdo::Battery(x) = self {
+            x.set_power(power / 250);
+        }
+        self
+    }
+
+    /// Raw PDO, same as `u32::from(pdo)`
+    pub fn bits(&self) -> u32 {
+        u32::from(self)
+    }
+
+    /// Decode a raw PDO, all augmented PDOs included
+    ///
+    /// Unlike `Pdo::try_from(bits)` this doesn't reject augmented PDOs other than PPS, they are
+    /// returned as [`Pdo::Augmented`] like before `TryFrom` existed.
+    pub fn from_bits(bits: u32) -> Option<Self> {
+        match bits & 0xC000_0000 {
+            PDO_SNK_AUGMENTED => Some(Pdo::Augmented(AugmentedPdo(bits))),
+            _ => Pdo::try_from(bits).ok(),
+        }
+    }
+}
+
+/// Decoded fields in real units, e.g. `Fixed { voltage_mv: 5000, current_ma: 1500, .. }`
+impl core::fmt::Debug for Pdo {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Pdo::Fixed(x) => f
+                .debug_struct("Fixed")
+                .field("voltage_mv", &(x.voltage() * 50))
+                .field("current_ma", &(x.current() * 10))
+                .field("dual_role_power", &x.dual_role_power())
+                .field("higher_capability", &x.higher_capability())
+                .field("unconstrained_power", &x.unconstrained_power())
+                .field(
+                    "usb_communications_capable",
+                    &x.usb_communications_capable(),
+                )
+                .field("dual_role_data", &x.dual_role_data())
+                .field("fast_role_swap", &x.fast_role_swap())
+                .finish(),
+            Pdo::Variable(x) => f
+                .debug_struct("Variable")
+                .field("min_voltage_mv", &x.min_voltage_mv())
+                .field("max_voltage_mv", &x.max_voltage_mv())
+                .field("current_ma", &x.current_ma())
+                .finish(),
+            Pdo::Battery(x) => f
+                .debug_struct("Battery")
+                .field("min_voltage_mv", &x.min_voltage_mv())
+                .field("max_voltage_mv", &x.max_voltage_mv())
+                .field("power_mw", &x.power_mw())
+                .finish(),
+            Pdo::Augmented(x) => f
+                .debug_struct("Augmented")
+                .field("min_voltage_mv", &(x.min_voltage() * 100))
+                .field("max_voltage_mv", &(x.max_voltage() * 100))
+                .field("max_current_ma", &(x.max_current() * 50))
+                .finish(),
+        }
+    }
+}
+
+/// Milli-units as units with two decimals, without floating point
+fn fmt_milli(f: &mut core::fmt::Formatter<'_>, milli: u32) -> core::fmt::Result {
+    write!(f, "{}.{:02}", milli / 1000, milli % 1000 / 10)
+}
+
+impl Pdo {
+    /// Name, voltage range in mV and current in mA or power in mW with its unit
+    fn summary(&self) -> (&'static str, Option<u32>, u32, u32, char) {
+        match self {
+            Pdo::Fixed(x) => ("Fixed", None, x.voltage() * 50, x.current() * 10, 'A'),
+            Pdo::Variable(x) => (
+                "Variable",
+                Some(x.min_voltage_mv()),
+                x.max_voltage_mv(),
+                x.current_ma(),
+                'A',
+            ),
+            Pdo::Battery(x) => (
+                "Battery",
+                Some(x.min_voltage_mv()),
+                x.max_voltage_mv(),
+                x.power_mw(),
+                'W',
+            ),
+            Pdo::Augmented(x) => (
+                "Augmented",
+                Some(x.min_voltage() * 100),
+                x.max_voltage() * 100,
+                x.max_current() * 50,
+                'A',
+            ),
+        }
+    }
+}
+
+/// Short summary, e.g. `Fixed 9.00V 3.00A` or `Battery 5.00-20.00V 45.00W`
+impl Display for Pdo {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let (name, min_voltage, max_voltage, value, unit) = self.summary();
+        write!(f, "{} ", name)?;
+        if let Some(min_voltage) = min_voltage {
+            fmt_milli(f, min_voltage)?;
+            write!(f, "-")?;
+        }
+        fmt_milli(f, max_voltage)?;
+        write!(f, "V ")?;
+        fmt_milli(f, value)?;
+        write!(f, "{}", unit)
+    }
+}
+
+#[cfg(feature = "ufmt")]
+mod ufmt_impls {
+    use super::Pdo;
+    use ufmt::{uDebug, uDisplay, uWrite, uwrite, Formatter};
+
+    /// Same as the `Debug` implementation
+    impl uDebug for Pdo {
+        fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error>
+        where
+            W: uWrite + ?Sized,
+        {
+            match self {
+                Pdo::Fixed(x) => f
+                    .debug_struct("Fixed")?
+                    .field("voltage_mv", &(x.voltage() * 50))?
+                    .field("current_ma", &(x.current() * 10))?
+                    .field("dual_role_power", &x.dual_role_power())?
+                    .field("higher_capability", &x.higher_capability())?
+                    .field("unconstrained_power", &x.unconstrained_power())?
+                    .field(
+                        "usb_communications_capable",
+                        &x.usb_communications_capable(),
+                    )?
+                    .field("dual_role_data", &x.dual_role_data())?
+                    .field("fast_role_swap", &x.fast_role_swap())?
+                    .finish(),
+                Pdo::Variable(x) => f
+                    .debug_struct("Variable")?
+                    .field("min_voltage_mv", &x.min_voltage_mv())?
+                    .field("max_voltage_mv", &x.max_voltage_mv())?
+                    .field("current_ma", &x.current_ma())?
+                    .finish(),
+                Pdo::Battery(x) => f
+                    .debug_struct("Battery")?
+                    .field("min_voltage_mv", &x.min_voltage_mv())?
+                    .field("max_voltage_mv", &x.max_voltage_mv())?
+                    .field("power_mw", &x.power_mw())?
+                    .finish(),
+                Pdo::Augmented(x) => f
+                    .debug_struct("Augmented")?
+                    .field("min_voltage_mv", &(x.min_voltage() * 100))?
+                    .field("max_voltage_mv", &(x.max_voltage() * 100))?
+                    .field("max_current_ma", &(x.max_current() * 50))?
+                    .finish(),
+            }
+        }
+    }
+
+    /// ufmt has no zero padding, so the hundredths are padded by hand
+    fn fmt_milli<W>(f: &mut Formatter<'_, W>, milli: u32) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        let hundredths = milli % 1000 / 10;
+        let pad = if hundredths < 10 { "0" } else { "" };
+        uwrite!(f, "{}.{}{}", milli / 1000, pad, hundredths)
+    }
+
+    /// Same as the `Display` implementation
+    impl uDisplay for Pdo {
+        fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error>
+        where
+            W: uWrite + ?Sized,
+        {
+            let (name, min_voltage, max_voltage, value, unit) = self.summary();
+            uwrite!(f, "{} ", name)?;
+            if let Some(min_voltage) = min_voltage {
+                fmt_milli(f, min_voltage)?;
+                f.write_str("-")?;
+            }
+            fmt_milli(f, max_voltage)?;
+            f.write_str("V ")?;
+            fmt_milli(f, value)?;
+            f.write_char(unit)
+        }
+    }
+}
+
+/// Raw value isn't a valid sink PDO
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidPdoBits(pub u32);
+
+impl Display for InvalidPdoBits {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid PDO {:#010x}", self.0)
+    }
+}
+
+impl TryFrom<u32> for Pdo {
+    type Error = InvalidPdoBits;
+
+    fn try_from(bits: u32) -> Result<Self, Self::Error> {
+        match bits & 0xC000_0000 {
+            PDO_SNK_FIXED => Ok(Pdo::Fixed(FixedPdo(bits))),
+            PDO_SNK_VARIABLE => Ok(Pdo::Variable(VariablePdo(bits))),
+            PDO_SNK_BATTERY => Ok(Pdo::Battery(BatteryPdo(bits))),
+            // AugmentedPdo only has the PPS layout, PD 3.1 AVS and reserved APDOs are rejected
+            PDO_SNK_AUGMENTED if AugmentedPdo(bits).progdev() == 0 => {
+                Ok(Pdo::Augmented(AugmentedPdo(bits)))
+            }
+            _ => Err(InvalidPdoBits(bits)),
+        }
+    }
+}
+
+impl From<&Pdo> for u32 {
+    fn from(pdo: &Pdo) -> Self {
+        match pdo {
+            Pdo::Fixed(a) => a.0,
+            Pdo::Variable(a) => a.0,
+            Pdo::Battery(a) => a.0,
+            Pdo::Augmented(a) => a.0,
+        }
+    }
+}
+
+impl From<Pdo> for u32 {
+    fn from(pdo: Pdo) -> Self {
+        u32::from(&pdo)
+    }
+}
+
+/// Why a set of sink PDOs can't be used, see [`validate_pdo_set`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdoSetError {
+    /// No PDOs
+    Empty,
+    /// More than three PDOs
+    TooMany,
+    /// PDO1 must be a fixed 5V PDO
+    FirstNot5V,
+    /// The STUSB4500 only supports fixed PDOs, contains the index of the offending PDO
+    NotFixed(usize),
+    /// PDO voltages must strictly increase, contains the index of the offending PDO
+    NotAscending(usize),
+    /// Voltage above 20V, contains the index of the offending PDO
+    VoltageTooHigh(usize),
+    /// Current above 5A, contains the index of the offending PDO
+    CurrentTooHigh(usize),
+}
+
+/// Check a set of sink PDOs against the USB PD and STUSB4500 rules
+///
+/// At most three fixed PDOs, the first being 5V, with strictly increasing voltages up to 20V and
+/// currents up to 5A.
+pub fn validate_pdo_set(pdos: &[Pdo]) -> Result<(), PdoSetError> {
+    if pdos.is_empty() {
+        return Err(PdoSetError::Empty);
+    }
+    if pdos.len() > 3 {
+        return Err(PdoSetError::TooMany);
+    }
+
+    let mut previous = 0;
+    for (i, pdo) in pdos.iter().enumerate() {
+        let Pdo::Fixed(pdo) = pdo else {
+            return Err(if i == 0 {
+                PdoSetError::FirstNot5V
+            } else {
+                PdoSetError::NotFixed(i)
+            });
+        };
+        // 50mV and 10mA units
+        let voltage = pdo.voltage();
+        if i == 0 && voltage != 100 {
+            return Err(PdoSetError::FirstNot5V);
+        }
+        if voltage <= previous {
+            return Err(PdoSetError::NotAscending(i));
+        }
+        if voltage > 400 {
+            return Err(PdoSetError::VoltageTooHigh(i));
+        }
+        if pdo.current() > 500 {
+            return Err(PdoSetError::CurrentTooHigh(i));
+        }
+        previous = voltage;
+    }
+    Ok(())
+}
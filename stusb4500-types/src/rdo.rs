@@ -0,0 +1,127 @@
+use core::fmt::Display;
+
+use bitfield::bitfield;
+use bitflags::bitflags;
+
+use crate::pdo::OutOfRange;
+
+bitfield! {
+  #[derive(Clone, Copy, PartialEq, Eq)]
+  pub struct Rdo(u32);
+  impl Debug;
+  // The fields default to u16
+  /// Object position of the requested PDO (1-7), 0 without a contract
+  pub position, _: 30, 28;
+  pub give_back, _ : 27;
+  pub capability_mismatch, _: 26;
+  pub usb_communication_capable, _: 25;
+  pub no_usb_suspend, _: 24;
+  pub unchunked_extended_messages, _: 23;
+  /// Current in 10mA units
+  pub operating_current, _: 19, 10;
+  /// Current in 10mA units
+  pub max_operating_current, _: 9, 0;
+}
+
+bitflags! {
+    /// Flag bits of a request data object
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct RdoFlags: u32 {
+        const GiveBack                  = 1 << 27;
+        const CapabilityMismatch        = 1 << 26;
+        const UsbCommunicationCapable   = 1 << 25;
+        const NoUsbSuspend              = 1 << 24;
+        const UnchunkedExtendedMessages = 1 << 23;
+    }
+}
+
+impl Rdo {
+    /// Request for a fixed or variable supply PDO
+    ///
+    /// `position` is the 1-based index of the source PDO, currents are in mA and rounded down
+    /// to 10mA units.
+    pub fn fixed_request(
+        position: u8,
+        current: u16,
+        max_current: u16,
+        flags: RdoFlags,
+    ) -> Result<Self, OutOfRange> {
+        let current = current as u32 / 10;
+        let max_current = max_current as u32 / 10;
+        if !(1..=7).contains(&position) || current > 0x3FF || max_current > 0x3FF {
+            return Err(OutOfRange);
+        }
+        Ok(Rdo((position as u32) << 28
+            | flags.bits()
+            | current << 10
+            | max_current))
+    }
+
+    /// Raw RDO
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Wrap a raw RDO, all bit patterns are accepted
+    pub fn from_bits(bits: u32) -> Self {
+        Rdo(bits)
+    }
+
+    /// Flag bits
+    pub fn flags(&self) -> RdoFlags {
+        RdoFlags::from_bits_truncate(self.0)
+    }
+
+    /// The sink asked for more than the source offered
+    pub fn is_mismatch(&self) -> bool {
+        self.capability_mismatch()
+    }
+
+    /// Operating current in mA
+    pub fn operating_current_ma(&self) -> u32 {
+        self.operating_current() * 10
+    }
+
+    /// Max operating current in mA
+    pub fn max_operating_current_ma(&self) -> u32 {
+        self.max_operating_current() * 10
+    }
+
+    /// Requested power in mW at `voltage_mv`, the RDO itself doesn't carry the voltage
+    pub fn requested_power_mw(&self, voltage_mv: u16) -> u32 {
+        voltage_mv as u32 * self.operating_current() / 100
+    }
+}
+
+impl From<u32> for Rdo {
+    fn from(bits: u32) -> Self {
+        Rdo(bits)
+    }
+}
+
+impl From<Rdo> for u32 {
+    fn from(rdo: Rdo) -> Self {
+        rdo.0
+    }
+}
+
+impl Display for Rdo {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.position() == 0 {
+            return write!(f, "No contract");
+        }
+        write!(
+            f,
+            "PDO{}: {}.{:02}A (max {}.{:02}A)",
+            self.position(),
+            self.operating_current() / 100,
+            self.operating_current() % 100,
+            self.max_operating_current() / 100,
+            self.max_operating_current() % 100,
+        )?;
+        if self.capability_mismatch() {
+            write!(f, ", capability mismatch")?;
+        }
+        Ok(())
+    }
+}
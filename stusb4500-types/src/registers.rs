@@ -0,0 +1,399 @@
+#![allow(non_upper_case_globals)]
+
+use bitflags::bitflags;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum Register {
+    BcdTypeCRevL = 0x06,
+    BcdTypeCRevH = 0x07,
+    BcdUsbPDRevL = 0x08,
+    BcdUsbPDRevH = 0x09,
+    DeviceCapabHigh = 0x0A,
+    AlertStatus1 = 0x0B,
+    AlertStatus1Mask = 0x0C,
+    PortStatus0 = 0x0D,
+    PortStatus1 = 0x0E,
+    TypeCMonitoringStatus0 = 0x0F,
+    TypeCMonitoringStatus1 = 0x10,
+    CCStatus = 0x11,
+    CCHWFaultStatus0 = 0x12,
+    CCHWFaultStatus1 = 0x13,
+    PDTypeCStatus = 0x14,
+    TypeCStatus = 0x15,
+    PRTStatus = 0x16,
+    //0x17-0x19, Reserved
+    PDCommandCtrl = 0x1A,
+    //0x1B-0x1F, Reserved
+    MonitoringCtrl0 = 0x20,
+    MonitoringCtrl1 = 0x21,
+    MonitoringCtrl2 = 0x22,
+    ResetCtrl = 0x23,
+    //0x24, Reserved
+    VbusDischargeTimeCtrl = 0x25,
+    VbusDischargeCtrl = 0x26,
+    VbusCtrl = 0x27,
+    //0x28, Reserved
+    PEFSM = 0x29,
+    //0x2B, Reserved
+    //0x2C, Reserved
+    GpioSWGpio = 0x2D,
+    //0x2E, Reserved
+    DeviceId = 0x2F,
+    RXByteCnt = 0x30,
+    RXHeaderL = 0x31,
+    RXHeaderH = 0x32,
+    RXDataObj = 0x33, //7 x 4 bytes
+    TXHeaderL = 0x51,
+    TXHeaderH = 0x52,
+    RWBuffer = 0x53, // 8 bytes
+    //0x5B-0x6F, Reserved
+    DPMPDONumb = 0x70,
+    //0x71-0x84, Reserved
+    DPMSNKPDO1 = 0x85,   //4 bytes
+    DPMSNKPDO2 = 0x89,   //4 bytes
+    DPMSNKPDO3 = 0x8D,   //4 bytes
+    RDORegStatus = 0x91, //4 bytes
+    NvmPassword = 0x95,
+    NvmCtrl0 = 0x96,
+    NvmCtrl1 = 0x97,
+}
+
+/// Address that isn't the start of a documented register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidRegister(pub u8);
+
+impl Register {
+    /// All registers in address order
+    pub const ALL: [Register; 43] = [
+        Register::BcdTypeCRevL,
+        Register::BcdTypeCRevH,
+        Register::BcdUsbPDRevL,
+        Register::BcdUsbPDRevH,
+        Register::DeviceCapabHigh,
+        Register::AlertStatus1,
+        Register::AlertStatus1Mask,
+        Register::PortStatus0,
+        Register::PortStatus1,
+        Register::TypeCMonitoringStatus0,
+        Register::TypeCMonitoringStatus1,
+        Register::CCStatus,
+        Register::CCHWFaultStatus0,
+        Register::CCHWFaultStatus1,
+        Register::PDTypeCStatus,
+        Register::TypeCStatus,
+        Register::PRTStatus,
+        Register::PDCommandCtrl,
+        Register::MonitoringCtrl0,
+        Register::MonitoringCtrl1,
+        Register::MonitoringCtrl2,
+        Register::ResetCtrl,
+        Register::VbusDischargeTimeCtrl,
+        Register::VbusDischargeCtrl,
+        Register::VbusCtrl,
+        Register::PEFSM,
+        Register::GpioSWGpio,
+        Register::DeviceId,
+        Register::RXByteCnt,
+        Register::RXHeaderL,
+        Register::RXHeaderH,
+        Register::RXDataObj,
+        Register::TXHeaderL,
+        Register::TXHeaderH,
+        Register::RWBuffer,
+        Register::DPMPDONumb,
+        Register::DPMSNKPDO1,
+        Register::DPMSNKPDO2,
+        Register::DPMSNKPDO3,
+        Register::RDORegStatus,
+        Register::NvmPassword,
+        Register::NvmCtrl0,
+        Register::NvmCtrl1,
+    ];
+
+    pub const fn address(self) -> u8 {
+        self as u8
+    }
+
+    /// Width in bytes, e.g. 4 for the PDO and RDO registers
+    pub const fn size(self) -> usize {
+        match self {
+            Register::RXDataObj => 28,
+            Register::RWBuffer => 8,
+            Register::DPMSNKPDO1
+            | Register::DPMSNKPDO2
+            | Register::DPMSNKPDO3
+            | Register::RDORegStatus => 4,
+            _ => 1,
+        }
+    }
+
+    /// Name in the datasheet, e.g. `ALERT_STATUS_1`
+    pub const fn name(self) -> &'static str {
+        match self {
+            Register::BcdTypeCRevL => "BCD_TYPEC_REV_LOW",
+            Register::BcdTypeCRevH => "BCD_TYPEC_REV_HIGH",
+            Register::BcdUsbPDRevL => "BCD_USBPD_REV_LOW",
+            Register::BcdUsbPDRevH => "BCD_USBPD_REV_HIGH",
+            Register::DeviceCapabHigh => "DEVICE_CAPAB_HIGH",
+            Register::AlertStatus1 => "ALERT_STATUS_1",
+            Register::AlertStatus1Mask => "ALERT_STATUS_1_MASK",
+            Register::PortStatus0 => "PORT_STATUS_0",
+            Register::PortStatus1 => "PORT_STATUS_1",
+            Register::TypeCMonitoringStatus0 => "TYPEC_MONITORING_STATUS_0",
+            Register::TypeCMonitoringStatus1 => "TYPEC_MONITORING_STATUS_1",
+            Register::CCStatus => "CC_STATUS",
+            Register::CCHWFaultStatus0 => "CC_HW_FAULT_STATUS_0",
+            Register::CCHWFaultStatus1 => "CC_HW_FAULT_STATUS_1",
+            Register::PDTypeCStatus => "PD_TYPEC_STATUS",
+            Register::TypeCStatus => "TYPEC_STATUS",
+            Register::PRTStatus => "PRT_STATUS",
+            Register::PDCommandCtrl => "PD_COMMAND_CTRL",
+            Register::MonitoringCtrl0 => "MONITORING_CTRL_0",
+            Register::MonitoringCtrl1 => "MONITORING_CTRL_1",
+            Register::MonitoringCtrl2 => "MONITORING_CTRL_2",
+            Register::ResetCtrl => "RESET_CTRL",
+            Register::VbusDischargeTimeCtrl => "VBUS_DISCHARGE_TIME_CTRL",
+            Register::VbusDischargeCtrl => "VBUS_DISCHARGE_CTRL",
+            Register::VbusCtrl => "VBUS_CTRL",
+            Register::PEFSM => "PE_FSM",
+            Register::GpioSWGpio => "GPIO_SW_GPIO",
+            Register::DeviceId => "DEVICE_ID",
+            Register::RXByteCnt => "RX_BYTE_CNT",
+            Register::RXHeaderL => "RX_HEADER_LOW",
+            Register::RXHeaderH => "RX_HEADER_HIGH",
+            Register::RXDataObj => "RX_DATA_OBJ",
+            Register::TXHeaderL => "TX_HEADER_LOW",
+            Register::TXHeaderH => "TX_HEADER_HIGH",
+            Register::RWBuffer => "RW_BUFFER",
+            Register::DPMPDONumb => "DPM_PDO_NUMB",
+            Register::DPMSNKPDO1 => "DPM_SNK_PDO1",
+            Register::DPMSNKPDO2 => "DPM_SNK_PDO2",
+            Register::DPMSNKPDO3 => "DPM_SNK_PDO3",
+            Register::RDORegStatus => "RDO_REG_STATUS",
+            Register::NvmPassword => "NVM_PASSWORD",
+            Register::NvmCtrl0 => "NVM_CTRL_0",
+            Register::NvmCtrl1 => "NVM_CTRL_1",
+        }
+    }
+
+    /// All single byte registers in address order, excluding the NVM registers
+    pub const BYTE_REGISTERS: [Register; 34] = [
+        Register::BcdTypeCRevL,
+        Register::BcdTypeCRevH,
+        Register::BcdUsbPDRevL,
+        Register::BcdUsbPDRevH,
+        Register::DeviceCapabHigh,
+        Register::AlertStatus1,
+        Register::AlertStatus1Mask,
+        Register::PortStatus0,
+        Register::PortStatus1,
+        Register::TypeCMonitoringStatus0,
+        Register::TypeCMonitoringStatus1,
+        Register::CCStatus,
+        Register::CCHWFaultStatus0,
+        Register::CCHWFaultStatus1,
+        Register::PDTypeCStatus,
+        Register::TypeCStatus,
+        Register::PRTStatus,
+        Register::PDCommandCtrl,
+        Register::MonitoringCtrl0,
+        Register::MonitoringCtrl1,
+        Register::MonitoringCtrl2,
+        Register::ResetCtrl,
+        Register::VbusDischargeTimeCtrl,
+        Register::VbusDischargeCtrl,
+        Register::VbusCtrl,
+        Register::PEFSM,
+        Register::GpioSWGpio,
+        Register::DeviceId,
+        Register::RXByteCnt,
+        Register::RXHeaderL,
+        Register::RXHeaderH,
+        Register::TXHeaderL,
+        Register::TXHeaderH,
+        Register::DPMPDONumb,
+    ];
+
+    /// Registers used while the NVM is unlocked
+    pub fn is_nvm(&self) -> bool {
+        matches!(
+            self,
+            Register::NvmPassword | Register::NvmCtrl0 | Register::NvmCtrl1 | Register::RWBuffer
+        )
+    }
+}
+
+/// Datasheet name, see [`Register::name`]
+impl core::fmt::Display for Register {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Register starting at `address`
+impl TryFrom<u8> for Register {
+    type Error = InvalidRegister;
+
+    fn try_from(address: u8) -> Result<Self, Self::Error> {
+        Register::ALL
+            .iter()
+            .find(|reg| reg.address() == address)
+            .copied()
+            .ok_or(InvalidRegister(address))
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct AlertMask: u8 {
+        const PortStatus            = 0b0100_0000;
+        const TypeCMonitoringStatus = 0b0010_0000;
+        const CCFaultStatus         = 0b0001_0000;
+        const PRTStatus             = 0b0000_0010;
+
+        const _Default = Self::PortStatus.bits() | Self::TypeCMonitoringStatus.bits() | Self::PRTStatus.bits();
+    }
+}
+
+impl Default for AlertMask {
+    fn default() -> Self {
+        AlertMask::_Default
+    }
+}
+
+bitflags! {
+    /// VBUS_DISCHARGE_CTRL
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct VbusDischargeCtrl: u8 {
+        /// Discharge VBUS to 0V on detach
+        const Discharge0V         = 0b1000_0000;
+        /// Discharge VBUS on a transition to a lower voltage
+        const DischargeTransition = 0b0100_0000;
+    }
+}
+
+bitflags! {
+    /// VBUS_CTRL, the other bits are reserved
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct VbusCtrl: u8 {
+        /// Drive VBUS_EN_SNK, closing the sink power path (SINK_VBUS_EN)
+        const SinkVbusEnable = 0b0000_0010;
+    }
+}
+
+bitflags! {
+    /// GPIO_SW_GPIO, only used when the GPIO is configured as [`GpioCfg::SwCtrl`]
+    ///
+    /// [`GpioCfg::SwCtrl`]: crate::config::GpioCfg::SwCtrl
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct GpioSwGpio: u8 {
+        /// Pull the open drain GPIO low, Hi-Z otherwise
+        const Low = 0b0000_0001;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Alert: u8 {
+        const PortStatus            = 0b0100_0000;
+        const TypeCMonitoringStatus = 0b0010_0000;
+        const CCHWFaultStatus       = 0b0001_0000;
+        const PDTypeCStatus         = 0b0000_1000;
+        const PRTStatus             = 0b0000_0010;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct PortStatus1: u8 {
+        /// Sourcing power, always clear on the STUSB4500 (POWER_MODE)
+        const PowerMode = 0b0000_1000;
+        /// Attached as DFP instead of UFP (DATA_MODE)
+        const DataMode  = 0b0000_0100;
+        const Attach    = 0b0000_0001;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct TypeCMonitoringStatus0: u8 {
+        const VbusHighStatus   = 0b0010_0000;
+        const VbusLowStatus    = 0b0001_0000;
+        const VbusReadyTrans   = 0b0000_1000;
+        const VbusValidSnkTrans = 0b0000_0100;
+        const VbusVSafe0VTrans = 0b0000_0010;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct TypeCMonitoringStatus1: u8 {
+        const VbusReady    = 0b0000_1000;
+        const VbusValidSnk = 0b0000_0100;
+        const VbusVSafe0V  = 0b0000_0010;
+    }
+}
+
+bitflags! {
+    /// CC_HW_FAULT_STATUS_0, transitions of CC_HW_FAULT_STATUS_1
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct CCHWFaultStatus0: u8 {
+        const VpuOvpFaultTrans = 0b0010_0000;
+        const VpuValidTrans    = 0b0001_0000;
+    }
+}
+
+bitflags! {
+    /// CC_HW_FAULT_STATUS_1
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct CCHWFaultStatus1: u8 {
+        /// Over-voltage on the CC pins
+        const VpuOvpFault    = 0b1000_0000;
+        const VpuValid       = 0b0100_0000;
+        /// VBUS discharge path failed
+        const VbusDischFault = 0b0001_0000;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct PRTStatus: u8 {
+        const BistReceived    = 0b0001_0000;
+        const MsgReceived     = 0b0000_0100;
+        const HwResetReceived = 0b0000_0001;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct NvmCtrl0: u8 {
+        const Power   = 0b1000_0000;
+        const Enable  = 0b0100_0000;
+        const Request = 0b0001_0000;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct NvmCtrl1: u8 {
+        const EraseSector0 = 0b0000_1000;
+        const EraseSector1 = 0b0001_0000;
+        const EraseSector2 = 0b0010_0000;
+        const EraseSector3 = 0b0100_0000;
+        const EraseSector4 = 0b1000_0000;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NvmCtrl1Opcode {
+    ReadSector = 0x00,   // Read the sector data
+    LoadPlr = 0x01,      // Load the Program Load Register
+    LoadSer = 0x02,      // Load the Sector Erase Register
+    DumpPlr = 0x03,      // Dump the Program Load Register
+    DumpSer = 0x04,      // Dump the Sector Erase Register
+    EraseSectors = 0x05, // Erase the specified sectors
+    WriteSector = 0x06,  // Program the sector data to EEPROM
+}
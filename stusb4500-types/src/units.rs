@@ -0,0 +1,131 @@
+//! Unit typed API using [`uom`] quantities, enabled by the `uom` feature
+
+use uom::si::electric_current::milliampere;
+use uom::si::electric_potential::millivolt;
+use uom::si::f32::{ElectricCurrent, ElectricPotential, Power};
+use uom::si::power::milliwatt;
+
+use crate::config::{ConfigError, NvmConfig};
+use crate::pdo::{AugmentedPdo, BatteryPdo, FixedPdo, OutOfRange, VariablePdo};
+use crate::PdoChannel;
+
+/// Round to the nearest integer, negative values saturate to 0
+fn round(value: f32) -> u32 {
+    (value + 0.5) as u32
+}
+
+fn mv(voltage: ElectricPotential) -> u32 {
+    round(voltage.get::<millivolt>())
+}
+
+fn ma(current: ElectricCurrent) -> u32 {
+    round(current.get::<milliampere>())
+}
+
+fn voltage(mv: u32) -> ElectricPotential {
+    ElectricPotential::new::<millivolt>(mv as f32)
+}
+
+fn current(ma: u32) -> ElectricCurrent {
+    ElectricCurrent::new::<milliampere>(ma as f32)
+}
+
+impl FixedPdo {
+    pub fn from_si(
+        voltage: ElectricPotential,
+        current: ElectricCurrent,
+    ) -> Result<Self, OutOfRange> {
+        let mut pdo = FixedPdo::default();
+        pdo.try_set_voltage(mv(voltage) / 50)?;
+        pdo.try_set_current(ma(current) / 10)?;
+        Ok(pdo)
+    }
+
+    pub fn voltage_si(&self) -> ElectricPotential {
+        voltage(self.voltage() * 50)
+    }
+
+    pub fn current_si(&self) -> ElectricCurrent {
+        current(self.current() * 10)
+    }
+}
+
+impl VariablePdo {
+    pub fn max_voltage_si(&self) -> ElectricPotential {
+        voltage(self.max_voltage() * 50)
+    }
+
+    pub fn min_voltage_si(&self) -> ElectricPotential {
+        voltage(self.min_voltage() * 50)
+    }
+
+    pub fn current_si(&self) -> ElectricCurrent {
+        current(self.current() * 10)
+    }
+}
+
+impl BatteryPdo {
+    pub fn max_voltage_si(&self) -> ElectricPotential {
+        voltage(self.max_voltage() * 50)
+    }
+
+    pub fn min_voltage_si(&self) -> ElectricPotential {
+        voltage(self.min_voltage() * 50)
+    }
+
+    pub fn power_si(&self) -> Power {
+        Power::new::<milliwatt>((self.power() * 250) as f32)
+    }
+}
+
+impl AugmentedPdo {
+    pub fn max_voltage_si(&self) -> ElectricPotential {
+        voltage(self.max_voltage() * 100)
+    }
+
+    pub fn min_voltage_si(&self) -> ElectricPotential {
+        voltage(self.min_voltage() * 100)
+    }
+
+    pub fn max_current_si(&self) -> ElectricCurrent {
+        current(self.max_current() * 50)
+    }
+}
+
+impl NvmConfig {
+    pub fn pdo_voltage_si(&self, pdo: PdoChannel) -> ElectricPotential {
+        voltage(self.pdo_voltage_mv(pdo) as u32)
+    }
+
+    pub fn set_pdo_voltage_si(
+        &mut self,
+        pdo: PdoChannel,
+        voltage: ElectricPotential,
+    ) -> Result<(), ConfigError> {
+        let voltage = u16::try_from(mv(voltage)).map_err(|_| ConfigError::OutOfRange)?;
+        self.set_pdo_voltage_mv(pdo, voltage)
+    }
+
+    /// PDO current, resolving the flexible current
+    pub fn pdo_current_si(&self, pdo: PdoChannel) -> ElectricCurrent {
+        current(self.pdo_effective_current_ma(pdo) as u32)
+    }
+
+    pub fn set_pdo_current_si(
+        &mut self,
+        pdo: PdoChannel,
+        current: ElectricCurrent,
+    ) -> Result<(), ConfigError> {
+        let current = u16::try_from(ma(current)).map_err(|_| ConfigError::OutOfRange)?;
+        self.set_pdo_current_ma(pdo, current)
+    }
+
+    pub fn flex_current_si(&self) -> ElectricCurrent {
+        current(self.flex_current_ma() as u32)
+    }
+
+    pub fn set_flex_current_si(&mut self, current: ElectricCurrent) -> Result<(), ConfigError> {
+        let current = u16::try_from(ma(current)).map_err(|_| ConfigError::OutOfRange)?;
+        self.set_flex_current_ma(current)
+    }
+}
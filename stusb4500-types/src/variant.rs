@@ -0,0 +1,99 @@
+//! Chip variant and supported specification versions
+
+use crate::image::NvmImage;
+
+/// Chip variant, as reported by DEVICE_ID
+///
+/// This is the finest identification of the silicon the chip exposes, there is no register
+/// holding the silicon cut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Variant {
+    /// DEVICE_ID 0x25
+    Stusb4500,
+    /// DEVICE_ID 0x21
+    Stusb4500B,
+    /// Any other DEVICE_ID, e.g. not a STUSB4500 at all
+    Unknown(u8),
+}
+
+impl From<u8> for Variant {
+    fn from(id: u8) -> Self {
+        match id {
+            0x25 => Variant::Stusb4500,
+            0x21 => Variant::Stusb4500B,
+            id => Variant::Unknown(id),
+        }
+    }
+}
+
+impl Variant {
+    /// Raw DEVICE_ID
+    pub fn device_id(&self) -> u8 {
+        match self {
+            Variant::Stusb4500 => 0x25,
+            Variant::Stusb4500B => 0x21,
+            Variant::Unknown(id) => *id,
+        }
+    }
+
+    /// Factory NVM content of this variant, see [`NvmImage::factory_default`]
+    pub fn factory_image(&self) -> Option<NvmImage> {
+        NvmImage::factory_default(*self)
+    }
+}
+
+/// Variant plus the supported Type-C and USB PD specification revisions
+///
+/// The specification revisions don't identify the silicon cut, use [`Variant`] for that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SpecVersions {
+    pub variant: Variant,
+    /// Type-C revision in BCD, e.g. 0x0120 for 1.2 (BCD_TYPEC_REV)
+    pub typec: u16,
+    /// USB PD revision in BCD, e.g. 0x0300 for 3.0 (BCD_USBPD_REV)
+    pub usb_pd: u16,
+}
+
+fn fmt_bcd(f: &mut core::fmt::Formatter<'_>, bcd: u16) -> core::fmt::Result {
+    write!(f, "{:x}.{:x}", bcd >> 8, (bcd >> 4) & 0xF)
+}
+
+impl core::fmt::Display for SpecVersions {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.variant {
+            Variant::Stusb4500 => write!(f, "STUSB4500")?,
+            Variant::Stusb4500B => write!(f, "STUSB4500B")?,
+            Variant::Unknown(_) => write!(f, "Unknown")?,
+        }
+        write!(f, " (ID {:#04x}), Type-C ", self.variant.device_id())?;
+        fmt_bcd(f, self.typec)?;
+        write!(f, ", USB PD ")?;
+        fmt_bcd(f, self.usb_pd)
+    }
+}
+
+/// Identification for asset reporting, see
+/// `STUSB4500::device_info` in the driver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceInfo {
+    pub spec_versions: SpecVersions,
+    /// NVM sector 0: two bytes of vendor data followed by the identification bytes programmed
+    /// at the factory
+    pub nvm_id: [u8; 8],
+}
+
+impl DeviceInfo {
+    /// Raw DEVICE_ID
+    pub fn device_id(&self) -> u8 {
+        self.spec_versions.variant.device_id()
+    }
+
+    /// Vendor data from NVM, see
+    /// [`NvmConfig::vendor_data`](crate::config::NvmConfig::vendor_data)
+    pub fn vendor_data(&self) -> u16 {
+        u16::from_le_bytes([self.nvm_id[0], self.nvm_id[1]])
+    }
+}
@@ -0,0 +1,98 @@
+//! Offline decoding and comparison of NVM images
+
+use stusb4500::{config::NvmConfig, image::NvmImage, PdoChannel};
+
+/// Decoded configuration fields as (name, value) pairs
+pub fn fields(config: &NvmConfig) -> Vec<(String, String)> {
+    let mut fields = vec![("PDO count".to_string(), config.pdo_count().to_string())];
+    for ch in PdoChannel::iter() {
+        let n = usize::from(ch) + 1;
+        fields.push((
+            format!("PDO{n} voltage"),
+            format!("{:.2} V", config.pdo_voltage_mv(ch) as f32 / 1000.0),
+        ));
+        let current = match config.pdo_current_ma(ch) {
+            Some(current) => format!("{:.2} A", current as f32 / 1000.0),
+            None => "flex".to_string(),
+        };
+        fields.push((format!("PDO{n} current"), current));
+        let window = match config.vbus_thresholds_mv(ch) {
+            (Some(low), high) => {
+                format!("{:.2}-{:.2} V", low as f32 / 1000.0, high as f32 / 1000.0)
+            }
+            (None, high) => format!("UVLO-{:.2} V", high as f32 / 1000.0),
+        };
+        fields.push((format!("PDO{n} VBUS window"), window));
+    }
+    let flag = |name: &str, value: bool| (name.to_string(), value.to_string());
+    fields.extend([
+        (
+            "Flex current".to_string(),
+            format!("{:.2} A", config.flex_current_ma() as f32 / 1000.0),
+        ),
+        flag("Unconstrained power", config.unconstrained_power()),
+        flag("USB comm capable", config.usb_comm_capable()),
+        flag("Power only above 5V", config.power_only_above_5v()),
+        flag("Request source current", config.req_src_current()),
+        (
+            "POWER_OK".to_string(),
+            match config.power_ok() {
+                Some(cfg) => format!("{cfg:?}"),
+                None => "reserved".to_string(),
+            },
+        ),
+        ("GPIO".to_string(), format!("{:?}", config.gpio_cfg())),
+        (
+            "Discharge time to 0V".to_string(),
+            format!("{} ms", config.discharge_time_to_0v_ms()),
+        ),
+        (
+            "Discharge time transition".to_string(),
+            format!("{} ms", config.discharge_time_transition_ms()),
+        ),
+        (
+            "Vendor data".to_string(),
+            format!("0x{:04X}", config.vendor_data()),
+        ),
+    ]);
+    fields
+}
+
+/// Print byte and decoded field differences, returns whether the images differ
+pub fn print_diff(old: &NvmImage, new: &NvmImage) -> bool {
+    let bytes: Vec<_> = old.diff(new).collect();
+    if bytes.is_empty() {
+        println!("Images are identical");
+        return false;
+    }
+
+    println!("Bytes:");
+    for diff in &bytes {
+        println!(
+            "  sector {} byte {} (0x{:02X}): 0x{:02X} -> 0x{:02X}",
+            diff.sector,
+            diff.offset,
+            0xC0 + diff.sector * 8 + diff.offset,
+            diff.old,
+            diff.new
+        );
+    }
+
+    let old_fields = fields(&NvmConfig::new(*old));
+    let new_fields = fields(&NvmConfig::new(*new));
+    let changed: Vec<_> = old_fields
+        .iter()
+        .zip(&new_fields)
+        .filter(|(old, new)| old.1 != new.1)
+        .collect();
+    println!();
+    if changed.is_empty() {
+        println!("No decoded field changed (undocumented bits only)");
+    } else {
+        println!("Fields:");
+        for ((name, old), (_, new)) in changed {
+            println!("  {name:<27} {old} -> {new}");
+        }
+    }
+    true
+}
@@ -0,0 +1,188 @@
+//! Errors reported to the user instead of panicking
+
+use embedded_hal::i2c::{Error as _, ErrorKind, NoAcknowledgeSource};
+use linux_embedded_hal::{i2cdev::linux::LinuxI2CError, I2CError};
+use std::{fmt, io, path::PathBuf};
+use stusb4500::config::ConfigError;
+
+/// Driver error on the linux I2C bus
+pub type DeviceError = stusb4500::Error<I2CError>;
+
+#[derive(Debug)]
+pub enum CliError {
+    /// The I2C bus couldn't be opened
+    Bus(PathBuf, LinuxI2CError),
+    /// Accessing the device failed while doing what the message says
+    Device(&'static str, DeviceError),
+    /// Reading or writing a file failed
+    File(PathBuf, io::Error),
+    /// The file is too short to contain an NVM image
+    InvalidImage(PathBuf),
+    /// A parameter is outside what the NVM can store
+    Config(ConfigError),
+    /// A configuration file couldn't be parsed
+    ConfigFile(PathBuf, String),
+    /// Terminal or network error while running `monitor` or `tui`
+    Io(io::Error),
+    /// Refused to continue, with the reason
+    Aborted(String),
+    /// `check-default` found the NVM different from the reference image, which is named
+    Drift(&'static str),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Bus(path, err) => write!(f, "Failed to open {}: {}", path.display(), err),
+            CliError::Device(what, stusb4500::Error::NvmLocked) => {
+                write!(f, "{}: this unit's NVM is write protected", what)
+            }
+            CliError::Device(what, stusb4500::Error::InterruptedProgramming(sector)) => write!(
+                f,
+                "{}: device lost at sector {}, reconnect and write again (e.g. `restore` the backup)",
+                what, sector
+            ),
+            CliError::Device(what, stusb4500::Error::ReadOnly) => {
+                write!(f, "{}: not allowed with --read-only", what)
+            }
+            CliError::Device(what, err) => write!(f, "{}: {:?}", what, err),
+            CliError::File(path, err) => write!(f, "{}: {}", path.display(), err),
+            CliError::InvalidImage(path) => write!(
+                f,
+                "{} must contain at least 40 bytes of NVM data",
+                path.display()
+            ),
+            CliError::Config(err) => write!(f, "Invalid configuration: {}", err),
+            CliError::ConfigFile(path, err) => {
+                write!(f, "Invalid configuration in {}: {}", path.display(), err)
+            }
+            CliError::Io(err) => write!(f, "{}", err),
+            CliError::Aborted(reason) => write!(f, "{}", reason),
+            CliError::Drift(reference) => write!(f, "NVM differs from the {}", reference),
+        }
+    }
+}
+
+/// Process exit status by failure cause, listed in `--help`. Don't renumber, scripts rely on them.
+///
+/// 2 is used by clap for invalid arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Anything not listed below, e.g. a missing file or invalid parameter
+    Failure = 1,
+    /// Nothing acknowledged the address or it isn't an STUSB4500
+    NoDevice = 3,
+    /// I2C bus couldn't be opened or a transfer failed
+    Bus = 4,
+    /// The NVM is write protected
+    NvmLocked = 5,
+    /// The NVM read back different from what was written
+    VerifyFailed = 6,
+    /// The device didn't complete a request in time
+    Timeout = 7,
+    /// Refused to continue, e.g. unknown variant without `--force` or a write with
+    /// `--read-only`
+    Aborted = 8,
+    /// `check-default` found the NVM changed
+    Drift = 9,
+    /// The device went away while programming, the NVM content is unknown
+    Interrupted = 10,
+}
+
+impl ExitCode {
+    /// Identifier used in `--errors json`
+    pub fn name(self) -> &'static str {
+        match self {
+            ExitCode::Failure => "failure",
+            ExitCode::NoDevice => "no_device",
+            ExitCode::Bus => "bus",
+            ExitCode::NvmLocked => "nvm_locked",
+            ExitCode::VerifyFailed => "verify_failed",
+            ExitCode::Timeout => "timeout",
+            ExitCode::Aborted => "aborted",
+            ExitCode::Drift => "drift",
+            ExitCode::Interrupted => "interrupted",
+        }
+    }
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> ExitCode {
+        use stusb4500::Error;
+        match self {
+            CliError::Bus(..) => ExitCode::Bus,
+            CliError::Device(_, err) => match err {
+                Error::Register { source, .. } | Error::RetriesExhausted { source, .. } => {
+                    match source.kind() {
+                        ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address) => {
+                            ExitCode::NoDevice
+                        }
+                        _ => ExitCode::Bus,
+                    }
+                }
+                Error::UnknownDevice(_) | Error::NotReady(_) => ExitCode::NoDevice,
+                Error::NvmLocked => ExitCode::NvmLocked,
+                Error::VerifyFailed(_) | Error::RolledBack(_) => ExitCode::VerifyFailed,
+                Error::Timeout | Error::NvmTimeout => ExitCode::Timeout,
+                Error::ReadOnly => ExitCode::Aborted,
+                Error::InterruptedProgramming(_) => ExitCode::Interrupted,
+                _ => ExitCode::Failure,
+            },
+            CliError::Aborted(_) => ExitCode::Aborted,
+            CliError::Drift(_) => ExitCode::Drift,
+            CliError::File(..)
+            | CliError::InvalidImage(_)
+            | CliError::Config(_)
+            | CliError::ConfigFile(..)
+            | CliError::Io(_) => ExitCode::Failure,
+        }
+    }
+
+    /// Single line JSON object for `--errors json`
+    pub fn to_json(&self) -> String {
+        let code = self.exit_code();
+        format!(
+            r#"{{"error":"{}","code":{},"message":"{}"}}"#,
+            code.name(),
+            code as u8,
+            escape_json(&self.to_string())
+        )
+    }
+}
+
+pub fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl From<ConfigError> for CliError {
+    fn from(err: ConfigError) -> Self {
+        CliError::Config(err)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(err: io::Error) -> Self {
+        CliError::Io(err)
+    }
+}
+
+/// Attach what was being done to a device error, e.g. `.context("Failed to unlock NVM")?`
+pub trait Context<T> {
+    fn context(self, what: &'static str) -> Result<T, CliError>;
+}
+
+impl<T> Context<T> for Result<T, DeviceError> {
+    fn context(self, what: &'static str) -> Result<T, CliError> {
+        self.map_err(|err| CliError::Device(what, err))
+    }
+}
@@ -0,0 +1,212 @@
+//! Build an NVM image from command line parameters, without hardware
+
+use clap::{Args, ValueEnum};
+use std::path::PathBuf;
+use stusb4500::{
+    config::{ConfigError, GpioCfg, NvmConfig, PowerOk},
+    PdoChannel,
+};
+
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// Output file for the 40 byte NVM image
+    #[arg(short, long, value_name = "FILE")]
+    pub output: PathBuf,
+
+    /// Start from this NVM file instead of the factory image
+    #[arg(long, value_name = "FILE")]
+    pub base: Option<PathBuf>,
+
+    /// PDO1 current, e.g. 1.5A. The voltage is always 5V
+    #[arg(long, value_name = "CURRENT", value_parser = parse_current)]
+    pdo1: Option<u16>,
+
+    /// PDO2 voltage and current, e.g. 9V/3A
+    #[arg(long, value_name = "VOLTAGE/CURRENT", value_parser = parse_pdo)]
+    pdo2: Option<(u16, u16)>,
+
+    /// PDO3 voltage and current, e.g. 15V/2A
+    #[arg(long, value_name = "VOLTAGE/CURRENT", value_parser = parse_pdo)]
+    pdo3: Option<(u16, u16)>,
+
+    /// Number of PDOs advertised, defaults to the highest PDO given
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=3))]
+    pdo_count: Option<u8>,
+
+    /// Flexible current, e.g. 2A
+    #[arg(long, value_name = "CURRENT", value_parser = parse_current)]
+    flex_current: Option<u16>,
+
+    /// PDOs using the flexible current, e.g. --flex-pdo 2 --flex-pdo 3
+    #[arg(long, value_name = "PDO", value_parser = clap::value_parser!(u8).range(1..=3))]
+    flex_pdo: Vec<u8>,
+
+    /// POWER_OK pin configuration
+    #[arg(long, value_enum)]
+    power_ok: Option<PowerOkArg>,
+
+    /// GPIO pin function
+    #[arg(long, value_enum)]
+    gpio: Option<GpioArg>,
+
+    #[arg(long)]
+    unconstrained_power: Option<bool>,
+
+    #[arg(long)]
+    usb_comm_capable: Option<bool>,
+
+    #[arg(long)]
+    power_only_above_5v: Option<bool>,
+
+    #[arg(long)]
+    req_src_current: Option<bool>,
+
+    /// VBUS discharge time to 0V in ms
+    #[arg(long, value_name = "MS")]
+    discharge_time_to_0v: Option<u16>,
+
+    /// VBUS discharge time on a PDO transition in ms
+    #[arg(long, value_name = "MS")]
+    discharge_time_transition: Option<u16>,
+
+    /// Vendor data, e.g. 0x0102
+    #[arg(long, value_parser = parse_u16)]
+    vendor_data: Option<u16>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PowerOkArg {
+    Config1,
+    Config2,
+    Config3,
+}
+
+impl From<PowerOkArg> for PowerOk {
+    fn from(arg: PowerOkArg) -> Self {
+        match arg {
+            PowerOkArg::Config1 => PowerOk::Config1,
+            PowerOkArg::Config2 => PowerOk::Config2,
+            PowerOkArg::Config3 => PowerOk::Config3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GpioArg {
+    SwCtrl,
+    ErrorRecovery,
+    Debug,
+    SinkPower,
+}
+
+impl From<GpioArg> for GpioCfg {
+    fn from(arg: GpioArg) -> Self {
+        match arg {
+            GpioArg::SwCtrl => GpioCfg::SwCtrl,
+            GpioArg::ErrorRecovery => GpioCfg::ErrorRecovery,
+            GpioArg::Debug => GpioCfg::Debug,
+            GpioArg::SinkPower => GpioCfg::SinkPower,
+        }
+    }
+}
+
+/// Apply the parameters on top of `base`
+pub fn build(base: NvmConfig, args: &GenerateArgs) -> Result<NvmConfig, ConfigError> {
+    let mut config = base;
+    if let Some(current) = args.pdo1 {
+        config.set_pdo_current_ma(PdoChannel::PDO1, current)?;
+    }
+    for (ch, pdo) in [(PdoChannel::PDO2, args.pdo2), (PdoChannel::PDO3, args.pdo3)] {
+        if let Some((voltage, current)) = pdo {
+            config.set_pdo_voltage_mv(ch, voltage)?;
+            config.set_pdo_current_ma(ch, current)?;
+        }
+    }
+    let highest = match (args.pdo2, args.pdo3) {
+        (_, Some(_)) => Some(3),
+        (Some(_), None) => Some(2),
+        (None, None) => None,
+    };
+    if let Some(count) = args.pdo_count.or(highest) {
+        config.set_pdo_count(count)?;
+    }
+
+    if let Some(current) = args.flex_current {
+        config.set_flex_current_ma(current)?;
+    }
+    for pdo in &args.flex_pdo {
+        config.set_pdo_flex_current(PdoChannel::ALL[*pdo as usize - 1]);
+    }
+
+    if let Some(cfg) = args.power_ok {
+        config.set_power_ok(cfg.into());
+    }
+    if let Some(cfg) = args.gpio {
+        config.set_gpio_cfg(cfg.into());
+    }
+    if let Some(enable) = args.unconstrained_power {
+        config.set_unconstrained_power(enable);
+    }
+    if let Some(enable) = args.usb_comm_capable {
+        config.set_usb_comm_capable(enable);
+    }
+    if let Some(enable) = args.power_only_above_5v {
+        config.set_power_only_above_5v(enable);
+    }
+    if let Some(enable) = args.req_src_current {
+        config.set_req_src_current(enable);
+    }
+    if let Some(time) = args.discharge_time_to_0v {
+        config.set_discharge_time_to_0v_ms(time)?;
+    }
+    if let Some(time) = args.discharge_time_transition {
+        config.set_discharge_time_transition_ms(time)?;
+    }
+    if let Some(data) = args.vendor_data {
+        config.set_vendor_data(data);
+    }
+    Ok(config)
+}
+
+/// Parse a value with a unit suffix (e.g. `1.5A` or `1500mA`) into milli-units
+fn parse_milli(s: &str, unit: char) -> Result<u16, String> {
+    let s = s.trim();
+    let (number, scale) = if let Some(n) = s.strip_suffix(&format!("m{unit}")) {
+        (n, 1.0)
+    } else if let Some(n) = s.strip_suffix(unit) {
+        (n, 1000.0)
+    } else {
+        return Err(format!(
+            "expected a value in {unit} or m{unit}, e.g. 1.5{unit}"
+        ));
+    };
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid number '{number}'"))?;
+    let milli = (value * scale).round();
+    if !(0.0..=u16::MAX as f64).contains(&milli) {
+        return Err(format!("'{s}' out of range"));
+    }
+    Ok(milli as u16)
+}
+
+fn parse_current(s: &str) -> Result<u16, String> {
+    parse_milli(s, 'A')
+}
+
+/// Parse `VOLTAGE/CURRENT`, e.g. `9V/3A`, into mV and mA
+fn parse_pdo(s: &str) -> Result<(u16, u16), String> {
+    let (voltage, current) = s
+        .split_once('/')
+        .ok_or_else(|| "expected VOLTAGE/CURRENT, e.g. 9V/3A".to_string())?;
+    Ok((parse_milli(voltage, 'V')?, parse_milli(current, 'A')?))
+}
+
+fn parse_u16(s: &str) -> Result<u16, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|e| e.to_string())
+}
@@ -5,30 +5,66 @@
 //!
 //! [gui]: https://www.st.com/en/embedded-software/stsw-stusb002.html
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::{
     fs::File,
     io::{Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use stusb4500::{
+    config::NvmConfig,
+    image::NvmImage,
+    nvm::{NvmProgress, NvmReport},
+    pdo, Address, PdoChannel, STUSB4500, STUSB4500_ADDR,
 };
-use stusb4500::{nvm::DEFAULT_NVM_DATA, pdo, Address, PdoChannel, STUSB4500, STUSB4500_ADDR};
 
 use linux_embedded_hal::I2cdev;
 
+use error::{CliError, Context};
+
+mod decode;
+mod error;
+mod generate;
+mod monitor;
+mod provision;
+#[cfg(feature = "tui")]
+mod tui;
+
 const I2C_BUS: &str = "i2c-0";
 
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Success
+  1  Other failure, e.g. missing file or invalid parameter
+  2  Invalid arguments
+  3  No device at the address, or not an STUSB4500
+  4  I2C bus error
+  5  NVM is write protected
+  6  NVM verification failed
+  7  Device timed out
+  8  Aborted, e.g. unknown variant without --force or a write with --read-only
+  9  check-default found the NVM changed
+  10 Device lost while programming, NVM content unknown";
+
 /// Utility to read and write STUSB4500 NVM
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, after_help = EXIT_CODES)]
 struct Cli {
-    /// Turn debugging information on
-    #[arg(short, action = clap::ArgAction::Count)]
-    verbosity: u8,
+    /// Turn debugging information on, -d for NVM and protocol steps, -dd also logs every
+    /// register access
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    debug: u8,
 
     /// Turn off all standard output
     #[arg(short, long)]
     silent: bool,
 
+    /// How to report a failure on stderr. `json` prints a single
+    /// `{"error":..,"code":..,"message":..}` line, even with --silent
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Text)]
+    errors: ErrorFormat,
+
     /// Optional I2C bus for access to stusb4500. Can be either the bus name or absolute path.
     /// Defaults to i2c-0
     #[arg(short, long, default_value=I2C_BUS)]
@@ -39,10 +75,21 @@ struct Cli {
     #[arg(short, long, default_value_t=STUSB4500_ADDR)]
     address: u8,
 
+    /// Refuse all register writes, so only `status` and `monitor` work. The device can't be
+    /// reconfigured or its NVM changed, even by mistake
+    #[arg(long)]
+    read_only: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum ErrorFormat {
+    Text,
+    Json,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Read register block from STUSB4500 NVM
@@ -56,34 +103,168 @@ enum Commands {
         /// Set a custom output file
         #[arg(short, long, value_name = "FILE")]
         file: PathBuf,
+
+        /// Don't save the current NVM content before erasing it
+        #[arg(long)]
+        no_backup: bool,
+
+        /// Erase and program all sectors, not just the ones that changed
+        #[arg(long)]
+        all: bool,
     },
     /// Write factory reset register block to STUSB4500 NVM
     /// as generated by the ST GUI application - so be careful.
-    FactoryReset,
+    FactoryReset {
+        /// Don't save the current NVM content before erasing it
+        #[arg(long)]
+        no_backup: bool,
+
+        /// Write the STUSB4500 factory data even if the chip is a different variant
+        #[arg(long)]
+        force: bool,
+    },
+    /// Write a backup created by `write` or `factory-reset` back to STUSB4500 NVM
+    Restore {
+        /// Backup file to restore
+        #[arg(short, long, value_name = "FILE")]
+        file: PathBuf,
+    },
+    /// Read a single NVM sector
+    ReadSector {
+        /// Sector 0-4
+        sector: u8,
+    },
+    /// Erase and write a single NVM sector
+    WriteSector {
+        /// Sector 0-4
+        sector: u8,
+
+        /// Eight bytes as hex, e.g. 00004BA0A1430040
+        #[arg(short, long, value_parser = parse_sector)]
+        data: [u8; 8],
+    },
+    /// Unlock the NVM and leave it unlocked
+    NvmUnlock,
+    /// Lock the NVM
+    NvmLock,
+    /// Show whether the NVM is unlocked
+    NvmStatus,
     /// Show status information
     Status,
+    /// Periodically print attach state, VBUS and contract
+    Monitor {
+        /// Sampling interval in ms
+        #[arg(short, long, default_value_t = 1000)]
+        interval: u64,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = monitor::Output::Text)]
+        output: monitor::Output,
+
+        /// Serve Prometheus metrics on this address, e.g. 0.0.0.0:9100
+        #[arg(long, value_name = "ADDR")]
+        metrics: Option<String>,
+    },
+    /// Interactive dashboard showing live status
+    #[cfg(feature = "tui")]
+    Tui,
+    /// Compare the NVM against the factory default of the detected variant and show what
+    /// changed. Exits with 9 if it differs
+    CheckDefault {
+        /// Compare against this NVM file instead, e.g. the image used for provisioning
+        #[arg(long, value_name = "FILE")]
+        golden: Option<PathBuf>,
+    },
+    /// Compare two NVM files without accessing the device
+    Diff {
+        /// Original NVM file
+        old: PathBuf,
+
+        /// Changed NVM file
+        new: PathBuf,
+    },
+    /// Build an NVM file from parameters without accessing the device
+    Generate(generate::GenerateArgs),
+    /// Program the NVM from a configuration file, verify it and write a JSON report with the
+    /// device ID, NVM fingerprint and decoded fields. The report is written on failure too
+    Provision {
+        /// TOML file with NVM fields, e.g. `V_SNK_PDO2 = 15000`. Missing fields keep their
+        /// factory value
+        #[arg(short, long, value_name = "FILE")]
+        config: PathBuf,
+
+        /// Output file for the JSON report
+        #[arg(short, long, value_name = "FILE")]
+        report: PathBuf,
+    },
 }
 
 fn main() {
     let cli = Cli::parse();
 
     env_logger::Builder::new()
-        .filter_level(match (cli.verbosity, cli.silent) {
+        .filter_level(match (cli.debug, cli.silent) {
             (_, true) => log::LevelFilter::Off,
             (0, _) => log::LevelFilter::Info,
             (1, _) => log::LevelFilter::Debug,
             (2.., _) => log::LevelFilter::Trace,
         })
         .init();
-    println!("{:?}", log::max_level());
+
+    let errors = cli.errors;
+    if let Err(err) = run(cli) {
+        match errors {
+            ErrorFormat::Text => log::error!("{}", err),
+            ErrorFormat::Json => eprintln!("{}", err.to_json()),
+        }
+        std::process::exit(err.exit_code() as i32);
+    }
+}
+
+fn run(cli: Cli) -> Result<(), CliError> {
+    // Offline commands
+    match &cli.command {
+        Some(Commands::Diff { old, new }) => {
+            let old = NvmImage(read_nvm_file(old)?);
+            let new = NvmImage(read_nvm_file(new)?);
+            decode::print_diff(&old, &new);
+            return Ok(());
+        }
+        Some(Commands::Generate(args)) => {
+            let base = match &args.base {
+                Some(path) => NvmConfig::new(NvmImage(read_nvm_file(path)?)),
+                None => NvmConfig::default(),
+            };
+            let config = generate::build(base, args)?;
+            write_nvm_file(&args.output, &config.image().0)?;
+            for (name, value) in decode::fields(&config) {
+                log::info!("{:<27} {}", name, value);
+            }
+            log::info!("Wrote {}", args.output.display());
+            return Ok(());
+        }
+        _ => {}
+    }
+    // Reject a bad configuration before touching the device
+    let provision_config = match &cli.command {
+        Some(Commands::Provision { config, .. }) => Some(provision::load_config(config)?),
+        _ => None,
+    };
 
     // Instantiate the device driver
     let mut bus_path = PathBuf::from("/dev/");
     bus_path = bus_path.join(cli.bus);
-    let mut stusb = STUSB4500::new(
-        I2cdev::new(bus_path).unwrap(),
-        Address::Custom(cli.address & 0x7F),
+    log::debug!(
+        "Opening {} at address 0x{:02X}",
+        bus_path.display(),
+        cli.address & 0x7F
     );
+    let i2c = I2cdev::new(&bus_path).map_err(|err| CliError::Bus(bus_path, err))?;
+    let mut builder = STUSB4500::builder(i2c).address(Address::Custom(cli.address & 0x7F));
+    if cli.read_only {
+        builder = builder.read_only();
+    }
+    let mut stusb = builder.build();
 
     // You can check for the existence of subcommands, and if found use their
     // matches just as you would the top level cmd
@@ -91,15 +272,12 @@ fn main() {
         Commands::Read { file } => {
             log::info!("Reading NVM data:");
 
-            let mut nvm = stusb.unlock_nvm().expect("Failed to unlock NVM");
-            let sectors = nvm.read_sectors().expect("Failed to read sectors");
-            nvm.lock().expect("Failed to lock NVM");
+            let mut nvm = stusb.unlock_nvm().context("Failed to unlock NVM")?;
+            let sectors = nvm.read_sectors().context("Failed to read sectors")?;
+            nvm.lock().context("Failed to lock NVM")?;
 
             if let Some(dereffile) = file.as_deref() {
-                let mut f = File::create(dereffile).expect("Couldn't create file");
-                sectors.iter().for_each(|sector| {
-                    f.write_all(sector).expect("Failed to write");
-                });
+                write_nvm_file(dereffile, &sectors)?;
             } else {
                 sectors.iter().for_each(|sector| {
                     sector.iter().for_each(|byte| print!(" 0x{:02X}", byte));
@@ -107,49 +285,174 @@ fn main() {
                 });
             }
         }
-        Commands::Write { file } => {
-            // Read the file
-            let mut f = File::open(file).expect("File not found");
-
-            let mut buffer: [u8; 40] = [0; 40];
-            f.read(&mut buffer).expect("Buffer overflow");
-            let mut sectors: [[u8; 8]; 5] = [[0; 8]; 5];
+        Commands::Write {
+            file,
+            no_backup,
+            all,
+        } => {
+            let sectors = read_nvm_file(file)?;
 
-            for slice in 0..5 {
-                for idx in 0..8 {
-                    sectors[slice][idx] = buffer[8 * slice + idx];
+            log::info!("Writing NVM data...");
+            let mut nvm = stusb.unlock_nvm().context("Failed to unlock NVM")?;
+            if !no_backup {
+                backup_nvm(&nvm.read_sectors().context("Failed to read sectors")?)?;
+            }
+            let report = if *all {
+                nvm.write_sectors_with_progress(sectors, true, log_progress)
+            } else {
+                nvm.write_changed_sectors(sectors, true)
+            }
+            .context("Failed to write NVM")?;
+            log_report(&report);
+            if !report.changed() {
+                log::info!("NVM already up to date");
+            }
+            nvm.lock().context("Failed to lock NVM")?;
+            log::info!("Done");
+        }
+        Commands::FactoryReset { no_backup, force } => {
+            let variant = stusb.variant().context("Failed to read device ID")?;
+            log::info!("Detected {:?}", variant);
+            let image = match NvmImage::factory_default(variant) {
+                Some(image) => image,
+                None if *force => NvmImage::DEFAULT,
+                None => {
+                    return Err(CliError::Aborted(format!(
+                        "No factory data known for {:?}, use --force to write the STUSB4500 data",
+                        variant
+                    )))
                 }
+            };
+
+            log::info!("Writing factory default NVM data...");
+            let mut nvm = stusb.unlock_nvm().context("Failed to unlock NVM")?;
+            if !no_backup {
+                backup_nvm(&nvm.read_sectors().context("Failed to read sectors")?)?;
             }
+            let report = nvm
+                .write_sectors_with_progress(image.0, true, log_progress)
+                .context("Failed to write NVM")?;
+            log_report(&report);
+            nvm.lock().context("Failed to lock NVM")?;
+            log::info!("Done");
+        }
+        Commands::Restore { file } => {
+            let sectors = read_nvm_file(file)?;
 
-            log::info!("Writing NVM data...");
-            let mut nvm = stusb.unlock_nvm().expect("Failed to unlock NVM");
-            nvm.write_sectors(sectors).expect("Failed to write NVM");
-            nvm.lock().expect("Failed to lock NVM");
+            log::info!("Restoring NVM data from {}...", file.display());
+            let mut nvm = stusb.unlock_nvm().context("Failed to unlock NVM")?;
+            let report = nvm
+                .write_sectors_with_progress(sectors, true, log_progress)
+                .context("Failed to write NVM")?;
+            log_report(&report);
+            nvm.lock().context("Failed to lock NVM")?;
             log::info!("Done");
         }
-        Commands::FactoryReset => {
-            log::info!("Writing factory default NVM data...");
-            let mut nvm = stusb.unlock_nvm().expect("Failed to unlock NVM");
-            nvm.write_sectors(DEFAULT_NVM_DATA)
-                .expect("Failed to write NVM");
-            nvm.lock().expect("Failed to lock NVM");
+        Commands::CheckDefault { golden } => {
+            let (reference, name) = match golden {
+                Some(path) => (NvmImage(read_nvm_file(path)?), "golden image"),
+                None => {
+                    let variant = stusb.variant().context("Failed to read device ID")?;
+                    log::info!("Detected {:?}", variant);
+                    let image = NvmImage::factory_default(variant).ok_or_else(|| {
+                        CliError::Aborted(format!(
+                            "No factory data known for {:?}, use --golden to compare against a file",
+                            variant
+                        ))
+                    })?;
+                    (image, "factory default")
+                }
+            };
+
+            let mut nvm = stusb.unlock_nvm().context("Failed to unlock NVM")?;
+            let live = NvmImage(nvm.read_sectors().context("Failed to read sectors")?);
+            nvm.lock().context("Failed to lock NVM")?;
+
+            println!("Comparing against the {}", name);
+            if decode::print_diff(&reference, &live) {
+                return Err(CliError::Drift(name));
+            }
+        }
+        Commands::Provision { config, report } => {
+            let Some(nvm_config) = &provision_config else {
+                unreachable!("loaded before opening the bus")
+            };
+            provision::run(&mut stusb, nvm_config, config, report)?;
+        }
+        Commands::Diff { .. } | Commands::Generate(_) => {
+            unreachable!("handled before opening the bus")
+        }
+        #[cfg(feature = "tui")]
+        Commands::Tui => {
+            tui::run(&mut stusb)?;
+        }
+        Commands::ReadSector { sector } => {
+            let mut nvm = stusb.unlock_nvm().context("Failed to unlock NVM")?;
+            let data = nvm.read_sector(*sector).context("Failed to read sector")?;
+            nvm.lock().context("Failed to lock NVM")?;
+
+            data.iter().for_each(|byte| print!(" 0x{:02X}", byte));
+            println!();
+        }
+        Commands::WriteSector { sector, data } => {
+            log::info!("Writing NVM sector {}...", sector);
+            let mut nvm = stusb.unlock_nvm().context("Failed to unlock NVM")?;
+            nvm.write_sector(*sector, *data)
+                .context("Failed to write sector")?;
+            nvm.lock().context("Failed to lock NVM")?;
             log::info!("Done");
         }
+        Commands::NvmUnlock => {
+            // Dropping the session leaves the chip unlocked
+            stusb.unlock_nvm().context("Failed to unlock NVM")?;
+            log::info!("NVM unlocked, run nvm-lock when done");
+        }
+        Commands::NvmLock => {
+            stusb.lock_nvm().context("Failed to lock NVM")?;
+            log::info!("NVM locked");
+        }
+        Commands::NvmStatus => {
+            let unlocked = stusb
+                .nvm_is_unlocked()
+                .context("Failed to read NVM state")?;
+            println!("NVM {}", if unlocked { "unlocked" } else { "locked" });
+        }
+        Commands::Monitor {
+            interval,
+            output,
+            metrics,
+        } => {
+            monitor::run(
+                &mut stusb,
+                std::time::Duration::from_millis(*interval),
+                *output,
+                metrics.as_deref(),
+            )?;
+        }
         Commands::Status => {
-            for (n, ch) in [PdoChannel::PDO1, PdoChannel::PDO2, PdoChannel::PDO3]
-                .iter()
-                .enumerate()
-            {
-                println!("PDO{n}:");
-                print_pdo(&stusb.get_pdo(*ch).expect("Failed to read PDO"));
+            let specs = stusb
+                .spec_versions()
+                .context("Failed to read specification versions")?;
+            println!("Device: {}", specs);
+            println!();
+
+            for ch in PdoChannel::iter() {
+                println!("PDO{}:", usize::from(ch) + 1);
+                print_pdo(&stusb.get_pdo(ch).context("Failed to read PDO")?);
                 println!();
             }
 
-            let voltage = stusb.get_voltage().expect("Failed to read voltage");
+            let voltage = stusb.get_voltage().context("Failed to read voltage")?;
             println!("Current Voltage {} V", voltage);
+            let faults = stusb.faults().context("Failed to read faults")?;
+            if faults.is_empty() {
+                println!("Faults: none");
+            } else {
+                println!("Faults: {:?}", faults);
+            }
             println!();
 
-            let current_rdo = stusb.get_current_rdo().expect("Failed to read PDO");
+            let current_rdo = stusb.get_current_rdo().context("Failed to read PDO")?;
             println!("Current RDO:");
             println!("- position                    {}", current_rdo.position());
             println!("- give_back                   {}", current_rdo.give_back());
@@ -179,6 +482,68 @@ fn main() {
             );
         }
     }
+    Ok(())
+}
+
+fn log_progress(progress: NvmProgress) {
+    match progress {
+        NvmProgress::Erased => log::debug!("Erased NVM"),
+        NvmProgress::Written(sector) => log::debug!("Wrote sector {}", sector),
+        NvmProgress::Verified(sector) => log::debug!("Verified sector {}", sector),
+    }
+}
+
+fn log_report(report: &NvmReport) {
+    for (sector, outcome) in report.0.iter().enumerate() {
+        log::info!("Sector {}: {}", sector, outcome);
+    }
+}
+
+/// Parse eight bytes of hex, optionally separated by spaces
+fn parse_sector(s: &str) -> Result<[u8; 8], String> {
+    let hex: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    if hex.len() != 16 || !hex.is_ascii() {
+        return Err("expected 8 bytes (16 hex digits)".to_string());
+    }
+    let mut data = [0; 8];
+    for (byte, chunk) in data.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        let chunk = std::str::from_utf8(chunk).map_err(|e| e.to_string())?;
+        *byte = u8::from_str_radix(chunk, 16).map_err(|e| e.to_string())?;
+    }
+    Ok(data)
+}
+
+/// Read a 40 byte NVM image as written by the `read` subcommand
+fn read_nvm_file(path: &Path) -> Result<[[u8; 8]; 5], CliError> {
+    let file_error = |err| CliError::File(path.to_path_buf(), err);
+    let mut f = File::open(path).map_err(file_error)?;
+
+    let mut buffer: [u8; 40] = [0; 40];
+    f.read_exact(&mut buffer).map_err(|err| match err.kind() {
+        std::io::ErrorKind::UnexpectedEof => CliError::InvalidImage(path.to_path_buf()),
+        _ => file_error(err),
+    })?;
+    Ok(NvmImage::from_bytes(&buffer).0)
+}
+
+/// Write a 40 byte NVM image as read by [`read_nvm_file`]
+fn write_nvm_file(path: &Path, sectors: &[[u8; 8]; 5]) -> Result<(), CliError> {
+    File::create(path)
+        .and_then(|mut f| f.write_all(&NvmImage(*sectors).to_bytes()))
+        .map_err(|err| CliError::File(path.to_path_buf(), err))
+}
+
+/// Save the NVM content to a timestamped file in the current directory
+fn backup_nvm(sectors: &[[u8; 8]; 5]) -> Result<(), CliError> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|t| t.as_secs())
+        .unwrap_or_default();
+    let path = PathBuf::from(format!("stusb4500-backup-{timestamp}.bin"));
+
+    write_nvm_file(&path, sectors)?;
+    log::info!("Saved current NVM data to {}", path.display());
+    Ok(())
 }
 
 fn print_pdo(pdo: &pdo::Pdo) {
@@ -245,15 +610,15 @@ fn print_pdo(pdo: &pdo::Pdo) {
             println!("- voltage:");
             println!(
                 "  max                           {:.2} V",
-                pdo.max_voltage() as f32 / 20.0
+                pdo.max_voltage() as f32 / 10.0
             );
             println!(
                 "  min                           {:.2} V",
-                pdo.min_voltage() as f32 / 20.0
+                pdo.min_voltage() as f32 / 10.0
             );
             println!(
                 "- current                       {:.2} A",
-                pdo.max_current() as f32 * 20.0
+                pdo.max_current() as f32 / 20.0
             );
         }
     }
@@ -0,0 +1,175 @@
+//! Periodic status sampling for long-running tests
+//!
+//! # CSV schema
+//!
+//! `--output csv` starts with a `# stusb4500 monitor csv v1` line followed by the header, then
+//! one row per sample. Columns are only ever added at the end; renaming, removing or changing
+//! the meaning of one bumps the version in the first line. Version 1:
+//!
+//! | Column        | Content                                                               |
+//! |---------------|-----------------------------------------------------------------------|
+//! | `timestamp`   | Unix time in ms                                                       |
+//! | `attach`      | 1 if a source is attached, 0 otherwise                                |
+//! | `contract_mv` | VBUS in mV of the ready contract, 0 without a contract                |
+//! | `contract_ma` | Operating current of the contract in mA, 0 without a contract         |
+//! | `vbus_mv`     | Measured VBUS in mV, 100 mV resolution                                |
+//! | `alerts`      | Unmasked ALERT_STATUS_1 bits as a decimal integer                     |
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use clap::ValueEnum;
+use linux_embedded_hal::I2cdev;
+use stusb4500::{status::ContractState, STUSB4500};
+
+/// First line of the CSV output, see the [module documentation](self)
+pub const CSV_VERSION: &str = "# stusb4500 monitor csv v1";
+
+/// Column names of [`CSV_VERSION`]
+pub const CSV_HEADER: &str = "timestamp,attach,contract_mv,contract_ma,vbus_mv,alerts";
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Output {
+    /// Human readable lines
+    Text,
+    /// Comma separated values with a versioned header, the columns are stable
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
+
+/// A single reading
+#[derive(Clone, Copy, Default)]
+struct Sample {
+    /// Unix time in ms
+    timestamp: u128,
+    attached: bool,
+    vbus_mv: u16,
+    /// Power of the active contract, 0 without a contract
+    contract_mw: u32,
+    /// Operating current of the active contract, 0 without a contract
+    contract_ma: u32,
+    /// VBUS of the ready contract, 0 without a contract
+    contract_mv: u32,
+    /// Unmasked ALERT_STATUS_1 bits
+    alerts: u8,
+}
+
+fn read(stusb: &mut STUSB4500<I2cdev>) -> Result<Sample, String> {
+    let err = |e| format!("{:?}", e);
+    let rdo = stusb.get_current_rdo().map_err(err)?;
+    let contract_mv = match stusb.contract_state().map_err(err)? {
+        ContractState::Ready(contract) => contract.voltage_mv as u32,
+        _ => 0,
+    };
+    let alerts =
+        stusb.get_alerts().map_err(err)?.bits() & !stusb.get_alerts_mask().map_err(err)?.bits();
+    Ok(Sample {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|t| t.as_millis())
+            .unwrap_or_default(),
+        attached: stusb.is_attached().map_err(err)?,
+        vbus_mv: stusb.get_voltage_mv().map_err(err)?,
+        contract_mw: stusb.get_negotiated_power().map_err(err)?.unwrap_or(0),
+        contract_ma: if rdo.position() == 0 {
+            0
+        } else {
+            rdo.operating_current_ma()
+        },
+        contract_mv,
+        alerts,
+    })
+}
+
+fn print(output: Output, s: &Sample) {
+    match output {
+        Output::Text => println!(
+            "{} attached={} vbus={}mV contract={}mW/{}mA",
+            s.timestamp, s.attached, s.vbus_mv, s.contract_mw, s.contract_ma
+        ),
+        Output::Csv => println!(
+            "{},{},{},{},{},{}",
+            s.timestamp, s.attached as u8, s.contract_mv, s.contract_ma, s.vbus_mv, s.alerts
+        ),
+        Output::Jsonl => println!(
+            r#"{{"timestamp":{},"attached":{},"vbus_mv":{},"contract_mv":{},"contract_mw":{},"contract_ma":{}}}"#,
+            s.timestamp, s.attached, s.vbus_mv, s.contract_mv, s.contract_mw, s.contract_ma
+        ),
+    }
+}
+
+fn metrics(s: &Sample) -> String {
+    format!(
+        "# TYPE stusb4500_attached gauge\n\
+         stusb4500_attached {}\n\
+         # TYPE stusb4500_vbus_mv gauge\n\
+         stusb4500_vbus_mv {}\n\
+         # TYPE stusb4500_contract_mv gauge\n\
+         stusb4500_contract_mv {}\n\
+         # TYPE stusb4500_contract_mw gauge\n\
+         stusb4500_contract_mw {}\n\
+         # TYPE stusb4500_contract_ma gauge\n\
+         stusb4500_contract_ma {}\n",
+        s.attached as u8, s.vbus_mv, s.contract_mv, s.contract_mw, s.contract_ma
+    )
+}
+
+/// Serve the latest sample as Prometheus metrics on every request
+fn serve_metrics(addr: impl ToSocketAddrs, latest: Arc<Mutex<Sample>>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    log::info!(
+        "Serving metrics on http://{}/metrics",
+        listener.local_addr()?
+    );
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            // Skip the request, every path returns the metrics
+            let mut line = String::new();
+            let mut reader = BufReader::new(&stream);
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                line.clear();
+            }
+
+            let body = metrics(&latest.lock().unwrap());
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+    });
+    Ok(())
+}
+
+pub fn run(
+    stusb: &mut STUSB4500<I2cdev>,
+    interval: Duration,
+    output: Output,
+    metrics: Option<&str>,
+) -> io::Result<()> {
+    let latest = Arc::new(Mutex::new(Sample::default()));
+    if let Some(addr) = metrics {
+        serve_metrics(addr, latest.clone())?;
+    }
+
+    if let Output::Csv = output {
+        println!("{}\n{}", CSV_VERSION, CSV_HEADER);
+    }
+    loop {
+        match read(stusb) {
+            Ok(sample) => {
+                print(output, &sample);
+                *latest.lock().unwrap() = sample;
+            }
+            Err(e) => log::warn!("Failed to read status: {}", e),
+        }
+        thread::sleep(interval);
+    }
+}
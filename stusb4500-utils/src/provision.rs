@@ -0,0 +1,150 @@
+//! One-shot manufacturing flow: program, verify, decode and report
+
+use linux_embedded_hal::I2cdev;
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use stusb4500::{config::NvmConfig, image::NvmImage, nvm::NvmReport, Error, STUSB4500};
+
+use crate::decode;
+use crate::error::{escape_json, CliError, Context};
+
+/// Parse a configuration file with the field names of the `serde` representation of
+/// [`NvmConfig`], e.g. `V_SNK_PDO2 = 15000`. Missing fields keep their factory value
+pub fn load_config(path: &Path) -> Result<NvmConfig, CliError> {
+    let text = fs::read_to_string(path).map_err(|err| CliError::File(path.to_path_buf(), err))?;
+    toml::from_str(&text).map_err(|err| CliError::ConfigFile(path.to_path_buf(), err.to_string()))
+}
+
+/// What ended up in the report, filled in as far as provisioning got
+#[derive(Default)]
+struct Report {
+    device_id: Option<u8>,
+    variant: Option<String>,
+    previous_fingerprint: Option<u32>,
+    sectors: Option<NvmReport>,
+    fingerprint: Option<u32>,
+    fields: Vec<(String, String)>,
+    error: Option<String>,
+}
+
+impl Report {
+    fn to_json(&self, config: &Path) -> String {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|t| t.as_secs())
+            .unwrap_or_default();
+        let string = |s: &str| format!("\"{}\"", escape_json(s));
+        let hex32 =
+            |value: Option<u32>| value.map_or("null".to_string(), |v| format!("\"0x{v:08X}\""));
+
+        let mut json = vec![
+            format!(
+                r#""result":"{}""#,
+                if self.error.is_none() { "pass" } else { "fail" }
+            ),
+            format!(r#""timestamp":{timestamp}"#),
+            format!(
+                r#""tool":"{} {}""#,
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            ),
+            format!(r#""config":{}"#, string(&config.display().to_string())),
+            format!(
+                r#""device_id":{}"#,
+                self.device_id
+                    .map_or("null".to_string(), |id| format!("\"0x{id:02X}\""))
+            ),
+            format!(
+                r#""variant":{}"#,
+                self.variant.as_deref().map_or("null".to_string(), string)
+            ),
+            format!(
+                r#""previous_fingerprint":{}"#,
+                hex32(self.previous_fingerprint)
+            ),
+            format!(r#""fingerprint":{}"#, hex32(self.fingerprint)),
+        ];
+        if let Some(sectors) = &self.sectors {
+            let sectors: Vec<String> = sectors.0.iter().map(|s| string(&s.to_string())).collect();
+            json.push(format!(r#""sectors":[{}]"#, sectors.join(",")));
+        }
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|(name, value)| format!("{}:{}", string(name), string(value)))
+            .collect();
+        json.push(format!(r#""fields":{{{}}}"#, fields.join(",")));
+        if let Some(error) = &self.error {
+            json.push(format!(r#""error":{}"#, string(error)));
+        }
+        format!("{{{}}}\n", json.join(","))
+    }
+}
+
+/// Program `config`, verify it and write a JSON report to `report_path`
+///
+/// The report is written on failure as well, with `"result":"fail"` and the error.
+pub fn run(
+    stusb: &mut STUSB4500<I2cdev>,
+    config: &NvmConfig,
+    config_path: &Path,
+    report_path: &Path,
+) -> Result<(), CliError> {
+    let mut report = Report::default();
+    let result = program(stusb, config, &mut report);
+    if let Err(err) = &result {
+        report.error = Some(err.to_string());
+    }
+    fs::write(report_path, report.to_json(config_path))
+        .map_err(|err| CliError::File(report_path.to_path_buf(), err))?;
+    log::info!("Wrote report to {}", report_path.display());
+    result
+}
+
+fn program(
+    stusb: &mut STUSB4500<I2cdev>,
+    config: &NvmConfig,
+    report: &mut Report,
+) -> Result<(), CliError> {
+    let variant = stusb.variant().context("Failed to read device ID")?;
+    log::info!("Detected {:?}", variant);
+    report.device_id = Some(variant.device_id());
+    report.variant = Some(format!("{:?}", variant));
+    if NvmImage::factory_default(variant).is_none() {
+        return Err(CliError::Aborted(format!(
+            "Refusing to provision {:?}, not a known STUSB4500 variant",
+            variant
+        )));
+    }
+
+    let image = config.image();
+    log::info!("Provisioning NVM data...");
+    let mut nvm = stusb.unlock_nvm().context("Failed to unlock NVM")?;
+    let previous = NvmImage(nvm.read_sectors().context("Failed to read sectors")?);
+    report.previous_fingerprint = Some(previous.fingerprint());
+    let sectors = nvm
+        .write_changed_sectors(image.0, true)
+        .context("Failed to write NVM")?;
+    report.sectors = Some(sectors);
+    let readback = NvmImage(nvm.read_sectors().context("Failed to read back NVM")?);
+    nvm.lock().context("Failed to lock NVM")?;
+
+    report.fingerprint = Some(readback.fingerprint());
+    report.fields = decode::fields(&NvmConfig::new(readback));
+    if let Some(sector) = readback
+        .0
+        .iter()
+        .zip(&image.0)
+        .position(|(read, written)| read != written)
+    {
+        return Err(CliError::Device(
+            "NVM changed after programming",
+            Error::VerifyFailed(sector as u8),
+        ));
+    }
+    log::info!("Done, fingerprint 0x{:08X}", readback.fingerprint());
+    Ok(())
+}
@@ -0,0 +1,168 @@
+//! Live dashboard
+//!
+//! Keys: `q` quit, `r` soft reset, `1`-`3` use PDO1..PDOn and renegotiate.
+
+use std::{io, time::Duration};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use linux_embedded_hal::I2cdev;
+use ratatui::{
+    layout::{Constraint, Layout},
+    widgets::{Block, Paragraph, Row, Table},
+    Frame,
+};
+use stusb4500::{pdo::Pdo, rdo::Rdo, registers::Alert, PdoChannel, STUSB4500};
+
+const REFRESH: Duration = Duration::from_millis(200);
+
+/// Everything shown on screen, read once per refresh
+struct Snapshot {
+    attached: bool,
+    voltage: u16,
+    rdo: Rdo,
+    alerts: Alert,
+    num_pdo: u8,
+    pdos: [Option<Pdo>; 3],
+}
+
+fn read(stusb: &mut STUSB4500<I2cdev>) -> Result<Snapshot, String> {
+    let err = |e| format!("{:?}", e);
+    Ok(Snapshot {
+        attached: stusb.is_attached().map_err(err)?,
+        voltage: stusb.get_voltage_mv().map_err(err)?,
+        rdo: stusb.get_current_rdo().map_err(err)?,
+        alerts: stusb.get_alerts().map_err(err)?,
+        num_pdo: stusb.get_num_pdo().map_err(err)?,
+        pdos: PdoChannel::ALL.map(|ch| stusb.get_pdo(ch).ok()),
+    })
+}
+
+fn pdo_row(index: usize, pdo: &Option<Pdo>, active: bool) -> Row<'static> {
+    let (kind, voltage, current) = match pdo {
+        Some(Pdo::Fixed(pdo)) => (
+            "Fixed",
+            format!("{:.2} V", pdo.voltage() as f32 / 20.0),
+            format!("{:.2} A", pdo.current() as f32 / 100.0),
+        ),
+        Some(Pdo::Variable(pdo)) => (
+            "Variable",
+            format!(
+                "{:.2}-{:.2} V",
+                pdo.min_voltage() as f32 / 20.0,
+                pdo.max_voltage() as f32 / 20.0
+            ),
+            format!("{:.2} A", pdo.current() as f32 / 100.0),
+        ),
+        Some(Pdo::Battery(pdo)) => (
+            "Battery",
+            format!(
+                "{:.2}-{:.2} V",
+                pdo.min_voltage() as f32 / 20.0,
+                pdo.max_voltage() as f32 / 20.0
+            ),
+            format!("{:.2} W", pdo.power() as f32 / 4.0),
+        ),
+        Some(Pdo::Augmented(pdo)) => (
+            "Augmented",
+            format!(
+                "{:.2}-{:.2} V",
+                pdo.min_voltage() as f32 / 10.0,
+                pdo.max_voltage() as f32 / 10.0
+            ),
+            format!("{:.2} A", pdo.max_current() as f32 / 20.0),
+        ),
+        None => ("Invalid", String::new(), String::new()),
+    };
+    Row::new(vec![
+        format!("PDO{}{}", index + 1, if active { "" } else { " (off)" }),
+        kind.to_string(),
+        voltage,
+        current,
+    ])
+}
+
+fn draw(frame: &mut Frame, snapshot: &Result<Snapshot, String>) {
+    let [status, pdos, help] = Layout::vertical([
+        Constraint::Length(7),
+        Constraint::Length(6),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    match snapshot {
+        Ok(s) => {
+            let text = format!(
+                "Attached: {}\nVBUS:     {:.1} V\nContract: {}\nAlerts:   {:?}",
+                if s.attached { "yes" } else { "no" },
+                s.voltage as f32 / 1000.0,
+                s.rdo,
+                s.alerts,
+            );
+            frame.render_widget(
+                Paragraph::new(text).block(Block::bordered().title("Status")),
+                status,
+            );
+
+            let rows = s
+                .pdos
+                .iter()
+                .enumerate()
+                .map(|(i, pdo)| pdo_row(i, pdo, i < s.num_pdo as usize));
+            let table = Table::new(
+                rows,
+                [
+                    Constraint::Length(12),
+                    Constraint::Length(10),
+                    Constraint::Length(16),
+                    Constraint::Length(10),
+                ],
+            )
+            .header(Row::new(vec!["", "Type", "Voltage", "Current"]))
+            .block(Block::bordered().title("Sink PDOs"));
+            frame.render_widget(table, pdos);
+        }
+        Err(e) => frame.render_widget(
+            Paragraph::new(format!("Failed to read status: {}", e))
+                .block(Block::bordered().title("Status")),
+            status,
+        ),
+    }
+
+    frame.render_widget(
+        Paragraph::new("q: quit  r: soft reset  1-3: use PDO1..n"),
+        help,
+    );
+}
+
+pub fn run(stusb: &mut STUSB4500<I2cdev>) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = (|| loop {
+        let snapshot = read(stusb);
+        terminal.draw(|frame| draw(frame, &snapshot))?;
+
+        if !event::poll(REFRESH)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('r') => {
+                let _ = stusb.soft_reset();
+            }
+            KeyCode::Char(c @ '1'..='3') => {
+                let num = c as u8 - b'0';
+                if stusb.set_num_pdo(num).is_ok() {
+                    let _ = stusb.soft_reset();
+                }
+            }
+            _ => {}
+        }
+    })();
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,147 @@
+//! Offline subcommands, run without an STUSB4500 attached
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_stusb4500-utils"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("stusb4500-utils-{}-{name}", std::process::id()))
+}
+
+#[test]
+fn generate_then_diff() {
+    let default = temp_file("default.bin");
+    let changed = temp_file("changed.bin");
+    let default_str = default.to_str().unwrap();
+    let changed_str = changed.to_str().unwrap();
+
+    assert!(run(&["generate", "-o", default_str]).status.success());
+    assert_eq!(std::fs::read(&default).unwrap().len(), 40);
+    assert!(run(&["generate", "-o", changed_str, "--pdo2", "12V/2A"])
+        .status
+        .success());
+
+    let output = run(&["diff", default_str, changed_str]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("PDO2 voltage"));
+    assert!(stdout.contains("12.00 V"));
+
+    std::fs::remove_file(default).unwrap();
+    std::fs::remove_file(changed).unwrap();
+}
+
+#[test]
+fn missing_file_fails() {
+    let missing = temp_file("missing.bin");
+    let output = run(&["diff", missing.to_str().unwrap(), missing.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("missing.bin"));
+}
+
+#[test]
+fn short_file_fails() {
+    let short = temp_file("short.bin");
+    std::fs::write(&short, [0; 8]).unwrap();
+    let output = run(&[
+        "generate",
+        "-o",
+        short.to_str().unwrap(),
+        "--base",
+        short.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("40 bytes"));
+    std::fs::remove_file(short).unwrap();
+}
+
+#[test]
+fn out_of_range_config_fails() {
+    let output_file = temp_file("range.bin");
+    let output = run(&[
+        "generate",
+        "-o",
+        output_file.to_str().unwrap(),
+        "--pdo2",
+        "30V/3A",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Invalid configuration"));
+    assert!(!output_file.exists());
+}
+
+#[test]
+fn json_errors() {
+    let missing = temp_file("missing.json.bin");
+    let output = run(&[
+        "--errors",
+        "json",
+        "diff",
+        missing.to_str().unwrap(),
+        missing.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with(r#"{"error":"failure","code":1,"message":""#));
+    assert!(stderr.contains("missing.json.bin"));
+    assert_eq!(stderr.lines().count(), 1);
+}
+
+#[test]
+fn missing_bus_exit_code() {
+    let output = run(&["--bus", "i2c-does-not-exist", "status"]);
+    assert_eq!(output.status.code(), Some(4));
+}
+
+#[test]
+fn provision_rejects_bad_config_before_bus() {
+    let config = temp_file("bad.toml");
+    let report = temp_file("bad.json");
+    std::fs::write(&config, "V_SNK_PDO2 = \"fast\"\n").unwrap();
+    let output = run(&[
+        "--bus",
+        "i2c-does-not-exist",
+        "provision",
+        "--config",
+        config.to_str().unwrap(),
+        "--report",
+        report.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Invalid configuration in"));
+    assert!(!report.exists());
+    std::fs::remove_file(config).unwrap();
+}
+
+#[test]
+fn provision_loads_config() {
+    let config = temp_file("good.toml");
+    let report = temp_file("good.json");
+    std::fs::write(&config, "DPM_SNK_PDO_NUMB = 2\nV_SNK_PDO2 = 12000\n").unwrap();
+    let output = run(&[
+        "--bus",
+        "i2c-does-not-exist",
+        "provision",
+        "--config",
+        config.to_str().unwrap(),
+        "--report",
+        report.to_str().unwrap(),
+    ]);
+    // Got as far as opening the bus
+    assert_eq!(output.status.code(), Some(4));
+    std::fs::remove_file(config).unwrap();
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "std")]
+
+use proptest::prelude::*;
+use stusb4500::{
+    config::{GpioCfg, NvmConfig, PowerOk},
+    gui_conf::GuiConfError,
+    image::NvmImage,
+    PdoChannel,
+};
+
+const PARAMS: &str = include_str!("fixtures/gui_params.txt");
+
+#[test]
+fn import_parameters() {
+    let config = NvmConfig::from_gui_conf(PARAMS).unwrap();
+    assert_eq!(config.vendor_data(), 0x1234);
+    assert_eq!(config.gpio_cfg(), GpioCfg::SinkPower);
+    assert_eq!(config.discharge_time_to_0v_ms(), 756);
+    assert_eq!(config.discharge_time_transition_ms(), 288);
+    assert_eq!(config.pdo_count(), 3);
+    assert!(config.usb_comm_capable());
+    assert_eq!(config.pdo_current_ma(PdoChannel::PDO1), Some(1500));
+    assert_eq!(config.pdo_voltage_mv(PdoChannel::PDO2), 9000);
+    assert_eq!(config.pdo_current_ma(PdoChannel::PDO2), Some(3000));
+    assert_eq!(config.pdo_voltage_mv(PdoChannel::PDO3), 15000);
+    assert_eq!(config.pdo_current_ma(PdoChannel::PDO3), None);
+    assert_eq!(config.pdo_effective_current_ma(PdoChannel::PDO3), 2000);
+    assert_eq!(config.vbus_high_shift_percent(PdoChannel::PDO1), 15);
+    assert_eq!(config.vbus_low_shift_percent(PdoChannel::PDO3), Some(15));
+    assert_eq!(config.power_ok(), Some(PowerOk::Config2));
+    assert!(config.power_only_above_5v());
+    assert!(config.is_recognized());
+    // Bits outside the named fields come from the factory image
+    assert_eq!(config.undocumented(), NvmConfig::DEFAULT.undocumented());
+}
+
+#[test]
+fn import_rejects_bad_parameters() {
+    assert_eq!(
+        NvmConfig::from_gui_conf("V_SNK_PDO4 = 100"),
+        Err(GuiConfError::UnknownParameter(1))
+    );
+    assert_eq!(
+        NvmConfig::from_gui_conf("[Parameters]\nGPIO_CFG = 4"),
+        Err(GuiConfError::OutOfRange(2))
+    );
+    assert_eq!(
+        NvmConfig::from_gui_conf("0xC0 = 0x00"),
+        Err(GuiConfError::Incomplete)
+    );
+}
+
+proptest! {
+    #[test]
+    fn gui_conf_roundtrip(sectors in any::<[[u8; 8]; 5]>()) {
+        let config = NvmConfig::new(NvmImage(sectors));
+        prop_assert_eq!(NvmConfig::from_gui_conf(&config.to_gui_conf()), Ok(config));
+    }
+}
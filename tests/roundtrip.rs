@@ -0,0 +1,408 @@
+use proptest::prelude::*;
+use stusb4500::{
+    config::{ConfigError, Decoded, GpioCfg, NvmConfig},
+    image::NvmImage,
+    pdo::{validate_pdo_set, AugmentedPdo, BatteryPdo, FixedPdo, Pdo, PdoSetError, VariablePdo},
+    rdo::{Rdo, RdoFlags},
+    PdoChannel,
+};
+
+// Sink capabilities can be built in const context
+static SINK_PDOS: [Pdo; 2] = [Pdo::new_fixed(100, 300), Pdo::new_fixed(240, 150)];
+
+fn image() -> impl Strategy<Value = NvmImage> {
+    any::<[[u8; 8]; 5]>().prop_map(NvmImage)
+}
+
+proptest! {
+    #[test]
+    fn pdo_bits_roundtrip(bits in any::<u32>()) {
+        match Pdo::try_from(bits) {
+            Ok(pdo) => prop_assert_eq!(u32::from(pdo), bits),
+            // Reserved APDO types
+            Err(_) => prop_assert!(bits >> 30 == 0x3 && (bits >> 28) & 0x3 != 0),
+        }
+    }
+
+    #[test]
+    fn pdo_from_bits_decodes_everything(bits in any::<u32>()) {
+        let pdo = Pdo::from_bits(bits).unwrap();
+        prop_assert_eq!(pdo.bits(), bits);
+    }
+
+    #[test]
+    fn const_pdo_constructors_match_setters(a in any::<u16>(), b in any::<u16>(), c in any::<u16>()) {
+        let fixed = FixedPdo::from_raw_units(a, b);
+        prop_assert_eq!(fixed.voltage(), a as u32 & 0x3ff);
+        prop_assert_eq!(fixed.current(), b as u32 & 0x3ff);
+        prop_assert_eq!(fixed.fixed(), 0);
+
+        let variable = VariablePdo::new(a, b, c);
+        prop_assert_eq!(variable.min_voltage(), (a as u32 / 50) & 0x3ff);
+        prop_assert_eq!(variable.max_voltage(), (b as u32 / 50) & 0x3ff);
+        prop_assert_eq!(variable.current(), (c as u32 / 10) & 0x3ff);
+        prop_assert_eq!(Pdo::try_from(variable.0), Ok(Pdo::Variable(variable)));
+
+        let battery = BatteryPdo::new(a, b, c as u32 * 16);
+        prop_assert_eq!(battery.min_voltage(), (a as u32 / 50) & 0x3ff);
+        prop_assert_eq!(battery.max_voltage(), (b as u32 / 50) & 0x3ff);
+        prop_assert_eq!(battery.power(), (c as u32 * 16 / 250) & 0x3ff);
+        prop_assert_eq!(Pdo::try_from(battery.0), Ok(Pdo::Battery(battery)));
+    }
+
+    #[test]
+    fn pdo_unit_setters_match_constructors(a in any::<u16>(), b in any::<u16>(), c in any::<u16>()) {
+        let mut variable = Pdo::Variable(VariablePdo::default());
+        variable.min_voltage_mv(a).max_voltage_mv(b).current_ma(c).power_mw(1000);
+        prop_assert_eq!(variable, Pdo::Variable(VariablePdo::new(a, b, c)));
+
+        let mut battery = Pdo::Battery(BatteryPdo::default());
+        battery.min_voltage_mv(a).max_voltage_mv(b).power_mw(c as u32 * 16).current_ma(1000);
+        prop_assert_eq!(battery, Pdo::Battery(BatteryPdo::new(a, b, c as u32 * 16)));
+
+        let mut fixed = Pdo::new_fixed(100, 0);
+        fixed.current_ma(c).min_voltage_mv(a).max_voltage_mv(b);
+        prop_assert_eq!(fixed, Pdo::new_fixed(100, ((c as u32 / 10) & 0x3ff) as u16));
+    }
+
+    #[test]
+    fn augmented_pdo_fields_are_disjoint(
+        max_voltage in 0u32..0x100,
+        min_voltage in 0u32..0x100,
+        max_current in 0u32..0x80,
+    ) {
+        let mut pdo = AugmentedPdo::default();
+        pdo.set_max_voltage(max_voltage);
+        pdo.set_min_voltage(min_voltage);
+        pdo.set_max_current(max_current);
+        prop_assert_eq!(pdo.max_voltage(), max_voltage);
+        prop_assert_eq!(pdo.min_voltage(), min_voltage);
+        prop_assert_eq!(pdo.max_current(), max_current);
+        prop_assert_eq!(pdo.battery(), 0x3);
+    }
+
+    #[test]
+    fn rdo_fixed_request_fields(
+        position in 1u8..=7,
+        current in 0u16..10240,
+        max_current in 0u16..10240,
+        flags in any::<u32>().prop_map(RdoFlags::from_bits_truncate),
+    ) {
+        let rdo = Rdo::fixed_request(position, current, max_current, flags).unwrap();
+        prop_assert_eq!(rdo.position(), position as u32);
+        prop_assert_eq!(rdo.operating_current_ma(), current as u32 / 10 * 10);
+        prop_assert_eq!(rdo.max_operating_current_ma(), max_current as u32 / 10 * 10);
+        prop_assert_eq!(rdo.flags(), flags);
+        prop_assert_eq!(Rdo::from_bits(rdo.bits()), rdo);
+    }
+
+    #[test]
+    fn nvm_bytes_roundtrip(image in image()) {
+        let bytes = image.to_bytes();
+        prop_assert_eq!(&bytes[8..16], &image.0[1]);
+        prop_assert_eq!(NvmImage::from_bytes(&bytes), image);
+    }
+
+    #[test]
+    fn vbus_monitoring_auto_is_consistent(pdo2 in 5000u16..=20000, pdo3 in 5000u16..=20000) {
+        let mut config = NvmConfig::DEFAULT;
+        config.set_pdo_voltage_mv(PdoChannel::PDO2, pdo2).unwrap();
+        config.set_pdo_voltage_mv(PdoChannel::PDO3, pdo3).unwrap();
+        config.set_pdo_count(3).unwrap();
+        prop_assert_eq!(config.set_vbus_monitoring_auto(), Ok(()));
+        prop_assert_eq!(config.check_vbus_monitoring(), Ok(()));
+    }
+
+    #[test]
+    fn nvm_config_roundtrip(image in image()) {
+        prop_assert_eq!(NvmConfig::DEFAULT.image(), NvmImage::default());
+        prop_assert_eq!(SINK_PDOS[1], Pdo::Fixed(FixedPdo::from_raw_units(240, 150)));
+        prop_assert_eq!(NvmConfig::new(image).image(), image);
+    }
+
+    #[test]
+    fn nvm_config_fields_reencode(image in image()) {
+        let decoded = NvmConfig::new(image);
+        let mut config = decoded;
+
+        if let Some(cfg) = decoded.power_ok() {
+            config.set_power_ok(cfg);
+        }
+        if (1..=3).contains(&decoded.pdo_count()) {
+            config.set_pdo_count(decoded.pdo_count()).unwrap();
+        }
+        for ch in PdoChannel::iter() {
+            if ch != PdoChannel::PDO1 && (5000..=20000).contains(&decoded.pdo_voltage_mv(ch)) {
+                config.set_pdo_voltage_mv(ch, decoded.pdo_voltage_mv(ch)).unwrap();
+            }
+            match decoded.pdo_current_ma(ch) {
+                Some(current) => config.set_pdo_current_ma(ch, current).unwrap(),
+                None => config.set_pdo_flex_current(ch),
+            }
+        }
+        if decoded.flex_current_ma() <= 5000 {
+            config.set_flex_current_ma(decoded.flex_current_ma()).unwrap();
+        }
+        config.set_discharge_time_to_0v_ms(decoded.discharge_time_to_0v_ms()).unwrap();
+        config
+            .set_discharge_time_transition_ms(decoded.discharge_time_transition_ms())
+            .unwrap();
+        config.set_unconstrained_power(decoded.unconstrained_power());
+        config.set_usb_comm_capable(decoded.usb_comm_capable());
+        config.set_power_only_above_5v(decoded.power_only_above_5v());
+        config.set_req_src_current(decoded.req_src_current());
+
+        prop_assert_eq!(config.image(), image);
+    }
+
+    #[test]
+    fn nvm_undocumented_bits_survive_setters(image in image(), pdo2 in 5000u16..=20000) {
+        let decoded = NvmConfig::new(image);
+        let mut config = decoded;
+        config.set_pdo_count(3).unwrap();
+        config.set_pdo_voltage_mv(PdoChannel::PDO2, pdo2).unwrap();
+        config.set_flex_current_ma(1230).unwrap();
+        config.set_gpio_cfg(GpioCfg::SinkPower);
+        config.set_vendor_data(0xBEEF);
+        prop_assert_eq!(config.undocumented(), decoded.undocumented());
+
+        // Documented and undocumented bits don't overlap
+        let cleared = NvmConfig::new(decoded.undocumented());
+        prop_assert!(cleared.fields().all(|field| field.raw == 0));
+    }
+
+    #[test]
+    fn nvm_five_volt_only_keeps_pdos(image in image()) {
+        let decoded = NvmConfig::new(image);
+        let mut config = decoded;
+        config.set_five_volt_only();
+        prop_assert!(config.five_volt_only());
+        prop_assert_eq!(config.pdo_count(), 1);
+        prop_assert!(!config.power_only_above_5v());
+        for pdo in [PdoChannel::PDO2, PdoChannel::PDO3] {
+            prop_assert_eq!(config.pdo_voltage_mv(pdo), decoded.pdo_voltage_mv(pdo));
+        }
+        prop_assert_eq!(config.undocumented(), decoded.undocumented());
+    }
+
+    #[test]
+    fn nvm_voltage_quantization(voltage in 5000u16..=20000) {
+        let mut config = NvmConfig::default();
+        config.set_pdo_voltage_mv(PdoChannel::PDO2, voltage).unwrap();
+        let stored = config.pdo_voltage_mv(PdoChannel::PDO2);
+        prop_assert!(stored <= voltage && voltage - stored < 50);
+    }
+
+    #[test]
+    fn nvm_current_quantization(current in 500u16..=5000) {
+        let mut config = NvmConfig::default();
+        config.set_pdo_current_ma(PdoChannel::PDO3, current).unwrap();
+        let stored = config.pdo_current_ma(PdoChannel::PDO3).unwrap();
+        let step = if current < 3000 { 250 } else { 500 };
+        prop_assert!(stored <= current && current - stored < step);
+    }
+
+    #[test]
+    fn nvm_flex_current_quantization(current in 0u16..=5000) {
+        let mut config = NvmConfig::default();
+        config.set_flex_current_ma(current).unwrap();
+        let stored = config.flex_current_ma();
+        prop_assert!(stored <= current && current - stored < 10);
+    }
+}
+
+#[test]
+fn offline_config_generation() {
+    // Build, encode, compare and decode an image without any bus type in scope
+    let image = NvmConfig::builder()
+        .pdo2(12_000, 2_000)
+        .pdo_count(2)
+        .build()
+        .unwrap()
+        .image();
+
+    let changed: Vec<_> = NvmImage::DEFAULT.diff(&image).collect();
+    assert!(!changed.is_empty());
+    assert!(changed.iter().all(|diff| diff.old != diff.new));
+
+    let decoded = NvmConfig::new(NvmImage::from_bytes(&image.to_bytes()));
+    assert_eq!(decoded.pdo_count(), 2);
+    assert_eq!(decoded.pdo_voltage_mv(PdoChannel::PDO2), 12_000);
+    assert_eq!(decoded.pdo_current_ma(PdoChannel::PDO2), Some(2_000));
+}
+
+#[test]
+fn pdo_formatting_uses_real_units() {
+    assert_eq!(SINK_PDOS[1].to_string(), "Fixed 12.00V 1.50A");
+    let battery = Pdo::Battery(BatteryPdo::new(5000, 20000, 45000));
+    assert_eq!(battery.to_string(), "Battery 5.00-20.00V 45.00W");
+    assert_eq!(
+        format!("{:?}", battery),
+        "Battery { min_voltage_mv: 5000, max_voltage_mv: 20000, power_mw: 45000 }"
+    );
+}
+
+#[cfg(feature = "ufmt")]
+#[test]
+fn pdo_ufmt_matches_core_fmt() {
+    let pdos = [
+        Pdo::new_fixed(180, 150),
+        Pdo::try_new_battery(100, 400, 180).unwrap(),
+    ];
+    for pdo in pdos {
+        let mut s = String::new();
+        ufmt::uwrite!(s, "{}", pdo).unwrap();
+        assert_eq!(s, pdo.to_string());
+        s.clear();
+        ufmt::uwrite!(s, "{:?}", pdo).unwrap();
+        assert_eq!(s, format!("{:?}", pdo));
+    }
+}
+
+#[test]
+fn register_addresses_roundtrip() {
+    use stusb4500::registers::{InvalidRegister, Register};
+
+    for pair in Register::ALL.windows(2) {
+        assert!(pair[0].address() + pair[0].size() as u8 <= pair[1].address());
+    }
+    for reg in Register::ALL {
+        assert_eq!(Register::try_from(reg.address()), Ok(reg));
+    }
+    assert_eq!(Register::try_from(0x34), Err(InvalidRegister(0x34)));
+    assert_eq!(Register::AlertStatus1.to_string(), "ALERT_STATUS_1");
+}
+
+#[test]
+fn gui_image_fields() {
+    let mut image = NvmImage::DEFAULT;
+    // Reserved POWER_OK setting and a bit outside any field
+    image.0[4][4] = (image.0[4][4] & !0x60) | 0x20;
+    image.0[0][7] |= 0x01;
+    let config = NvmConfig::new(image);
+    assert!(NvmConfig::DEFAULT.is_recognized());
+    assert!(!config.is_recognized());
+
+    let power_ok = config
+        .fields()
+        .find(|field| field.name == "POWER_OK_CFG")
+        .unwrap();
+    assert_eq!(power_ok.raw, 1);
+    assert_eq!(power_ok.decoded, None);
+    let pdo3 = config
+        .fields()
+        .find(|field| field.name == "V_SNK_PDO3")
+        .unwrap();
+    assert_eq!(pdo3.raw, 400);
+    assert_eq!(pdo3.decoded, Some(Decoded::Millivolts(20000)));
+
+    assert_eq!(config.undocumented().0[0][7], 0x01);
+    assert_eq!(config.image(), image);
+}
+
+#[test]
+fn factory_vbus_monitoring() {
+    let config = NvmConfig::DEFAULT;
+    assert_eq!(config.vbus_high_shift_percent(PdoChannel::PDO1), 15);
+    assert_eq!(config.vbus_low_shift_percent(PdoChannel::PDO1), None);
+    assert_eq!(
+        config.vbus_thresholds_mv(PdoChannel::PDO2),
+        (Some(12000), 16500)
+    );
+    assert_eq!(
+        config.vbus_thresholds_mv(PdoChannel::PDO3),
+        (Some(16000), 22000)
+    );
+    // Overlapping windows are fine, only the window of the contract is monitored
+    assert_eq!(config.check_vbus_monitoring(), Ok(()));
+
+    // Close PDOs get the minimal 5% window on both sides
+    let config = NvmConfig::builder()
+        .pdo2(9000, 3000)
+        .pdo3(9500, 3000)
+        .vbus_monitoring_auto()
+        .build()
+        .unwrap();
+    assert_eq!(config.vbus_high_shift_percent(PdoChannel::PDO2), 5);
+    assert_eq!(config.vbus_low_shift_percent(PdoChannel::PDO3), Some(5));
+
+    // A zero voltage PDO can't be monitored
+    let mut image = NvmConfig::DEFAULT.image();
+    image.0[4][2] = 0;
+    image.0[4][3] &= !0x03;
+    let config = NvmConfig::new(image);
+    assert_eq!(
+        config.check_vbus_monitoring(),
+        Err(ConfigError::MonitoringWindow(PdoChannel::PDO3))
+    );
+}
+
+#[test]
+fn pdo_set_valid() {
+    assert_eq!(validate_pdo_set(&SINK_PDOS), Ok(()));
+    assert_eq!(
+        validate_pdo_set(&[
+            Pdo::new_fixed(100, 300),
+            Pdo::new_fixed(180, 300),
+            Pdo::new_fixed(400, 500),
+        ]),
+        Ok(())
+    );
+}
+
+#[test]
+fn pdo_set_empty() {
+    assert_eq!(validate_pdo_set(&[]), Err(PdoSetError::Empty));
+}
+
+#[test]
+fn pdo_set_too_many() {
+    let pdos = [
+        Pdo::new_fixed(100, 300),
+        Pdo::new_fixed(180, 300),
+        Pdo::new_fixed(300, 300),
+        Pdo::new_fixed(400, 300),
+    ];
+    assert_eq!(validate_pdo_set(&pdos), Err(PdoSetError::TooMany));
+}
+
+#[test]
+fn pdo_set_first_not_5v() {
+    let pdos = [Pdo::new_fixed(180, 300)];
+    assert_eq!(validate_pdo_set(&pdos), Err(PdoSetError::FirstNot5V));
+    let pdos = [Pdo::Variable(VariablePdo::new(100, 100, 300))];
+    assert_eq!(validate_pdo_set(&pdos), Err(PdoSetError::FirstNot5V));
+}
+
+#[test]
+fn pdo_set_not_fixed() {
+    let pdos = [
+        Pdo::new_fixed(100, 300),
+        Pdo::Variable(VariablePdo::new(100, 400, 300)),
+    ];
+    assert_eq!(validate_pdo_set(&pdos), Err(PdoSetError::NotFixed(1)));
+}
+
+#[test]
+fn pdo_set_not_ascending() {
+    let pdos = [
+        Pdo::new_fixed(100, 300),
+        Pdo::new_fixed(300, 300),
+        Pdo::new_fixed(180, 300),
+    ];
+    assert_eq!(validate_pdo_set(&pdos), Err(PdoSetError::NotAscending(2)));
+    let pdos = [Pdo::new_fixed(100, 300), Pdo::new_fixed(100, 150)];
+    assert_eq!(validate_pdo_set(&pdos), Err(PdoSetError::NotAscending(1)));
+}
+
+#[test]
+fn pdo_set_voltage_too_high() {
+    let pdos = [Pdo::new_fixed(100, 300), Pdo::new_fixed(401, 300)];
+    assert_eq!(validate_pdo_set(&pdos), Err(PdoSetError::VoltageTooHigh(1)));
+}
+
+#[test]
+fn pdo_set_current_too_high() {
+    let pdos = [Pdo::new_fixed(100, 300), Pdo::new_fixed(400, 501)];
+    assert_eq!(validate_pdo_set(&pdos), Err(PdoSetError::CurrentTooHigh(1)));
+}
@@ -0,0 +1,44 @@
+//! Compile-time checks that the driver can be moved to and shared between tasks whenever the
+//! bus can
+
+use stusb4500::{
+    config::NvmConfig, nvm::NvmImage, nvm::STUSB4500Nvm, Error, Stusb4500Builder, Stusb4500Group,
+    STUSB4500,
+};
+
+fn assert_send<T: Send>() {}
+fn assert_sync<T: Sync>() {}
+
+#[allow(dead_code)]
+fn driver_is_send<'a, I2C: Send + 'a>() {
+    assert_send::<STUSB4500<I2C>>();
+    assert_send::<STUSB4500Nvm<'a, I2C>>();
+    assert_send::<Stusb4500Group<I2C, 2>>();
+}
+
+#[allow(dead_code)]
+fn driver_is_sync<'a, I2C: Sync + 'a>() {
+    assert_sync::<STUSB4500<I2C>>();
+    assert_sync::<STUSB4500Nvm<'a, I2C>>();
+    assert_sync::<Stusb4500Group<I2C, 2>>();
+}
+
+#[allow(dead_code)]
+fn built_driver_is_send<I2C: Send, D: Send, P: Send>() {
+    assert_send::<STUSB4500<I2C, D, P>>();
+    assert_send::<Stusb4500Builder<I2C, D, P>>();
+}
+
+#[allow(dead_code)]
+fn error_is_send_sync<E: Send + Sync>() {
+    assert_send::<Error<E>>();
+    assert_sync::<Error<E>>();
+}
+
+#[test]
+fn data_types_are_send_sync() {
+    assert_send::<NvmImage>();
+    assert_sync::<NvmImage>();
+    assert_send::<NvmConfig>();
+    assert_sync::<NvmConfig>();
+}
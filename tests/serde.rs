@@ -0,0 +1,40 @@
+#![cfg(feature = "serde")]
+
+use proptest::prelude::*;
+use stusb4500::{config::NvmConfig, image::NvmImage, PdoChannel};
+
+proptest! {
+    #[test]
+    fn nvm_config_json_roundtrip(sectors in any::<[[u8; 8]; 5]>()) {
+        let config = NvmConfig::new(NvmImage(sectors));
+        let json = serde_json::to_string(&config).unwrap();
+        prop_assert_eq!(serde_json::from_str::<NvmConfig>(&json).unwrap(), config);
+    }
+}
+
+#[test]
+fn nvm_config_json_fields_override_image() {
+    let json = r#"{ "DPM_SNK_PDO_NUMB": 2, "V_SNK_PDO2": 9000, "I_SNK_PDO2": 0 }"#;
+    let config: NvmConfig = serde_json::from_str(json).unwrap();
+
+    assert_eq!(config.pdo_count(), 2);
+    assert_eq!(config.pdo_voltage_mv(PdoChannel::PDO2), 9000);
+    assert_eq!(config.pdo_current_ma(PdoChannel::PDO2), None);
+    // Missing fields come from the factory image
+    let default = NvmConfig::default();
+    assert_eq!(
+        config.pdo_voltage_mv(PdoChannel::PDO3),
+        default.pdo_voltage_mv(PdoChannel::PDO3)
+    );
+}
+
+#[cfg(feature = "postcard")]
+proptest! {
+    #[test]
+    fn nvm_config_postcard_roundtrip(sectors in any::<[[u8; 8]; 5]>()) {
+        let config = NvmConfig::new(NvmImage(sectors));
+        let mut buf = [0; 128];
+        let bytes = config.to_postcard(&mut buf).unwrap();
+        prop_assert_eq!(NvmConfig::from_postcard(bytes).unwrap(), config);
+    }
+}
@@ -0,0 +1,190 @@
+//! Timeout handling against a simulated clock
+//!
+//! The driver measures time by the delays it requests, so a [`DelayNs`] that advances a shared
+//! clock instead of sleeping lets the bus model slow hardware. Nothing here actually sleeps.
+
+use core::cell::Cell;
+use core::convert::Infallible;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+use std::rc::Rc;
+use stusb4500::nvm::{NVM_POLL_INTERVAL_US, NVM_POLL_LIMIT};
+use stusb4500::registers::Register;
+use stusb4500::{Address, Error, PollPolicy, STUSB4500};
+
+/// Simulated time in ns
+type Clock = Rc<Cell<u64>>;
+
+/// Advances the clock by the requested time
+struct SimDelay(Clock);
+
+impl DelayNs for SimDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.0.set(self.0.get() + u64::from(ns));
+    }
+}
+
+const REQUEST: u8 = 0x10;
+
+/// Register file where an NVM request completes `nvm_latency_ns` after it was started
+struct SlowNvmBus {
+    regs: [u8; 256],
+    pointer: usize,
+    clock: Clock,
+    nvm_latency_ns: u64,
+    request_done: u64,
+}
+
+impl SlowNvmBus {
+    fn new(clock: Clock, nvm_latency_ns: u64) -> Self {
+        SlowNvmBus {
+            regs: [0; 256],
+            pointer: 0,
+            clock,
+            nvm_latency_ns,
+            request_done: 0,
+        }
+    }
+}
+
+impl ErrorType for SlowNvmBus {
+    type Error = Infallible;
+}
+
+impl I2c for SlowNvmBus {
+    fn transaction(
+        &mut self,
+        _address: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let now = self.clock.get();
+        if now >= self.request_done {
+            self.regs[Register::NvmCtrl0 as usize] &= !REQUEST;
+        }
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => {
+                    if let Some((register, data)) = bytes.split_first() {
+                        self.pointer = *register as usize;
+                        for byte in data {
+                            if self.pointer == Register::NvmCtrl0 as usize && byte & REQUEST != 0 {
+                                self.request_done = now + self.nvm_latency_ns;
+                            }
+                            self.regs[self.pointer] = *byte;
+                            self.pointer += 1;
+                        }
+                    }
+                }
+                Operation::Read(buf) => {
+                    for byte in buf.iter_mut() {
+                        *byte = self.regs[self.pointer];
+                        self.pointer += 1;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+const NVM_TIMEOUT_NS: u64 = NVM_POLL_LIMIT as u64 * NVM_POLL_INTERVAL_US as u64 * 1_000;
+
+fn slow_nvm(nvm_latency_ns: u64) -> (Clock, SlowNvmBus) {
+    let clock = Clock::default();
+    let bus = SlowNvmBus::new(clock.clone(), nvm_latency_ns);
+    (clock, bus)
+}
+
+#[test]
+fn slow_nvm_request_completes() {
+    let (clock, mut bus) = slow_nvm(NVM_TIMEOUT_NS / 2);
+    let mut stusb = STUSB4500::builder(&mut bus)
+        .delay(SimDelay(clock.clone()))
+        .build();
+
+    let mut nvm = stusb.unlock_nvm().unwrap();
+    assert!(nvm.read_sector(0).is_ok());
+    let waited = clock.get();
+    assert!((NVM_TIMEOUT_NS / 2..NVM_TIMEOUT_NS).contains(&waited));
+}
+
+#[test]
+fn stuck_nvm_request_times_out() {
+    let (clock, mut bus) = slow_nvm(2 * NVM_TIMEOUT_NS);
+    let mut stusb = STUSB4500::builder(&mut bus)
+        .delay(SimDelay(clock.clone()))
+        .build();
+
+    let mut nvm = stusb.unlock_nvm().unwrap();
+    assert!(matches!(nvm.read_sector(0), Err(Error::NvmTimeout)));
+    // Gave up after polling for the documented time, not before and not much later
+    assert_eq!(clock.get(), NVM_TIMEOUT_NS);
+}
+
+#[test]
+fn wait_for_voltage_deadline() {
+    // VBUS never comes up
+    let (clock, mut bus) = slow_nvm(0);
+    let mut stusb = STUSB4500::builder(&mut bus)
+        .delay(SimDelay(clock.clone()))
+        .build();
+
+    assert!(matches!(
+        stusb.wait_for_voltage(9000, 500, PollPolicy::timeout(250)),
+        Err(Error::Timeout)
+    ));
+    assert_eq!(clock.get(), 250_000_000);
+}
+
+#[test]
+fn wait_for_contract_deadline() {
+    let (clock, mut bus) = slow_nvm(0);
+    let mut delay = SimDelay(clock.clone());
+    let mut stusb = STUSB4500::new(&mut bus, Address::Default);
+
+    assert!(matches!(
+        stusb.wait_for_contract(&mut delay, PollPolicy::timeout(100)),
+        Err(Error::Timeout)
+    ));
+    assert_eq!(clock.get(), 100_000_000);
+}
+
+#[test]
+fn poll_interval_keeps_deadline() {
+    let (clock, mut bus) = slow_nvm(0);
+    let mut delay = SimDelay(clock.clone());
+    let mut stusb = STUSB4500::new(&mut bus, Address::Default);
+
+    // 40ms doesn't divide 100ms, the last wait is shortened
+    assert!(matches!(
+        stusb.wait_for_attach(&mut delay, PollPolicy::new(40, 100), 0),
+        Err(Error::Timeout)
+    ));
+    assert_eq!(clock.get(), 100_000_000);
+}
+
+#[test]
+fn nvm_timeout_relocks() {
+    let (clock, mut bus) = slow_nvm(2 * NVM_TIMEOUT_NS);
+    let mut stusb = STUSB4500::builder(&mut bus)
+        .delay(SimDelay(clock.clone()))
+        .build();
+
+    assert!(matches!(stusb.nvm_fingerprint(), Err(Error::NvmTimeout)));
+    // The session was locked again, status access keeps working
+    assert!(stusb.get_voltage_mv().is_ok());
+}
+
+#[test]
+fn failed_boot_write_relocks() {
+    let (clock, mut bus) = slow_nvm(2 * NVM_TIMEOUT_NS);
+    let mut stusb = STUSB4500::builder(&mut bus)
+        .delay(SimDelay(clock.clone()))
+        .build();
+
+    assert!(matches!(
+        stusb.write_sectors_if_changed([[0xFF; 8]; 5]),
+        Err(Error::NvmTimeout)
+    ));
+    assert!(stusb.get_voltage_mv().is_ok());
+}
@@ -0,0 +1,994 @@
+//! I2C transaction budget of common flows
+//!
+//! Each I2C transaction is one START..STOP sequence on the bus. A register write is a single
+//! transaction, a register read is two (address write, then read). At 100 kHz a 4 byte register
+//! read takes around 0.8 ms, so the counts below also bound how long each flow occupies a shared
+//! bus. A change that makes a flow chattier has to update its budget here.
+
+use core::convert::Infallible;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+use stusb4500::negotiate::RetryPolicy;
+use stusb4500::nvm::{NvmReport, DEFAULT_NVM_DATA};
+use stusb4500::registers::Register;
+use stusb4500::{Address, Error, PdoChannel, PollPolicy, STUSB4500};
+
+/// Register file with auto-incrementing reads, NVM requests complete immediately
+struct CountingBus {
+    regs: [u8; 256],
+    pointer: usize,
+    transactions: usize,
+    /// Ignore writes to NVM_CTRL_0, like a protected NVM
+    nvm_locked: bool,
+    /// Brown out after this many transactions, coming back with the NVM locked
+    reset_after: Option<usize>,
+    /// NVM requests never complete
+    nvm_stuck: bool,
+}
+
+impl CountingBus {
+    fn new() -> Self {
+        let mut regs = [0; 256];
+        // 5V 3A contract on PDO1
+        regs[Register::PEFSM as usize] = 0x18;
+        regs[Register::MonitoringCtrl1 as usize] = 50;
+        regs[Register::DPMPDONumb as usize] = 1;
+        let pdo1 = Register::DPMSNKPDO1 as usize;
+        regs[pdo1..pdo1 + 4].copy_from_slice(&(100u32 << 10 | 300).to_le_bytes());
+        let rdo = Register::RDORegStatus as usize;
+        regs[rdo..rdo + 4].copy_from_slice(&(1u32 << 28 | 300 << 10 | 300).to_le_bytes());
+        CountingBus {
+            regs,
+            pointer: 0,
+            transactions: 0,
+            nvm_locked: false,
+            reset_after: None,
+            nvm_stuck: false,
+        }
+    }
+}
+
+impl ErrorType for CountingBus {
+    type Error = Infallible;
+}
+
+impl I2c for CountingBus {
+    fn transaction(
+        &mut self,
+        _address: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        if self.reset_after == Some(self.transactions) {
+            self.regs[Register::NvmCtrl0 as usize] = 0;
+            self.nvm_locked = true;
+        }
+        self.transactions += 1;
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => {
+                    if let Some((register, data)) = bytes.split_first() {
+                        self.pointer = *register as usize;
+                        for byte in data {
+                            if !(self.nvm_locked && self.pointer == Register::NvmCtrl0 as usize) {
+                                self.regs[self.pointer] = *byte;
+                            }
+                            self.pointer += 1;
+                        }
+                    }
+                    // Requests complete right away
+                    if !self.nvm_stuck {
+                        self.regs[Register::NvmCtrl0 as usize] &= !0x10;
+                    }
+                }
+                Operation::Read(buf) => {
+                    for byte in buf.iter_mut() {
+                        *byte = self.regs[self.pointer];
+                        self.pointer += 1;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct NoDelay;
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+fn transactions<F>(flow: F) -> usize
+where
+    F: FnOnce(&mut STUSB4500<&mut CountingBus>),
+{
+    let mut bus = CountingBus::new();
+    flow(&mut STUSB4500::new(&mut bus, Address::Default));
+    bus.transactions
+}
+
+#[test]
+fn full_nvm_write_budget() {
+    let count = transactions(|dev| {
+        let mut nvm = dev.unlock_nvm().unwrap();
+        nvm.write_sectors(DEFAULT_NVM_DATA).unwrap();
+        nvm.lock().unwrap();
+    });
+    // Unlock 5, erase 8, 9 per sector, lock 3
+    assert_eq!(count, 5 + 8 + 5 * 9 + 3);
+}
+
+#[test]
+fn nonblocking_nvm_write_budget() {
+    let count = transactions(|dev| {
+        let mut nvm = dev.unlock_nvm().unwrap();
+        let mut write = nvm.start_write_sectors(DEFAULT_NVM_DATA).unwrap();
+        while let Err(err) = write.poll() {
+            assert!(matches!(err, nb::Error::WouldBlock));
+        }
+        // Finished writes stay finished
+        assert!(write.poll().is_ok());
+        nvm.lock().unwrap();
+    });
+    // Same as the blocking write
+    assert_eq!(count, 5 + 8 + 5 * 9 + 3);
+}
+
+#[test]
+fn stuck_nonblocking_nvm_write() {
+    use stusb4500::nvm::NVM_POLL_LIMIT;
+
+    let mut bus = CountingBus::new();
+    bus.nvm_stuck = true;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    let mut nvm = dev.unlock_nvm().unwrap();
+    let mut write = nvm.start_write_sectors(DEFAULT_NVM_DATA).unwrap();
+    for _ in 0..NVM_POLL_LIMIT {
+        assert!(matches!(write.poll(), Err(nb::Error::WouldBlock)));
+    }
+    assert!(matches!(
+        write.poll(),
+        Err(nb::Error::Other(Error::NvmTimeout))
+    ));
+    nvm.lock().unwrap();
+}
+
+#[test]
+fn unchanged_nvm_write_budget() {
+    let count = transactions(|dev| {
+        let mut nvm = dev.unlock_nvm().unwrap();
+        let report = nvm.write_changed_sectors([[0; 8]; 5], true).unwrap();
+        assert_eq!(report, NvmReport::UNCHANGED);
+        nvm.lock().unwrap();
+    });
+    // Unlock 5, 6 per sector read, lock 3
+    assert_eq!(count, 5 + 5 * 6 + 3);
+}
+
+#[test]
+fn changed_nvm_write_report() {
+    let mut sectors = [[0; 8]; 5];
+    sectors[1] = DEFAULT_NVM_DATA[1];
+    let count = transactions(|dev| {
+        let mut nvm = dev.unlock_nvm().unwrap();
+        let report = nvm.write_changed_sectors(sectors, false).unwrap();
+        assert!(report.changed());
+        for (i, sector) in report.0.iter().enumerate() {
+            assert_eq!(sector.skipped, i != 1);
+            assert_eq!(sector.erased && sector.written, i == 1);
+            assert!(!sector.verified);
+        }
+        assert_eq!(report.0[1].to_string(), "erased, written");
+        assert_eq!(report.0[0].to_string(), "skipped (identical)");
+        nvm.lock().unwrap();
+    });
+    // Unlock 5, 6 per sector read, erase 8, 9 for the changed sector, lock 3
+    assert_eq!(count, 5 + 5 * 6 + 8 + 9 + 3);
+}
+
+#[test]
+fn image_source_at_boot() {
+    use stusb4500::image::ShortImage;
+    use stusb4500::nvm::SourceError;
+
+    // Same as the NVM, only read back
+    let stored = [0; 40];
+    let count = transactions(|dev| {
+        let report = dev.write_image_from(&mut &stored[..]).unwrap();
+        assert_eq!(report, Some(NvmReport::UNCHANGED));
+    });
+    assert_eq!(count, 5 + 5 * 6 + 3);
+
+    // Erased flash holds no image
+    let erased = [0xFF; 64];
+    let count = transactions(|dev| {
+        assert_eq!(dev.write_image_from(&mut &erased[..]).unwrap(), None);
+    });
+    assert_eq!(count, 0);
+
+    let count = transactions(|dev| {
+        assert!(matches!(
+            dev.write_image_from(&mut &stored[..8]),
+            Err(SourceError::Source(ShortImage(8)))
+        ));
+    });
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn unplug_during_nvm_write() {
+    let mut bus = CountingBus::new();
+    // Unlock 5, erase 8, two sectors of 9
+    bus.reset_after = Some(5 + 8 + 2 * 9);
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    let mut nvm = dev.unlock_nvm().unwrap();
+    assert!(matches!(
+        nvm.write_sectors(DEFAULT_NVM_DATA),
+        Err(Error::InterruptedProgramming(2))
+    ));
+    // The stale session is locked instead of failing with NvmBusy
+    assert_eq!(dev.get_num_pdo().unwrap(), 1);
+    assert!(!dev.nvm_is_unlocked().unwrap());
+}
+
+#[test]
+fn atomic_nvm_update_rolls_back() {
+    // The bus doesn't store NVM data, so sector 0 reads back as zeros like the old image
+    let count = transactions(|dev| {
+        let mut nvm = dev.unlock_nvm().unwrap();
+        assert!(matches!(
+            nvm.update_sectors_atomic(DEFAULT_NVM_DATA),
+            Err(Error::RolledBack(0))
+        ));
+        nvm.lock().unwrap();
+    });
+    // Unlock 5, read the old image, erase 8, write 9 per sector, verify sector 0, erase and
+    // write again, verify all, lock 3
+    assert_eq!(count, 5 + 5 * 6 + (8 + 5 * 9 + 6) + (8 + 5 * 9 + 5 * 6) + 3);
+}
+
+#[test]
+fn locked_nvm_is_reported() {
+    let mut bus = CountingBus::new();
+    bus.nvm_locked = true;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert!(matches!(dev.unlock_nvm(), Err(Error::NvmLocked)));
+    // The driver isn't left in NVM mode
+    assert_eq!(dev.get_voltage_mv().unwrap(), 5000);
+}
+
+#[test]
+fn status_snapshot_budget() {
+    let count = transactions(|dev| {
+        dev.get_alerts().unwrap();
+        dev.get_voltage_mv().unwrap();
+        dev.get_current_rdo().unwrap();
+        dev.get_pdo(PdoChannel::PDO1).unwrap();
+    });
+    assert_eq!(count, 4 * 2);
+}
+
+#[test]
+fn status_view_matches_driver() {
+    use stusb4500::status::StatusView;
+
+    let mut bus = CountingBus::new();
+    bus.regs[Register::PortStatus1 as usize] = 0x21;
+    bus.regs[Register::TypeCMonitoringStatus1 as usize] = 0x04;
+    let mirror = bus.regs;
+    assert!(StatusView::from_slice(&mirror[..StatusView::LEN - 1]).is_none());
+    let view = StatusView::from_slice(&mirror).unwrap();
+
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert_eq!(view.is_attached(), dev.is_attached().unwrap());
+    assert_eq!(view.device_mode(), dev.device_mode().unwrap());
+    assert_eq!(view.monitoring_status(), dev.monitoring_status().unwrap());
+    assert_eq!(view.pe_state(), dev.pe_state().unwrap());
+    assert_eq!(view.typec_state(), dev.typec_state().unwrap());
+    assert_eq!(view.num_pdo(), dev.get_num_pdo().unwrap());
+    assert_eq!(
+        view.pdo(PdoChannel::PDO1),
+        Some(dev.get_pdo(PdoChannel::PDO1).unwrap())
+    );
+    assert_eq!(view.active_contract(), Some((5000, 3000)));
+    assert_eq!(view.active_contract(), dev.get_active_contract().unwrap());
+    assert_eq!(view.contract_state(), dev.contract_state().unwrap());
+    assert_eq!(view.alert_snapshot(), dev.alert_snapshot().unwrap());
+}
+
+#[test]
+fn faults_budget() {
+    use stusb4500::status::Faults;
+
+    // Detached, VBUS low is not a fault
+    let mut bus = CountingBus::new();
+    bus.regs[Register::TypeCMonitoringStatus0 as usize] = 0x10;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert_eq!(dev.faults().unwrap(), Faults::empty());
+    // A single burst read
+    assert_eq!(bus.transactions, 2);
+
+    bus.regs[Register::PortStatus1 as usize] = 0x01;
+    bus.regs[Register::TypeCMonitoringStatus0 as usize] = 0x20;
+    bus.regs[Register::CCHWFaultStatus1 as usize] = 0x80 | 0x10;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert_eq!(
+        dev.faults().unwrap(),
+        Faults::CcOverVoltage | Faults::VbusDischarge | Faults::VbusOverVoltage
+    );
+}
+
+#[test]
+fn active_contract_budget() {
+    let count = transactions(|dev| {
+        assert_eq!(dev.get_active_contract().unwrap(), Some((5000, 3000)));
+    });
+    // RDO and VBUS
+    assert_eq!(count, 2 * 2);
+
+    let mut bus = CountingBus::new();
+    bus.regs[Register::RDORegStatus as usize + 3] = 0;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert_eq!(dev.get_active_contract().unwrap(), None);
+    // RDO only
+    assert_eq!(bus.transactions, 2);
+}
+
+#[test]
+fn negotiation_budget() {
+    // Contract already matches, nothing is reset
+    let count = transactions(|dev| {
+        let summary = dev
+            .negotiate_with_retry(&mut NoDelay, RetryPolicy::default())
+            .unwrap();
+        assert!(summary.is_success());
+    });
+    // RDO, PDO count, PDO and VBUS
+    assert_eq!(count, 4 * 2);
+
+    // Contract never matches, every attempt soft resets and checks again
+    let count = transactions(|dev| {
+        dev.set_num_pdo(2).unwrap();
+        let summary = dev
+            .negotiate_with_retry(&mut NoDelay, RetryPolicy::default())
+            .unwrap();
+        assert_eq!(summary.attempts, 3);
+    });
+    // PDO count, first check, then a 2 write soft reset and another check per attempt
+    assert_eq!(count, 1 + 8 + 3 * (2 + 8));
+}
+
+#[test]
+fn configure_sink_budget() {
+    let mut bus = CountingBus::new();
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    dev.configure_sink(9_000, 3_000).unwrap();
+    assert_eq!(
+        dev.get_pdo(PdoChannel::PDO2).unwrap(),
+        stusb4500::pdo::Pdo::new_fixed(180, 300)
+    );
+    assert!(matches!(
+        dev.configure_sink(21_000, 3_000),
+        Err(Error::OutaRangePdo)
+    ));
+    // PDO2, PDO count and a 2 write soft reset, then the PDO2 read
+    assert_eq!(bus.transactions, 1 + 1 + 2 + 2);
+}
+
+#[test]
+fn apply_profile_budget() {
+    use stusb4500::pdo::{Pdo, PdoSetError};
+    use stusb4500::profile::Profile;
+
+    let dock = Profile::new(
+        "desk-dock 20V",
+        &[Pdo::new_fixed(100, 300), Pdo::new_fixed(400, 225)],
+    )
+    .unwrap()
+    .with_discharge_on_disconnect(true);
+    assert_eq!(
+        Profile::new("no 5V", &[Pdo::new_fixed(180, 300)]),
+        Err(PdoSetError::FirstNot5V)
+    );
+
+    let mut bus = CountingBus::new();
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    dev.apply_profile(&dock).unwrap();
+    assert_eq!(dev.get_num_pdo().unwrap(), 2);
+    assert_eq!(dev.get_pdo(PdoChannel::PDO2).unwrap(), dock.pdos()[1]);
+    // Two PDOs, PDO count, discharge read-modify-write and a 2 write soft reset, then the
+    // count and PDO2 reads
+    assert_eq!(bus.transactions, 2 + 1 + 3 + 2 + 2 + 2);
+}
+
+#[test]
+fn five_volt_only_budget() {
+    let mut bus = CountingBus::new();
+    bus.regs[Register::DPMPDONumb as usize] = 3;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    dev.set_five_volt_only().unwrap();
+    assert!(dev.is_five_volt_only().unwrap());
+    // PDO count and a 2 write soft reset, then the count read
+    assert_eq!(bus.transactions, 1 + 2 + 2);
+}
+
+#[test]
+fn runtime_config_survives_reset() {
+    use stusb4500::pdo::Pdo;
+    use stusb4500::registers::AlertMask;
+
+    let mut bus = CountingBus::new();
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    dev.set_pdo(PdoChannel::PDO2, &Pdo::new_fixed(180, 200))
+        .unwrap();
+    dev.set_num_pdo(2).unwrap();
+    dev.set_alerts_mask(AlertMask::PortStatus).unwrap();
+    dev.set_discharge_on_disconnect(false).unwrap();
+    bus.regs[Register::MonitoringCtrl2 as usize] = 0x55;
+    let configured = bus.regs;
+
+    bus.transactions = 0;
+    let saved = STUSB4500::new(&mut bus, Address::Default)
+        .save_runtime_config()
+        .unwrap();
+    assert_eq!(bus.transactions, 5 * 2);
+    assert_eq!(saved.pdo_count, 2);
+    assert_eq!(saved.alerts_mask, AlertMask::PortStatus);
+
+    // Chip reset back to the NVM settings
+    let fresh = CountingBus::new();
+    for register in [
+        Register::DPMPDONumb,
+        Register::AlertStatus1Mask,
+        Register::MonitoringCtrl2,
+        Register::VbusDischargeCtrl,
+    ] {
+        bus.regs[register as usize] = fresh.regs[register as usize];
+    }
+    let pdo2 = Register::DPMSNKPDO2 as usize;
+    bus.regs[pdo2..pdo2 + 4].fill(0);
+
+    bus.transactions = 0;
+    STUSB4500::new(&mut bus, Address::Default)
+        .restore_runtime_config(&saved)
+        .unwrap();
+    assert_eq!(bus.transactions, 8);
+    assert_eq!(bus.regs, configured);
+}
+
+#[test]
+fn contract_watcher_reports_changes() {
+    use stusb4500::negotiate::{ContractValues, ContractWatcher};
+
+    let mut watcher = ContractWatcher::new();
+    let mut bus = CountingBus::new();
+    bus.regs[Register::PortStatus1 as usize] = 0x01;
+    let first = watcher
+        .update(&mut STUSB4500::new(&mut bus, Address::Default))
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.old, None);
+    assert!(first.voltage_changed());
+    let unchanged = watcher
+        .update(&mut STUSB4500::new(&mut bus, Address::Default))
+        .unwrap();
+    assert_eq!(unchanged, None);
+
+    // Same voltage, less current
+    let rdo = Register::RDORegStatus as usize;
+    bus.regs[rdo..rdo + 4].copy_from_slice(&(1u32 << 28 | 150 << 10 | 150).to_le_bytes());
+    bus.transactions = 0;
+    let change = watcher
+        .update(&mut STUSB4500::new(&mut bus, Address::Default))
+        .unwrap()
+        .unwrap();
+    assert!(!change.voltage_changed());
+    assert_eq!(
+        change.new,
+        Some(ContractValues {
+            voltage_mv: 5000,
+            current_ma: 1500
+        })
+    );
+    // Attach, PE state, RDO and VBUS
+    assert_eq!(bus.transactions, 4 * 2);
+
+    // Source detached
+    bus.regs[Register::PortStatus1 as usize] = 0x00;
+    let change = watcher
+        .update(&mut STUSB4500::new(&mut bus, Address::Default))
+        .unwrap()
+        .unwrap();
+    assert!(change.voltage_changed());
+    assert_eq!(change.new, None);
+    assert_eq!(watcher.contract(), None);
+}
+
+#[test]
+fn contract_state_during_renegotiation() {
+    use stusb4500::negotiate::{ContractValues, ContractWatcher};
+    use stusb4500::status::ContractState;
+
+    let mut watcher = ContractWatcher::new();
+    let mut bus = CountingBus::new();
+    bus.regs[Register::PortStatus1 as usize] = 0x01;
+    watcher
+        .update(&mut STUSB4500::new(&mut bus, Address::Default))
+        .unwrap();
+
+    // Soft reset towards 9V, VBUS already moved while the RDO is still the 5V one
+    bus.regs[Register::PEFSM as usize] = 0x17;
+    bus.regs[Register::MonitoringCtrl1 as usize] = 90;
+    bus.transactions = 0;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert_eq!(dev.contract_state().unwrap(), ContractState::Negotiating);
+    // Neither RDO nor VBUS are read
+    assert_eq!(bus.transactions, 2 * 2);
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert_eq!(watcher.update(&mut dev).unwrap(), None);
+
+    // SNK_READY before the RDO is latched
+    bus.regs[Register::PEFSM as usize] = 0x18;
+    let rdo = Register::RDORegStatus as usize;
+    bus.regs[rdo..rdo + 4].fill(0);
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert_eq!(watcher.update(&mut dev).unwrap(), None);
+    assert_eq!(
+        watcher.contract(),
+        Some(ContractValues {
+            voltage_mv: 5000,
+            current_ma: 3000
+        })
+    );
+
+    bus.regs[rdo..rdo + 4].copy_from_slice(&(2u32 << 28 | 200 << 10 | 200).to_le_bytes());
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    let change = watcher.update(&mut dev).unwrap().unwrap();
+    assert!(change.voltage_changed());
+    assert_eq!(
+        change.new,
+        Some(ContractValues {
+            voltage_mv: 9000,
+            current_ma: 2000
+        })
+    );
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    let state = dev.contract_state().unwrap();
+    assert_eq!(state.contract().map(|c| c.current_ma()), Some(2000));
+
+    // Idle policy engine, e.g. a source without PD
+    bus.regs[Register::PEFSM as usize] = 0x00;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert_eq!(dev.contract_state().unwrap(), ContractState::NoContract);
+    bus.regs[Register::PortStatus1 as usize] = 0x00;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert_eq!(dev.contract_state().unwrap(), ContractState::Detached);
+}
+
+#[test]
+fn wait_for_voltage_budget() {
+    let mut bus = CountingBus::new();
+    bus.regs[Register::TypeCMonitoringStatus1 as usize] = 0x04;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert_eq!(
+        dev.wait_for_voltage(5000, 250, PollPolicy::timeout(100))
+            .unwrap(),
+        5000
+    );
+    // VBUS and monitoring status
+    assert_eq!(bus.transactions, 2 * 2);
+
+    // Never settles, 10 polls and a last check
+    let count = transactions(|dev| {
+        assert!(matches!(
+            dev.wait_for_voltage(9000, 450, PollPolicy::timeout(100)),
+            Err(Error::Timeout)
+        ));
+    });
+    assert_eq!(count, 11 * 2);
+
+    // A coarser interval trades latency for bus load, 4 polls and a last check at the deadline
+    let count = transactions(|dev| {
+        assert!(matches!(
+            dev.wait_for_voltage(9000, 450, PollPolicy::new(30, 100)),
+            Err(Error::Timeout)
+        ));
+    });
+    assert_eq!(count, 5 * 2);
+}
+
+#[test]
+fn current_limit_follows_contract() {
+    use stusb4500::charger::{CurrentLimiter, InputCurrentLimit};
+
+    struct Charger(Vec<u32>);
+
+    impl InputCurrentLimit for Charger {
+        type Error = Infallible;
+
+        fn set_input_current_limit(&mut self, limit_ma: u32) -> Result<(), Infallible> {
+            self.0.push(limit_ma);
+            Ok(())
+        }
+    }
+
+    let mut bus = CountingBus::new();
+    bus.regs[Register::PortStatus1 as usize] = 0x01;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    let mut charger = Charger(Vec::new());
+    let mut limiter = CurrentLimiter::new(250);
+
+    // 3A PD contract
+    assert_eq!(limiter.update(&mut dev, &mut charger).unwrap(), Some(3000));
+    assert_eq!(limiter.update(&mut dev, &mut charger).unwrap(), None);
+    // Decreases apply right away, small increases wait for the hysteresis
+    assert_eq!(limiter.apply(1500, &mut charger).unwrap(), Some(1500));
+    assert_eq!(limiter.apply(1600, &mut charger).unwrap(), None);
+    assert_eq!(limiter.apply(1750, &mut charger).unwrap(), Some(1750));
+    assert_eq!(charger.0, [3000, 1500, 1750]);
+    assert_eq!(limiter.applied_ma(), Some(1750));
+}
+
+#[test]
+fn read_only_never_writes() {
+    let mut bus = CountingBus::new();
+    let mut dev = STUSB4500::builder(&mut bus).read_only().build();
+    assert!(dev.is_read_only());
+    assert_eq!(dev.get_num_pdo().unwrap(), 1);
+    assert!(matches!(dev.set_num_pdo(2), Err(Error::ReadOnly)));
+    assert!(matches!(dev.soft_reset(), Err(Error::ReadOnly)));
+    assert!(matches!(dev.unlock_nvm(), Err(Error::ReadOnly)));
+    // Only the PDO count read reached the bus
+    assert_eq!(bus.transactions, 2);
+}
+
+#[test]
+fn send_command_budget() {
+    use stusb4500::message::{ControlMessage, MessageType};
+    use stusb4500::NoDelay;
+
+    let reply = |reply: ControlMessage| {
+        let mut bus = CountingBus::new();
+        bus.regs[Register::PRTStatus as usize] = 0x04;
+        let header = Register::RXHeaderL as usize;
+        bus.regs[header..header + 2].copy_from_slice(&u16::from(u8::from(reply)).to_le_bytes());
+        bus
+    };
+
+    let mut bus = reply(ControlMessage::Accept);
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert_eq!(
+        dev.send_command(
+            &mut NoDelay,
+            ControlMessage::SoftReset,
+            PollPolicy::new(1, 30)
+        )
+        .unwrap(),
+        MessageType::Control(ControlMessage::Accept)
+    );
+    // Clear PRT_STATUS, TX_HEADER and PD_COMMAND_CTRL, then PRT_STATUS and RX_HEADER once
+    assert_eq!(bus.transactions, 2 + 1 + 1 + 2 + 2);
+
+    let mut bus = reply(ControlMessage::Reject);
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert!(matches!(
+        dev.send_command(
+            &mut NoDelay,
+            ControlMessage::SoftReset,
+            PollPolicy::new(1, 30)
+        ),
+        Err(Error::CommandRejected(ControlMessage::Reject))
+    ));
+
+    // Nothing received, PRT_STATUS is polled for 5ms
+    let mut bus = CountingBus::new();
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert!(matches!(
+        dev.send_command(
+            &mut NoDelay,
+            ControlMessage::GetSourceCap,
+            PollPolicy::new(1, 5)
+        ),
+        Err(Error::Timeout)
+    ));
+    assert_eq!(bus.transactions, 2 + 1 + 1 + 6 * 2);
+}
+
+#[test]
+fn rx_buffer_budget() {
+    use stusb4500::message::{DataMessage, MessageType};
+
+    let mut bus = CountingBus::new();
+    // Source_Capabilities with two PDOs
+    let header = Register::RXHeaderL as usize;
+    bus.regs[header..header + 2].copy_from_slice(&(2u16 << 12 | 0x01).to_le_bytes());
+    let data = Register::RXDataObj as usize;
+    bus.regs[data..data + 4].copy_from_slice(&0x0801_912Cu32.to_le_bytes());
+    bus.regs[data + 4..data + 8].copy_from_slice(&0x0002_D12Cu32.to_le_bytes());
+    bus.regs[data + 8] = 0xFF;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+
+    let message = dev.read_rx_buffer().unwrap();
+    assert_eq!(
+        message.message_type(),
+        MessageType::Data(DataMessage::SourceCapabilities)
+    );
+    assert_eq!(message.objects(), [0x0801_912C, 0x0002_D12C]);
+    // A single burst read
+    assert_eq!(bus.transactions, 2);
+}
+
+#[test]
+fn source_capabilities_revision() {
+    use stusb4500::message::SpecRevision;
+    use stusb4500::NoDelay;
+
+    let caps = |revision: u16| {
+        let mut bus = CountingBus::new();
+        bus.regs[Register::PRTStatus as usize] = 0x04;
+        // Source_Capabilities with 5V 3A and a 3.3-11V 3A PPS APDO
+        let header = Register::RXHeaderL as usize;
+        bus.regs[header..header + 2]
+            .copy_from_slice(&(2u16 << 12 | revision << 6 | 0x01).to_le_bytes());
+        let data = Register::RXDataObj as usize;
+        let pps = 0b11u32 << 30 | 110 << 17 | 33 << 8 | 60;
+        bus.regs[data..data + 4].copy_from_slice(&0x0801_912Cu32.to_le_bytes());
+        bus.regs[data + 4..data + 8].copy_from_slice(&pps.to_le_bytes());
+        let mut dev = STUSB4500::new(&mut bus, Address::Default);
+        dev.request_source_capabilities(&mut NoDelay, PollPolicy::new(1, 30))
+            .unwrap()
+    };
+
+    let pd3 = caps(2);
+    assert_eq!(pd3.revision(), SpecRevision::Rev30);
+    assert_eq!(pd3.len(), 2);
+    assert!(pd3.supports_pps());
+    // PD 2.0 sources can't be asked for PPS
+    let pd2 = caps(1);
+    assert_eq!(pd2.revision(), SpecRevision::Rev20);
+    assert!(!pd2.supports_pps());
+}
+
+#[test]
+fn source_capabilities_rejected() {
+    use stusb4500::message::ControlMessage;
+    use stusb4500::NoDelay;
+
+    let mut bus = CountingBus::new();
+    bus.regs[Register::PRTStatus as usize] = 0x04;
+    let header = Register::RXHeaderL as usize;
+    bus.regs[header..header + 2]
+        .copy_from_slice(&u16::from(u8::from(ControlMessage::NotSupported)).to_le_bytes());
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert!(matches!(
+        dev.request_source_capabilities(&mut NoDelay, PollPolicy::new(1, 30)),
+        Err(Error::CommandRejected(ControlMessage::NotSupported))
+    ));
+}
+
+#[test]
+fn alert_snapshot_budget() {
+    use stusb4500::registers::Alert;
+
+    let mut bus = CountingBus::new();
+    bus.regs[Register::AlertStatus1 as usize] = (Alert::PortStatus | Alert::PRTStatus).bits();
+    bus.regs[Register::AlertStatus1Mask as usize] = Alert::PRTStatus.bits();
+    bus.regs[Register::PortStatus1 as usize] = 0x21;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    let snapshot = dev.alert_snapshot().unwrap();
+    // The raw value keeps the masked alert
+    assert_eq!(
+        snapshot.raw[0],
+        (Alert::PortStatus | Alert::PRTStatus).bits()
+    );
+    assert_eq!(snapshot.alerts, Alert::PortStatus);
+    assert!(snapshot.attached);
+    // A single burst read
+    assert_eq!(bus.transactions, 2);
+}
+
+#[cfg(feature = "heapless")]
+#[test]
+fn alert_service_budget() {
+    use stusb4500::events::{self, Event};
+    use stusb4500::registers::Alert;
+
+    let mut queue = heapless::spsc::Queue::<Event, 2>::new();
+    let (mut producer, consumer) = events::split(&mut queue);
+
+    let mut bus = CountingBus::new();
+    bus.regs[Register::AlertStatus1 as usize] = (Alert::PortStatus | Alert::PRTStatus).bits();
+    bus.regs[Register::PortStatus1 as usize] = 0x01;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    // Only one slot, the protocol and contract events are dropped
+    assert_eq!(producer.service(&mut dev).unwrap(), 1);
+    assert_eq!(producer.dropped(), 2);
+    // A single burst read, then PE state, RDO and VBUS for the contract
+    assert_eq!(bus.transactions, 2 + 3 * 2);
+
+    assert_eq!(consumer.collect::<Vec<_>>(), [Event::Attached]);
+}
+
+#[cfg(feature = "heapless")]
+#[test]
+fn alert_service_contract_change() {
+    use stusb4500::events::{self, Event};
+    use stusb4500::negotiate::{ContractChange, ContractValues};
+    use stusb4500::registers::Alert;
+
+    let mut queue = heapless::spsc::Queue::<Event, 4>::new();
+    let (mut producer, mut consumer) = events::split(&mut queue);
+
+    let mut bus = CountingBus::new();
+    bus.regs[Register::AlertStatus1 as usize] = Alert::PRTStatus.bits();
+    bus.regs[Register::PortStatus1 as usize] = 0x01;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    producer.service(&mut dev).unwrap();
+    consumer.by_ref().for_each(drop);
+    // Unchanged contract, no event
+    assert_eq!(producer.service(&mut dev).unwrap(), 1);
+    assert!(matches!(consumer.next(), Some(Event::Protocol(_))));
+    assert_eq!(consumer.next(), None);
+
+    // Source moved to 9V
+    bus.regs[Register::MonitoringCtrl1 as usize] = 90;
+    bus.regs[Register::AlertStatus1 as usize] = Alert::PRTStatus.bits();
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    assert_eq!(producer.service(&mut dev).unwrap(), 2);
+    assert!(matches!(consumer.next(), Some(Event::Protocol(_))));
+    assert_eq!(
+        consumer.next(),
+        Some(Event::ContractChanged(ContractChange {
+            old: Some(ContractValues {
+                voltage_mv: 5000,
+                current_ma: 3000
+            }),
+            new: Some(ContractValues {
+                voltage_mv: 9000,
+                current_ma: 3000
+            }),
+        }))
+    );
+}
+
+#[cfg(feature = "heapless")]
+#[test]
+fn alert_service_faults() {
+    use stusb4500::events::{self, Event};
+    use stusb4500::registers::Alert;
+    use stusb4500::status::Faults;
+
+    let mut queue = heapless::spsc::Queue::<Event, 4>::new();
+    let (mut producer, consumer) = events::split(&mut queue);
+
+    let mut bus = CountingBus::new();
+    bus.regs[Register::AlertStatus1 as usize] = Alert::TypeCMonitoringStatus.bits();
+    bus.regs[Register::PortStatus1 as usize] = 0x01;
+    // VBUS collapsed under load
+    bus.regs[Register::TypeCMonitoringStatus0 as usize] = 0x10;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    // No contract read without an attach, Type-C or protocol alert
+    assert_eq!(producer.service(&mut dev).unwrap(), 2);
+    let events = consumer.collect::<Vec<_>>();
+    assert!(matches!(events[0], Event::Monitoring(status) if status.vbus_low));
+    assert_eq!(events[1], Event::Fault(Faults::VbusUnderVoltage));
+}
+
+#[test]
+fn superloop_service_rate_limit() {
+    use stusb4500::events::{AlertService, Event};
+    use stusb4500::registers::Alert;
+
+    let mut alerts = AlertService::new(50);
+    let mut bus = CountingBus::new();
+    bus.regs[Register::AlertStatus1 as usize] = Alert::PortStatus.bits();
+    bus.regs[Register::PortStatus1 as usize] = 0x01;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    let events = alerts.service(&mut dev, u32::MAX - 10).unwrap();
+    assert_eq!(events.len(), 2);
+    assert!(alerts.snapshot().unwrap().attached);
+    // Too early, even across the counter wrapping
+    assert!(alerts.service(&mut dev, 20).unwrap().is_empty());
+    // Burst read, then PE state, RDO and VBUS for the contract
+    assert_eq!(bus.transactions, 2 + 3 * 2);
+
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    let events = alerts.service(&mut dev, 39).unwrap();
+    // Contract unchanged since the first read
+    assert_eq!(events.collect::<Vec<_>>(), [Event::Attached]);
+    assert_eq!(bus.transactions, 2 * (2 + 3 * 2));
+}
+
+#[cfg(feature = "stats")]
+#[test]
+fn stats_count_transitions() {
+    let mut bus = CountingBus::new();
+    bus.regs[Register::PortStatus1 as usize] = 0x01;
+    let mut dev = STUSB4500::new(&mut bus, Address::Default);
+    dev.set_num_pdo(2).unwrap();
+    dev.negotiate_with_retry(&mut NoDelay, RetryPolicy::default())
+        .unwrap();
+    dev.is_attached().unwrap();
+    dev.is_attached().unwrap();
+
+    let stats = dev.stats();
+    assert_eq!(stats.soft_resets, 3);
+    // The contract stays up across the checks
+    assert_eq!(stats.contracts, 1);
+    assert_eq!(stats.attaches, 1);
+    assert_eq!(stats.hard_resets, 0);
+
+    dev.reset_stats();
+    dev.is_attached().unwrap();
+    assert_eq!(dev.stats().attaches, 0);
+}
+
+/// Fails every transaction while `stuck`, like a slave holding SDA low
+struct StuckBus {
+    inner: CountingBus,
+    stuck: bool,
+}
+
+impl ErrorType for StuckBus {
+    type Error = embedded_hal::i2c::ErrorKind;
+}
+
+impl I2c for StuckBus {
+    fn transaction(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        if self.stuck {
+            self.inner.transactions += 1;
+            return Err(embedded_hal::i2c::ErrorKind::Bus);
+        }
+        self.inner
+            .transaction(address, operations)
+            .map_err(|e| match e {})
+    }
+}
+
+#[test]
+fn bus_recovery_budget() {
+    let mut bus = StuckBus {
+        inner: CountingBus::new(),
+        stuck: true,
+    };
+    let mut dev = STUSB4500::builder(&mut bus)
+        .retries(1)
+        .bus_recovery(3, |bus| bus.stuck = false)
+        .build();
+    // Two failed attempts, the third failure in the next call triggers the recovery
+    assert!(matches!(
+        dev.get_voltage_mv(),
+        Err(Error::RetriesExhausted { .. })
+    ));
+    assert_eq!(dev.get_voltage_mv().unwrap(), 5000);
+    // Three failed, then a successful read
+    assert_eq!(bus.inner.transactions, 3 + 2);
+}
+
+#[test]
+fn group_ports_use_group_settings() {
+    use stusb4500::Stusb4500Group;
+
+    let mut bus = StuckBus {
+        inner: CountingBus::new(),
+        stuck: true,
+    };
+    let mut group = Stusb4500Group::new(&mut bus, [Address::Default])
+        .retries(1)
+        .bus_recovery(2, |bus| bus.stuck = false);
+    // Two failed attempts trigger the recovery, the retry after it succeeds
+    let [alerts] = group.poll_alerts();
+    assert!(alerts.is_ok());
+    assert!(!bus.stuck);
+
+    let mut bus = CountingBus::new();
+    let mut group = Stusb4500Group::new(&mut bus, [Address::Default; 2]).read_only();
+    for result in group.write_sectors_all(DEFAULT_NVM_DATA) {
+        assert!(matches!(result, Err(Error::ReadOnly)));
+    }
+    assert_eq!(bus.transactions, 0);
+}